@@ -0,0 +1,100 @@
+//! Spawn throughput of the different paths a future can take into an executor.
+//!
+//! Run with `cargo bench --features reference-executor`. Each case spawns a batch of futures and
+//! then runs them to completion, reporting the best time per spawn over several rounds:
+//!
+//! - `boxed`: a `LocalSpawner` over an executor shimmed with `impl_local_spawner!`, which boxes
+//!   every future before handing it over.
+//! - `two-phase`: a `LocalSpawner` over the reference executor, which writes the future straight
+//!   into the executor's task allocation.
+//! - `direct`: boxing the future and pushing it into the same queue the `boxed` shim uses,
+//!   without `ispawn` in between.
+
+use core::{
+    cell::RefCell,
+    future::Future,
+    hint::black_box,
+    pin::Pin,
+    task::{Context, Waker},
+};
+use ispawn::{LocalSpawner, reference::ReferenceExecutor};
+use std::{collections::VecDeque, rc::Rc, time::Instant};
+
+const BATCH: usize = 1_000;
+const ROUNDS: usize = 50;
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+#[derive(Clone, Default)]
+struct BoxQueue {
+    queue: Rc<RefCell<VecDeque<Task>>>,
+}
+
+impl BoxQueue {
+    fn run(&self) {
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            let Some(mut future) = self.queue.borrow_mut().pop_front() else {
+                return;
+            };
+            let _ = future.as_mut().poll(&mut cx);
+        }
+    }
+}
+
+ispawn::impl_local_spawner!(BoxQueue, |this, future| {
+    this.queue.borrow_mut().push_back(future);
+    Ok(())
+});
+
+fn bench(name: &str, size: &str, mut round: impl FnMut()) {
+    let mut best = u128::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        round();
+        best = best.min(start.elapsed().as_nanos());
+    }
+    println!(
+        "{name:>10} {size:>6}: {:>7.1} ns/spawn",
+        best as f64 / BATCH as f64
+    );
+}
+
+fn run<const N: usize>(size: &str) {
+    let future = || {
+        let payload = [0u8; N];
+        async move {
+            black_box(&payload);
+        }
+    };
+
+    let queue = BoxQueue::default();
+    let spawner = LocalSpawner::new(queue.clone());
+    bench("boxed", size, || {
+        for _ in 0..BATCH {
+            spawner.spawn(future()).unwrap();
+        }
+        queue.run();
+    });
+
+    let ex = ReferenceExecutor::new();
+    let spawner = ex.spawner();
+    bench("two-phase", size, || {
+        for _ in 0..BATCH {
+            spawner.spawn(future()).unwrap();
+        }
+        ex.run_until_stalled();
+    });
+
+    bench("direct", size, || {
+        for _ in 0..BATCH {
+            queue.queue.borrow_mut().push_back(Box::pin(future()));
+        }
+        queue.run();
+    });
+}
+
+fn main() {
+    run::<8>("8 B");
+    run::<1024>("1 KiB");
+}
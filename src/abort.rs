@@ -0,0 +1,232 @@
+use crate::{AbortTask, cancel::CancelSignal};
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+/// A handle that cancels a task spawned with
+/// [`LocalSpawner::spawn_abortable`](crate::LocalSpawner::spawn_abortable).
+///
+/// Dropping the handle does not abort the task.
+#[derive(Clone)]
+pub struct AbortHandle {
+    inner: Abort,
+}
+
+#[derive(Clone)]
+enum Abort {
+    Signal(CancelSignal),
+    /// Aborted by the executor itself, see [`Extensions::spawn_abortable`].
+    ///
+    /// [`Extensions::spawn_abortable`]: crate::Extensions::spawn_abortable
+    Native {
+        abort: AbortTask,
+        aborted: Rc<Cell<bool>>,
+    },
+}
+
+impl AbortHandle {
+    pub(crate) fn new(signal: CancelSignal) -> Self {
+        Self {
+            inner: Abort::Signal(signal),
+        }
+    }
+
+    pub(crate) fn native(abort: AbortTask) -> Self {
+        Self {
+            inner: Abort::Native {
+                abort,
+                aborted: Rc::new(Cell::new(false)),
+            },
+        }
+    }
+
+    /// Abort the task. Executors that can abort their tasks themselves drop it right away, or
+    /// once it's done being polled, others drop it the next time they poll it.
+    pub fn abort(&self) {
+        match &self.inner {
+            Abort::Signal(signal) => signal.cancel(),
+            Abort::Native { abort, aborted } => {
+                aborted.set(true);
+                abort();
+            }
+        }
+    }
+
+    /// Whether [`abort`](Self::abort) has been called.
+    pub fn is_aborted(&self) -> bool {
+        match &self.inner {
+            Abort::Signal(signal) => signal.is_cancelled(),
+            Abort::Native { aborted, .. } => aborted.get(),
+        }
+    }
+}
+
+/// Owns a task spawned with [`LocalSpawner::spawn_owned`](crate::LocalSpawner::spawn_owned),
+/// cancelling it when dropped so that e.g. a struct's background task can't outlive the struct.
+/// The task is dropped the next time its executor polls it.
+#[must_use = "the task is cancelled when the guard is dropped"]
+pub struct Task {
+    signal: Option<CancelSignal>,
+}
+
+impl Task {
+    pub(crate) fn new(signal: CancelSignal) -> Self {
+        Self {
+            signal: Some(signal),
+        }
+    }
+
+    /// Let the task run to completion on its own instead of cancelling it.
+    pub fn detach(mut self) {
+        self.signal = None;
+    }
+
+    /// Cancel the task now, the same as dropping the guard.
+    pub fn cancel(self) {}
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        if let Some(signal) = &self.signal {
+            signal.cancel();
+        }
+    }
+}
+
+/// A cooperative cancellation signal that can be shared by any number of tasks spawned with
+/// [`LocalSpawner::spawn_cancellable`](crate::LocalSpawner::spawn_cancellable), on any executor,
+/// including those that can't abort tasks themselves.
+///
+/// Clones share the signal.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    signal: CancelSignal,
+}
+
+impl CancelToken {
+    /// Create a new `CancelToken` that hasn't been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every task spawned with this token. Each is dropped the next time its executor polls
+    /// it, and tasks spawned with it afterwards are dropped before they ever run.
+    pub fn cancel(&self) {
+        self.signal.cancel();
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.signal.is_cancelled()
+    }
+
+    /// Resolves once the token is cancelled, e.g. to clean up before a task is dropped.
+    pub fn cancelled(&self) -> impl core::future::Future<Output = ()> + 'static {
+        let future = self.signal.wrap(core::future::pending::<()>());
+        async move {
+            future.await;
+        }
+    }
+
+    pub(crate) fn signal(&self) -> &CancelSignal {
+        &self.signal
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::CancelToken;
+    use crate::LocalSpawner;
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_abort() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+
+        let alive = Rc::new(Cell::new(()));
+        let alive2 = alive.clone();
+        let handle = spawner
+            .spawn_abortable(async move {
+                let _alive = alive2;
+                core::future::pending::<()>().await;
+            })
+            .unwrap();
+
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 2);
+        handle.abort();
+        ex.run_until_stalled();
+        assert!(handle.is_aborted());
+        assert_eq!(Rc::strong_count(&alive), 1);
+    }
+
+    #[test]
+    fn test_owned_task() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+
+        let alive = Rc::new(Cell::new(()));
+        let mut tasks = alloc::vec::Vec::new();
+        for _ in 0..2 {
+            let alive = alive.clone();
+            let task = spawner
+                .spawn_owned(async move {
+                    let _alive = alive;
+                    core::future::pending::<()>().await;
+                })
+                .unwrap();
+            tasks.push(task);
+        }
+
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 3);
+        tasks.pop().unwrap().detach();
+        drop(tasks);
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 2);
+    }
+
+    #[test]
+    fn test_cancel_token() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+        let token = CancelToken::new();
+
+        let alive = Rc::new(Cell::new(()));
+        for _ in 0..2 {
+            let alive = alive.clone();
+            spawner
+                .spawn_cancellable(&token, async move {
+                    let _alive = alive;
+                    core::future::pending::<()>().await;
+                })
+                .unwrap();
+        }
+        let cleaned_up = Rc::new(Cell::new(false));
+        let cleaned_up2 = cleaned_up.clone();
+        let cancelled = token.cancelled();
+        spawner
+            .spawn(async move {
+                cancelled.await;
+                cleaned_up2.set(true);
+            })
+            .unwrap();
+
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 3);
+        token.clone().cancel();
+        ex.run_until_stalled();
+        assert!(token.is_cancelled());
+        assert!(cleaned_up.get());
+        assert_eq!(Rc::strong_count(&alive), 1);
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        spawner
+            .spawn_cancellable(&token, async move { ran2.set(true) })
+            .unwrap();
+        ex.run_until_stalled();
+        assert!(!ran.get());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{LocalSpawner, Result, SpawnError};
+use core::{
+    alloc::Layout,
+    future::Future,
+    pin::Pin,
+    ptr::NonNull,
+    task::{Context, Poll},
+};
+
+/// An allocator for the memory holding spawned futures, installed on a `LocalSpawner` with
+/// [`LocalSpawner::with_alloc_hooks`].
+///
+/// ```
+/// # use core::alloc::{GlobalAlloc, Layout};
+/// # use std::alloc::System;
+/// static SYSTEM: ispawn::AllocHooks = ispawn::AllocHooks {
+///     alloc: |layout| unsafe { System.alloc(layout) },
+///     dealloc: |ptr, layout| unsafe { System.dealloc(ptr, layout) },
+/// };
+///
+/// fn install(spawner: ispawn::LocalSpawner) -> ispawn::LocalSpawner {
+///     spawner.with_alloc_hooks(&SYSTEM)
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct AllocHooks {
+    /// Allocate memory for `layout`. Returns null on failure.
+    ///
+    /// # Safety
+    ///
+    /// `layout` must have a non-zero size.
+    pub alloc: unsafe fn(layout: Layout) -> *mut u8,
+
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `alloc` for the same `layout`.
+    pub dealloc: unsafe fn(ptr: *mut u8, layout: Layout),
+}
+
+impl LocalSpawner {
+    /// Allocate spawned futures with `hooks` rather than leaving it to the executor.
+    ///
+    /// Each future is written to memory from `hooks`, and the executor only gets a small future
+    /// pointing to it, whose task it allocates as usual. Spawns fail with
+    /// `SpawnError::AllocFailed` if `hooks` can't allocate. The hooks are only preserved by
+    /// [`into_raw_parts`](Self::into_raw_parts) with the `alloc` feature.
+    pub fn with_alloc_hooks(mut self, hooks: &'static AllocHooks) -> Self {
+        self.alloc_hooks = Some(hooks);
+        self
+    }
+}
+
+/// A future stored in memory allocated by [`AllocHooks`], which it frees when dropped.
+pub(crate) struct HookedBox<F> {
+    ptr: NonNull<F>,
+    hooks: &'static AllocHooks,
+}
+
+impl<F> HookedBox<F> {
+    /// Allocate memory for an `F` with `hooks`, and move the result of `f` into it.
+    pub(crate) fn new(hooks: &'static AllocHooks, f: impl FnOnce() -> F) -> Result<Self> {
+        let layout = Layout::new::<F>();
+        if layout.size() == 0 {
+            let ptr = NonNull::dangling();
+            unsafe { ptr.write(f()) };
+            return Ok(Self { ptr, hooks });
+        }
+
+        let ptr = unsafe { (hooks.alloc)(layout) } as *mut F;
+        let ptr = NonNull::new(ptr).ok_or(SpawnError::AllocFailed)?;
+        // Free the memory again if `f` panics.
+        let guard = Dealloc { ptr, hooks };
+        unsafe { ptr.write(f()) };
+        core::mem::forget(guard);
+        Ok(Self { ptr, hooks })
+    }
+}
+
+impl<F: Future> Future for HookedBox<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // Safety: the future lives at a fixed address until `self` is dropped.
+        unsafe { Pin::new_unchecked(&mut *self.ptr.as_ptr()) }.poll(cx)
+    }
+}
+
+impl<F> Drop for HookedBox<F> {
+    fn drop(&mut self) {
+        let _dealloc = Dealloc {
+            ptr: self.ptr,
+            hooks: self.hooks,
+        };
+        unsafe { self.ptr.drop_in_place() };
+    }
+}
+
+/// Frees the memory behind `ptr` when dropped, without dropping the `F` in it.
+struct Dealloc<F> {
+    ptr: NonNull<F>,
+    hooks: &'static AllocHooks,
+}
+
+impl<F> Drop for Dealloc<F> {
+    fn drop(&mut self) {
+        let layout = Layout::new::<F>();
+        if layout.size() != 0 {
+            unsafe { (self.hooks.dealloc)(self.ptr.as_ptr() as *mut u8, layout) }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "reference-executor"))]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::{
+        cell::Cell,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+    static COUNTING: AllocHooks = AllocHooks {
+        alloc: |layout| {
+            LIVE.fetch_add(1, Ordering::Relaxed);
+            unsafe { alloc::alloc::alloc(layout) }
+        },
+        dealloc: |ptr, layout| {
+            LIVE.fetch_sub(1, Ordering::Relaxed);
+            unsafe { alloc::alloc::dealloc(ptr, layout) }
+        },
+    };
+
+    #[test]
+    fn test_futures_are_allocated_with_hooks() {
+        let ex = crate::reference::ReferenceExecutor::new();
+        let spawner = ex.spawner().with_alloc_hooks(&COUNTING);
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        let state = [0u8; 64];
+        spawner
+            .spawn(async move {
+                let _ = &state;
+                ran2.set(true);
+            })
+            .unwrap();
+        assert_eq!(LIVE.load(Ordering::Relaxed), 1);
+
+        ex.run_until_stalled();
+        assert!(ran.get());
+        assert_eq!(LIVE.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,27 @@
+use crate::{LocalSpawner, Result, SpawnError};
+
+impl LocalSpawner {
+    /// Detect the executor the caller runs on, so glue code works across environments without
+    /// being handed a spawner. This is best-effort, checking in order for:
+    ///
+    /// - With the `dioxus` feature, a current Dioxus scope, see
+    ///   [`DioxusSpawner::current`](crate::DioxusSpawner::current).
+    /// - With the `wasm-bindgen` feature, targeting `wasm32`, the JavaScript event loop through
+    ///   [`WasmBindgenSpawner`](crate::WasmBindgenSpawner).
+    ///
+    /// Fails with `SpawnError::Unsupported` if none of them is found. Tokio isn't detected: it
+    /// doesn't expose whether a `LocalSet` or `LocalRuntime` is current, without which
+    /// `tokio::task::spawn_local` panics, so hand its `LocalSet` over explicitly instead.
+    pub fn from_current_context() -> Result<LocalSpawner> {
+        #[cfg(feature = "dioxus")]
+        if let Ok(spawner) = crate::DioxusSpawner::current() {
+            return Ok(LocalSpawner::new(spawner));
+        }
+
+        #[cfg(all(feature = "wasm-bindgen", target_arch = "wasm32"))]
+        return Ok(LocalSpawner::new(crate::WasmBindgenSpawner));
+
+        #[cfg(not(all(feature = "wasm-bindgen", target_arch = "wasm32")))]
+        Err(SpawnError::Unsupported)
+    }
+}
@@ -1,44 +1,286 @@
-use crate::{IntoLocalSpawner, Result, SpawnCompleter, SpawnCompleterBuilder};
-use alloc::{alloc::Layout, boxed::Box, rc::Rc};
+use crate::{
+    DowncastLocalSpawner, Extensions, IntoLocalSpawner, IntoSpawner, Result, ScopedLocalSpawner,
+    SendSpawnCompleter, SendSpawnSlot, SpawnCompleter, SpawnSlot, TaskMeta, WeakOps,
+};
+use alloc::{alloc::Layout, boxed::Box, rc::Rc, sync::Arc};
 use core::future::Future;
 
-impl IntoLocalSpawner for Rc<async_executor::LocalExecutor<'static>> {
+crate::impl_local_spawner!(
+    Rc<async_executor::LocalExecutor<'static>>,
+    |this, future| {
+        this.spawn(future).detach();
+        Ok(())
+    },
+    extensions = Extensions::NONE
+        .with_spawn_inline(|handle, future, _| {
+            let this = unsafe { &*(handle as *const async_executor::LocalExecutor<'static>) };
+            this.spawn(future).detach();
+            Ok(())
+        })
+        .with_spawn_abortable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const async_executor::LocalExecutor<'static>) };
+            Ok(abort_task(this.spawn(future)))
+        })
+        .with_spawn_joinable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const async_executor::LocalExecutor<'static>) };
+            Ok(join_task(this.spawn(future)))
+        }),
+);
+
+/// Aborts a task by dropping its `async_executor::Task`, which cancels it. The task is detached
+/// instead once the `AbortHandle` is dropped, so that it keeps running.
+fn abort_task(task: async_executor::Task<()>) -> crate::AbortTask {
+    struct Abort(core::cell::RefCell<Option<async_executor::Task<()>>>);
+
+    impl Drop for Abort {
+        fn drop(&mut self) {
+            if let Some(task) = self.0.get_mut().take() {
+                task.detach();
+            }
+        }
+    }
+
+    let abort = Abort(core::cell::RefCell::new(Some(task)));
+    Rc::new(move || drop(abort.0.borrow_mut().take()))
+}
+
+/// Hands a task's output back through its `async_executor::Task`, detaching the task instead of
+/// cancelling it once the `JoinHandle` is dropped.
+fn join_task(task: async_executor::Task<crate::TaskOutput>) -> crate::JoinTask {
+    struct Join(Option<async_executor::FallibleTask<crate::TaskOutput>>);
+
+    impl Future for Join {
+        type Output = core::result::Result<crate::TaskOutput, crate::JoinError>;
+
+        fn poll(
+            mut self: core::pin::Pin<&mut Self>,
+            cx: &mut core::task::Context<'_>,
+        ) -> core::task::Poll<Self::Output> {
+            let task = self
+                .0
+                .as_mut()
+                .expect("`JoinHandle` polled after completion");
+            let output = core::task::ready!(core::pin::Pin::new(task).poll(cx));
+            self.0 = None;
+            core::task::Poll::Ready(output.ok_or(crate::JoinError::Cancelled))
+        }
+    }
+
+    impl Drop for Join {
+        fn drop(&mut self) {
+            if let Some(task) = self.0.take() {
+                task.detach();
+            }
+        }
+    }
+
+    Box::pin(Join(Some(task.fallible())))
+}
+
+/// Leaked executors are never dropped, so the handle needs no reference counting.
+impl IntoLocalSpawner for &'static async_executor::StaticLocalExecutor {
+    const EXTENSIONS: Extensions = Extensions::NONE
+        .with_boxed_tasks()
+        .with_weak(WeakOps::STATIC)
+        .with_spawn_inline(|handle, future, _| {
+            let this = unsafe { &*(handle as *const async_executor::StaticLocalExecutor) };
+            this.spawn(future).detach();
+            Ok(())
+        })
+        .with_spawn_abortable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const async_executor::StaticLocalExecutor) };
+            Ok(abort_task(this.spawn(future)))
+        })
+        .with_spawn_joinable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const async_executor::StaticLocalExecutor) };
+            Ok(join_task(this.spawn(future)))
+        });
+
     unsafe fn into_handle(self) -> *const () {
-        Rc::into_raw(self) as *const ()
+        self as *const async_executor::StaticLocalExecutor as *const ()
     }
 
     unsafe fn spawn_dyn(
         _: *const (),
-        builder: SpawnCompleterBuilder,
+        slot: SpawnSlot,
         future_layout: Layout,
-    ) -> SpawnCompleter {
-        let future_ptr = unsafe { alloc::alloc::alloc(future_layout) } as *mut ();
+    ) -> Result<SpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
         let task_ptr = future_ptr;
-        builder.build(task_ptr, future_ptr)
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
     }
 
     unsafe fn finish_spawn(
         handle: *const (),
         task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
     ) -> Result<()> {
         let future_box: Box<dyn Future<Output = ()>> =
             unsafe { Box::from_raw(task_ptr_as_dyn_future) };
 
-        let this = unsafe { &*(handle as *const async_executor::LocalExecutor<'static>) };
+        let this: &'static async_executor::StaticLocalExecutor =
+            unsafe { &*(handle as *const async_executor::StaticLocalExecutor) };
+        this.spawn(Box::into_pin(future_box)).detach();
+
+        Ok(())
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(handle: *const ()) -> Option<Self> {
+        Some(unsafe { &*(handle as *const async_executor::StaticLocalExecutor) })
+    }
+}
+
+unsafe impl DowncastLocalSpawner for &'static async_executor::StaticLocalExecutor {
+    type Target = async_executor::StaticLocalExecutor;
+}
+
+impl IntoSpawner for Arc<async_executor::Executor<'static>> {
+    unsafe fn into_handle(self) -> *const () {
+        Arc::into_raw(self) as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        _: *const (),
+        slot: SendSpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SendSpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut (dyn Future<Output = ()> + Send),
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        let future_box: Box<dyn Future<Output = ()> + Send> =
+            unsafe { Box::from_raw(task_ptr_as_dyn_future) };
+
+        let this = unsafe { &*(handle as *const async_executor::Executor<'static>) };
         this.spawn(Box::into_pin(future_box)).detach();
 
         Ok(())
     }
 
     unsafe fn on_clone(handle: *const ()) {
+        unsafe { Arc::increment_strong_count(handle as *const async_executor::Executor<'static>) }
+    }
+
+    unsafe fn on_drop(handle: *const ()) {
         unsafe {
-            Rc::increment_strong_count(handle as *const async_executor::LocalExecutor<'static>)
+            let _ = Arc::from_raw(handle as *const async_executor::Executor<'static>);
         }
     }
+}
+
+/// Leaked executors are never dropped, so the handle needs no reference counting.
+impl IntoSpawner for &'static async_executor::StaticExecutor {
+    unsafe fn into_handle(self) -> *const () {
+        self as *const async_executor::StaticExecutor as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        _: *const (),
+        slot: SendSpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SendSpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut (dyn Future<Output = ()> + Send),
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        let future_box: Box<dyn Future<Output = ()> + Send> =
+            unsafe { Box::from_raw(task_ptr_as_dyn_future) };
+
+        let this: &'static async_executor::StaticExecutor =
+            unsafe { &*(handle as *const async_executor::StaticExecutor) };
+        this.spawn(Box::into_pin(future_box)).detach();
+
+        Ok(())
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+}
+
+impl<'a> ScopedLocalSpawner<'a> {
+    /// Create a `ScopedLocalSpawner` for a `LocalExecutor` whose futures may borrow data living
+    /// for `'a`, e.g. a short-lived executor created per test or per request.
+    pub fn from_local_executor(ex: Rc<async_executor::LocalExecutor<'a>>) -> Self {
+        // Safety: `LocalExecutor<'a>` accepts futures that live for `'a`, and the spawner cannot
+        // outlive `'a` since it holds onto the executor.
+        unsafe { Self::new_unchecked(ScopedLocalExecutor(ex)) }
+    }
+}
+
+struct ScopedLocalExecutor<'a>(Rc<async_executor::LocalExecutor<'a>>);
+
+impl<'a> IntoLocalSpawner for ScopedLocalExecutor<'a> {
+    const EXTENSIONS: Extensions = Extensions::NONE.with_boxed_tasks();
+
+    unsafe fn into_handle(self) -> *const () {
+        Rc::into_raw(self.0) as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        _: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        // The future only lives for `'a`, which `ScopedLocalSpawner` erased to get it here.
+        let task_ptr: *mut (dyn Future<Output = ()> + 'a) = task_ptr_as_dyn_future;
+        let future_box: Box<dyn Future<Output = ()> + 'a> = unsafe { Box::from_raw(task_ptr) };
+
+        let this = unsafe { &*(handle as *const async_executor::LocalExecutor<'a>) };
+        this.spawn(Box::into_pin(future_box)).detach();
+
+        Ok(())
+    }
+
+    unsafe fn on_clone(handle: *const ()) {
+        unsafe { Rc::increment_strong_count(handle as *const async_executor::LocalExecutor<'a>) }
+    }
 
     unsafe fn on_drop(handle: *const ()) {
         unsafe {
-            let _ = Rc::from_raw(handle as *const async_executor::LocalExecutor<'static>);
+            let _ = Rc::from_raw(handle as *const async_executor::LocalExecutor<'a>);
         }
     }
 }
@@ -73,4 +315,148 @@ mod test {
 
         spawner.spawn(async move {}).unwrap();
     }
+
+    #[test]
+    fn test_scoped_async_executor() {
+        let mut result = None;
+        {
+            let ex = Rc::new(async_executor::LocalExecutor::new());
+            let spawner = crate::ScopedLocalSpawner::from_local_executor(ex.clone());
+
+            let result = &mut result;
+            spawner
+                .spawn(async move {
+                    *result = Some(42);
+                })
+                .unwrap();
+
+            while ex.try_tick() {}
+        }
+
+        assert_eq!(result, Some(42));
+    }
+
+    #[test]
+    fn test_scoped_spawn_with_spawner() {
+        let mut results = [0; 2];
+        {
+            let ex = Rc::new(async_executor::LocalExecutor::new());
+            let spawner = crate::ScopedLocalSpawner::from_local_executor(ex.clone());
+
+            let [parent, child] = &mut results;
+            spawner
+                .spawn_with_spawner(|spawner| async move {
+                    *parent = 1;
+                    spawner.spawn(async move { *child = 2 }).unwrap();
+                })
+                .unwrap();
+
+            while ex.try_tick() {}
+        }
+
+        assert_eq!(results, [1, 2]);
+    }
+
+    #[test]
+    fn test_static_local_executor() {
+        let ex = async_executor::LocalExecutor::new().leak();
+        let spawner = crate::LocalSpawner::new(ex);
+        assert!(spawner.extensions().spawn_inline.is_some());
+        assert!(spawner.extensions().weak.is_some());
+
+        // The handle is never released, so a weak spawner can always be upgraded.
+        let weak = spawner.downgrade();
+        drop(spawner);
+        let spawner = weak.upgrade().unwrap();
+
+        let (result_tx, mut result_rx) = localq::mpsc::channel(1);
+        spawner
+            .spawn(async move {
+                result_tx.try_send(42).unwrap();
+            })
+            .unwrap();
+
+        let result = pollster::block_on(ex.run(async move { result_rx.recv().await }));
+
+        assert_eq!(result.unwrap(), 42);
+    }
+
+    #[test]
+    fn test_dropped_abort_handle_detaches() {
+        let ex = Rc::new(async_executor::LocalExecutor::new());
+        let spawner = crate::LocalSpawner::new(ex.clone());
+
+        let ran = Rc::new(core::cell::Cell::new(false));
+        let ran2 = ran.clone();
+        let handle = spawner
+            .spawn_abortable(async move { ran2.set(true) })
+            .unwrap();
+        drop(handle);
+        while ex.try_tick() {}
+        assert!(ran.get());
+
+        let static_ex = async_executor::LocalExecutor::new().leak();
+        let static_spawner = crate::LocalSpawner::new(static_ex);
+        ran.set(false);
+        let ran3 = ran.clone();
+        let handle = static_spawner
+            .spawn_abortable(async move { ran3.set(true) })
+            .unwrap();
+        drop(handle);
+        while static_ex.try_tick() {}
+        assert!(ran.get());
+    }
+
+    #[test]
+    fn test_native_join() {
+        let ex = Rc::new(async_executor::LocalExecutor::new());
+        let spawner = crate::LocalSpawner::new(ex.clone());
+        assert!(
+            spawner
+                .capabilities()
+                .contains(crate::Capabilities::SUPPORTS_JOIN)
+        );
+
+        let ran = Rc::new(core::cell::Cell::new(false));
+        let ran2 = ran.clone();
+        drop(
+            spawner
+                .spawn_with_handle(async move { ran2.set(true) })
+                .unwrap(),
+        );
+        let handle = spawner.spawn_with_handle(async { 42 }).unwrap();
+        assert_eq!(pollster::block_on(ex.run(handle)), Ok(42));
+        while ex.try_tick() {}
+        assert!(ran.get());
+
+        let handle = spawner
+            .spawn_with_handle(core::future::pending::<()>())
+            .unwrap();
+        drop((spawner, ex));
+        assert!(handle.is_finished());
+        assert_eq!(pollster::block_on(handle), Err(crate::JoinError::Cancelled));
+    }
+
+    #[test]
+    fn test_executor() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        let ex = Arc::new(async_executor::Executor::new());
+        let spawner = crate::Spawner::new(ex.clone());
+        let static_spawner = crate::Spawner::new(async_executor::Executor::new().leak());
+
+        let ran = Arc::new(AtomicUsize::new(0));
+        let ran2 = ran.clone();
+        spawner
+            .spawn(async move {
+                ran2.fetch_add(1, Ordering::Relaxed);
+            })
+            .unwrap();
+        static_spawner.spawn(async {}).unwrap();
+
+        while ex.try_tick() {}
+        assert_eq!(ran.load(Ordering::Relaxed), 1);
+        drop(spawner);
+        assert_eq!(Arc::strong_count(&ex), 1);
+    }
 }
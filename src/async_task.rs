@@ -0,0 +1,248 @@
+use crate::{AbortTask, Extensions, JoinError, JoinTask, TaskOutput};
+use alloc::{boxed::Box, rc::Rc, sync::Arc};
+use async_task::Runnable;
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// Spawns onto an executor built on `async-task`, which only has to schedule the
+/// [`Runnable`]s of its tasks.
+///
+/// Each task is created with `async_task::spawn_local` directly over the spawned future, which is
+/// stored inline in the task when it fits an [`InlineFuture`](crate::InlineFuture), leaving the
+/// task as the only allocation. Only larger futures are boxed first.
+///
+/// ```
+/// let (queue, runnables) = std::sync::mpsc::channel();
+/// let spawner = ispawn::LocalSpawner::new(ispawn::AsyncTaskSpawner::new(move |runnable| {
+///     let _ = queue.send(runnable);
+/// }));
+///
+/// spawner.spawn(async { println!("spawned") }).unwrap();
+/// while let Ok(runnable) = runnables.try_recv() {
+///     runnable.run();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct AsyncTaskSpawner {
+    schedule: Arc<dyn Fn(Runnable) + Send + Sync>,
+}
+
+impl AsyncTaskSpawner {
+    /// Create a new `AsyncTaskSpawner` whose tasks are scheduled with `schedule`, once when they
+    /// are spawned and again every time they are woken. Wakers may be woken from any thread, so
+    /// `schedule` must be `Send` and `Sync`, while the `Runnable`s must be run on the thread that
+    /// spawned them. Every task keeps `schedule` alive.
+    pub fn new(schedule: impl Fn(Runnable) + Send + Sync + 'static) -> Self {
+        Self {
+            schedule: Arc::new(schedule),
+        }
+    }
+
+    fn spawn<F>(&self, future: F) -> async_task::Task<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let schedule = self.schedule.clone();
+        let (runnable, task) = async_task::spawn_local(future, move |runnable| schedule(runnable));
+        runnable.schedule();
+        task
+    }
+}
+
+crate::impl_local_spawner!(
+    AsyncTaskSpawner,
+    |this, future| {
+        this.spawn(future).detach();
+        Ok(())
+    },
+    extensions = Extensions::NONE
+        .with_spawn_inline(|handle, future, _| {
+            let this = unsafe { &*(handle as *const AsyncTaskSpawner) };
+            this.spawn(future).detach();
+            Ok(())
+        })
+        .with_spawn_abortable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const AsyncTaskSpawner) };
+            Ok(abort_task(this.spawn(future)))
+        })
+        .with_spawn_joinable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const AsyncTaskSpawner) };
+            Ok(join_task(this.spawn(future)))
+        }),
+);
+
+/// Aborts a task by dropping its `async_task::Task`, which cancels it. The task is detached instead
+/// once the `AbortHandle` is dropped, so that it keeps running.
+fn abort_task(task: async_task::Task<()>) -> AbortTask {
+    struct Abort(RefCell<Option<async_task::Task<()>>>);
+
+    impl Drop for Abort {
+        fn drop(&mut self) {
+            if let Some(task) = self.0.get_mut().take() {
+                task.detach();
+            }
+        }
+    }
+
+    let abort = Abort(RefCell::new(Some(task)));
+    Rc::new(move || drop(abort.0.borrow_mut().take()))
+}
+
+/// Hands a task's output back through its `async_task::Task`, detaching the task instead of
+/// cancelling it once the `JoinHandle` is dropped.
+fn join_task(task: async_task::Task<TaskOutput>) -> JoinTask {
+    struct Join(Option<async_task::FallibleTask<TaskOutput>>);
+
+    impl Future for Join {
+        type Output = core::result::Result<TaskOutput, JoinError>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let task = self
+                .0
+                .as_mut()
+                .expect("`JoinHandle` polled after completion");
+            let output = core::task::ready!(Pin::new(task).poll(cx));
+            self.0 = None;
+            Poll::Ready(output.ok_or(JoinError::Cancelled))
+        }
+    }
+
+    impl Drop for Join {
+        fn drop(&mut self) {
+            if let Some(task) = self.0.take() {
+                task.detach();
+            }
+        }
+    }
+
+    Box::pin(Join(Some(task.fallible())))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LocalSpawner;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_async_task_spawner() {
+        let (queue, runnables) = std::sync::mpsc::channel();
+        let spawner = LocalSpawner::new(AsyncTaskSpawner::new(move |runnable| {
+            queue.send(runnable).unwrap();
+        }));
+
+        let ran = Rc::new(Cell::new(0));
+        let ran2 = ran.clone();
+        spawner
+            .spawn(async move {
+                // Yields once, to be rescheduled by its waker.
+                let mut yielded = false;
+                core::future::poll_fn(|cx| {
+                    if core::mem::replace(&mut yielded, true) {
+                        return core::task::Poll::Ready(());
+                    }
+                    cx.waker().wake_by_ref();
+                    core::task::Poll::Pending
+                })
+                .await;
+                ran2.set(ran2.get() + 1);
+            })
+            .unwrap();
+
+        let payload = [1u8; 256];
+        let ran3 = ran.clone();
+        spawner
+            .spawn(async move { ran3.set(ran3.get() + payload[0] as usize) })
+            .unwrap();
+
+        let mut runs = 0;
+        while let Ok(runnable) = runnables.try_recv() {
+            runnable.run();
+            runs += 1;
+        }
+        assert_eq!(runs, 3);
+        assert_eq!(ran.get(), 2);
+        assert_eq!(Rc::strong_count(&ran), 1);
+    }
+
+    #[test]
+    fn test_native_abort() {
+        let (queue, runnables) = std::sync::mpsc::channel();
+        let spawner = LocalSpawner::new(AsyncTaskSpawner::new(move |runnable| {
+            queue.send(runnable).unwrap();
+        }));
+        assert!(
+            spawner
+                .capabilities()
+                .contains(crate::Capabilities::SUPPORTS_ABORT)
+        );
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        let handle = spawner
+            .spawn_abortable(async move { ran2.set(true) })
+            .unwrap();
+        handle.abort();
+        assert!(handle.is_aborted());
+        while let Ok(runnable) = runnables.try_recv() {
+            runnable.run();
+        }
+        assert!(!ran.get());
+        assert_eq!(Rc::strong_count(&ran), 1);
+    }
+
+    #[test]
+    fn test_dropped_abort_handle_detaches() {
+        let (queue, runnables) = std::sync::mpsc::channel();
+        let spawner = LocalSpawner::new(AsyncTaskSpawner::new(move |runnable| {
+            queue.send(runnable).unwrap();
+        }));
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        let handle = spawner
+            .spawn_abortable(async move { ran2.set(true) })
+            .unwrap();
+        drop(handle);
+        while let Ok(runnable) = runnables.try_recv() {
+            runnable.run();
+        }
+        assert!(ran.get());
+    }
+
+    #[test]
+    fn test_native_join() {
+        let (queue, runnables) = std::sync::mpsc::channel();
+        let spawner = LocalSpawner::new(AsyncTaskSpawner::new(move |runnable| {
+            queue.send(runnable).unwrap();
+        }));
+        assert!(
+            spawner
+                .capabilities()
+                .contains(crate::Capabilities::SUPPORTS_JOIN)
+        );
+
+        let handle = spawner.spawn_with_handle(async { 42 }).unwrap();
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        drop(
+            spawner
+                .spawn_with_handle(async move { ran2.set(true) })
+                .unwrap(),
+        );
+        while let Ok(runnable) = runnables.try_recv() {
+            runnable.run();
+        }
+        assert!(handle.is_finished());
+        assert_eq!(pollster::block_on(handle), Ok(42));
+        assert!(ran.get());
+
+        let handle = spawner.spawn_with_handle(async { 42 }).unwrap();
+        drop(runnables.try_recv().unwrap());
+        assert!(handle.is_finished());
+        assert_eq!(pollster::block_on(handle), Err(JoinError::Cancelled));
+    }
+}
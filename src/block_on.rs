@@ -0,0 +1,55 @@
+use crate::{LocalSpawner, Result, SpawnError};
+use core::{future::Future, pin::pin};
+
+impl LocalSpawner {
+    /// Run the executor on the current thread until `f` completes, returning its output, e.g. to
+    /// run async setup from synchronous initialization code.
+    ///
+    /// Executors that can be run from a spawner provide this through [`Extensions::block_on`],
+    /// and fail if they can't be run from here, e.g. because they're already running. Otherwise,
+    /// with the `pollster` feature, `f` is blocked on by itself without running the executor's
+    /// other tasks, so it must not wait on them. Fails with `SpawnError::Unsupported` without
+    /// either.
+    ///
+    /// [`Extensions::block_on`]: crate::Extensions::block_on
+    pub fn block_on<F: Future>(&self, f: F) -> Result<F::Output> {
+        let Some(block_on) = self.vtable.ext.block_on else {
+            #[cfg(feature = "pollster")]
+            return Ok(pollster::block_on(f));
+
+            #[cfg(not(feature = "pollster"))]
+            return Err(SpawnError::Unsupported);
+        };
+
+        let mut output = None;
+        {
+            let future = pin!(async { output = Some(f.await) });
+            unsafe { block_on(self.handle, future) }?;
+        }
+        // The executor claimed success without running the future to completion.
+        output.ok_or(SpawnError::Other)
+    }
+}
+
+#[cfg(all(test, feature = "pollster"))]
+mod test {
+    use super::*;
+    use alloc::{boxed::Box, rc::Rc};
+    use core::pin::Pin;
+
+    struct Rejecting;
+
+    impl crate::BoxedLocalSpawn for Rejecting {
+        fn spawn_boxed(&self, _: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+            Err(SpawnError::Unsupported)
+        }
+    }
+
+    #[test]
+    fn test_block_on_pollster_fallback() {
+        let spawner = LocalSpawner::new(Rc::new(Rejecting));
+        let borrowed = alloc::string::String::from("setup");
+        let output = spawner.block_on(async { borrowed.len() }).unwrap();
+        assert_eq!(output, 5);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{BlockingJob, LocalSpawner, Result, SpawnError};
+use alloc::boxed::Box;
+
+impl LocalSpawner {
+    /// Run a blocking function, e.g. a slow FFI call, without blocking the executor's thread.
+    ///
+    /// The function runs on the executor's blocking thread pool if it has one (see
+    /// [`Extensions::spawn_blocking`](crate::Extensions::spawn_blocking)), otherwise on a new
+    /// `std` thread. Fails with `SpawnError::Unsupported` without either, i.e. on `no_std`.
+    pub fn spawn_blocking<F: FnOnce() + Send + 'static>(&self, f: F) -> Result<()> {
+        unsafe { spawn_blocking(self.vtable.ext.spawn_blocking, self.handle, Box::new(f)) }
+    }
+}
+
+/// Safety: `handle` must be live, and `executor` must be the `spawn_blocking` extension of the
+/// spawner it belongs to.
+pub(crate) unsafe fn spawn_blocking(
+    executor: Option<crate::extensions::SpawnBlocking>,
+    handle: *const (),
+    job: BlockingJob,
+) -> Result<()> {
+    if let Some(spawn_blocking) = executor {
+        return unsafe { spawn_blocking(handle, job) };
+    }
+
+    #[cfg(feature = "std")]
+    return match std::thread::Builder::new().spawn(job) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(SpawnError::Executor(Box::new(e))),
+    };
+
+    #[cfg(not(feature = "std"))]
+    {
+        drop(job);
+        Err(SpawnError::Unsupported)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use alloc::{rc::Rc, vec::Vec};
+    use core::{cell::RefCell, future::Future, pin::Pin};
+
+    #[derive(Default)]
+    struct Queue(RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>>);
+
+    impl crate::BoxedLocalSpawn for Queue {
+        fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+            self.0.borrow_mut().push(future);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_spawn_blocking_on_thread() {
+        let spawner = LocalSpawner::new(Rc::new(Queue::default()));
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawner
+            .spawn_blocking(move || {
+                tx.send(std::thread::current().id()).unwrap();
+            })
+            .unwrap();
+        assert_ne!(rx.recv().unwrap(), std::thread::current().id());
+    }
+}
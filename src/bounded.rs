@@ -0,0 +1,156 @@
+use crate::{LocalSpawner, Result, SpawnError, TaskMeta, tracker::TaskTracker};
+use core::future::Future;
+
+/// A spawner that caps how many of the tasks spawned through it may be in flight at once, so that
+/// e.g. per-request tasks can't grow without bound.
+///
+/// [`try_spawn`](Self::try_spawn) fails with `SpawnError::QueueFull` at the limit, while
+/// [`spawn`](Self::spawn) waits for a running task to finish first. Clones share the limit.
+#[derive(Clone)]
+pub struct BoundedSpawner {
+    inner: LocalSpawner,
+    tracker: TaskTracker,
+    limit: usize,
+}
+
+impl BoundedSpawner {
+    /// Create a new `BoundedSpawner` spawning at most `limit` concurrent tasks onto `inner`.
+    pub fn new(inner: LocalSpawner, limit: usize) -> Self {
+        Self {
+            inner,
+            tracker: TaskTracker::new(),
+            limit,
+        }
+    }
+
+    /// Spawn a `Future`, failing with `SpawnError::QueueFull` if `limit` tasks are in flight.
+    #[track_caller]
+    pub fn try_spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.spawn_tracked(f, TaskMeta::new::<F>())
+    }
+
+    /// Spawn a `Future` once fewer than `limit` tasks are in flight.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(
+        &self,
+        f: F,
+    ) -> impl Future<Output = Result<()>> + '_ {
+        let meta = TaskMeta::new::<F>();
+        async move {
+            self.tracker.below(self.limit).await;
+            self.spawn_tracked(f, meta)
+        }
+    }
+
+    /// Like [`try_spawn`](Self::try_spawn), but the `Future` is returned by `f`, which is handed a
+    /// clone of this spawner so the task can spawn children under the same limit.
+    #[track_caller]
+    pub fn try_spawn_with_spawner<F, Fut>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(BoundedSpawner) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.try_spawn(f(self.clone()))
+    }
+
+    /// Like [`spawn`](Self::spawn), but the `Future` is returned by `f`, which is handed a clone
+    /// of this spawner so the task can spawn children under the same limit.
+    #[track_caller]
+    pub fn spawn_with_spawner<F, Fut>(&self, f: F) -> impl Future<Output = Result<()>> + '_
+    where
+        F: FnOnce(BoundedSpawner) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.spawn(f(self.clone()))
+    }
+
+    /// The number of tasks in flight.
+    pub fn active(&self) -> usize {
+        self.tracker.active()
+    }
+
+    /// The maximum number of tasks in flight.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    fn spawn_tracked<F: Future<Output = ()> + 'static>(&self, f: F, meta: TaskMeta) -> Result<()> {
+        if self.active() >= self.limit {
+            return Err(SpawnError::QueueFull);
+        }
+        let future = self.tracker.wrap(f);
+        self.inner.spawn_with_meta(
+            async move {
+                future.await;
+            },
+            meta,
+        )
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use alloc::{rc::Rc, vec::Vec};
+    use core::cell::Cell;
+    use futures_executor::LocalPool;
+
+    #[test]
+    fn test_limit_and_backpressure() {
+        let mut ex = LocalPool::new();
+        let unbounded = LocalSpawner::new(Rc::new(ex.spawner()));
+        let spawner = BoundedSpawner::new(unbounded.clone(), 2);
+
+        let mut senders = Vec::new();
+        for _ in 0..2 {
+            let (tx, mut rx) = localq::mpsc::channel::<()>(1);
+            senders.push(tx);
+            spawner
+                .try_spawn(async move {
+                    let _ = rx.recv().await;
+                })
+                .unwrap();
+        }
+        assert!(matches!(
+            spawner.try_spawn(async {}),
+            Err(SpawnError::QueueFull)
+        ));
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        let spawner2 = spawner.clone();
+        unbounded
+            .spawn(async move {
+                spawner2.spawn(async move { ran2.set(true) }).await.unwrap();
+            })
+            .unwrap();
+        ex.run_until_stalled();
+        assert!(!ran.get());
+        assert_eq!(spawner.active(), 2);
+
+        senders[0].try_send(()).unwrap();
+        ex.run_until_stalled();
+        assert!(ran.get());
+        assert_eq!(spawner.active(), 1);
+    }
+
+    #[test]
+    fn test_spawn_with_spawner_shares_limit() {
+        let mut ex = LocalPool::new();
+        let spawner = BoundedSpawner::new(LocalSpawner::new(Rc::new(ex.spawner())), 1);
+
+        let child = Rc::new(Cell::new(None));
+        let child2 = child.clone();
+        spawner
+            .try_spawn_with_spawner(|spawner| async move {
+                child2.set(Some(matches!(
+                    spawner.try_spawn(async {}),
+                    Err(SpawnError::QueueFull)
+                )));
+            })
+            .unwrap();
+        ex.run_until_stalled();
+        assert_eq!(child.get(), Some(true));
+        assert_eq!(spawner.active(), 0);
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{
+    DowncastLocalSpawner, Extensions, IntoLocalSpawner, Result, SpawnCompleter, SpawnError,
+    SpawnSlot, TaskMeta, WeakOps,
+};
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::{alloc::Layout, boxed::Box, rc::Rc};
+use core::{future::Future, pin::Pin, ptr::NonNull};
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc as PortableArc;
+
+/// A safe way to integrate an executor with `ispawn`: implement this for the executor's spawner
+/// and create a `LocalSpawner` from an `Rc` of it.
+///
+/// ```
+/// # use core::{future::Future, pin::Pin};
+/// struct MyExecutor;
+///
+/// impl ispawn::BoxedLocalSpawn for MyExecutor {
+///     fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> ispawn::Result<()> {
+///         // Queue `future` up for polling.
+///         # drop(future);
+///         Ok(())
+///     }
+/// }
+///
+/// let spawner = ispawn::LocalSpawner::new(std::rc::Rc::new(MyExecutor));
+/// ```
+///
+/// Every spawned future is boxed before being handed to the executor. Executors that can allocate
+/// their tasks with the future inline should implement [`IntoLocalSpawner`] instead.
+pub trait BoxedLocalSpawn {
+    /// Spawn a boxed `Future`.
+    fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Result<()>;
+}
+
+impl<T: BoxedLocalSpawn + 'static> IntoLocalSpawner for Rc<T> {
+    const EXTENSIONS: Extensions = Extensions::NONE
+        .with_boxed_tasks()
+        .with_weak(WeakOps::rc::<T>());
+
+    unsafe fn into_handle(self) -> *const () {
+        Rc::into_raw(self) as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        _: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        let future_box: Box<dyn Future<Output = ()>> =
+            unsafe { Box::from_raw(task_ptr_as_dyn_future) };
+
+        let this = unsafe { &*(handle as *const T) };
+        this.spawn_boxed(Box::into_pin(future_box))
+    }
+
+    unsafe fn on_clone(handle: *const ()) {
+        unsafe { Rc::increment_strong_count(handle as *const T) }
+    }
+
+    unsafe fn on_drop(handle: *const ()) {
+        unsafe {
+            let _ = Rc::from_raw(handle as *const T);
+        }
+    }
+
+    unsafe fn from_handle(handle: *const ()) -> Option<Self> {
+        Some(unsafe { Rc::from_raw(handle as *const T) })
+    }
+}
+
+unsafe impl<T: BoxedLocalSpawn + 'static> DowncastLocalSpawner for Rc<T> {
+    type Target = T;
+}
+
+/// Implements `IntoLocalSpawner` for an `Arc`, which may come from `alloc` or from
+/// `portable-atomic-util`.
+#[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
+macro_rules! impl_arc {
+    ($(#[$attr:meta])* $arc:ident, $weak:expr) => {
+        $(#[$attr])*
+        impl<T: BoxedLocalSpawn + 'static> IntoLocalSpawner for $arc<T> {
+            const EXTENSIONS: Extensions = Extensions::NONE
+                .with_boxed_tasks()
+                .with_weak($weak);
+
+            unsafe fn into_handle(self) -> *const () {
+                $arc::into_raw(self) as *const ()
+            }
+
+            unsafe fn spawn_dyn(
+                _: *const (),
+                slot: SpawnSlot,
+                future_layout: Layout,
+            ) -> Result<SpawnCompleter> {
+                unsafe { crate::__private::box_spawn_dyn(slot, future_layout) }
+            }
+
+            unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+                unsafe { crate::__private::box_cancel_spawn(task_ptr, future_layout) }
+            }
+
+            unsafe fn finish_spawn(
+                handle: *const (),
+                task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+                _meta: TaskMeta,
+            ) -> Result<()> {
+                let future = unsafe { crate::__private::box_future(task_ptr_as_dyn_future) };
+                let this = unsafe { &*(handle as *const T) };
+                this.spawn_boxed(future)
+            }
+
+            unsafe fn on_clone(handle: *const ()) {
+                unsafe { $arc::increment_strong_count(handle as *const T) }
+            }
+
+            unsafe fn on_drop(handle: *const ()) {
+                unsafe { drop($arc::from_raw(handle as *const T)) }
+            }
+
+            unsafe fn from_handle(handle: *const ()) -> Option<Self> {
+                Some(unsafe { $arc::from_raw(handle as *const T) })
+            }
+        }
+
+        unsafe impl<T: BoxedLocalSpawn + 'static> DowncastLocalSpawner for $arc<T> {
+            type Target = T;
+        }
+    };
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl_arc!(
+    /// For executors that are already shared through an `Arc`, e.g. with other threads.
+    Arc,
+    WeakOps::arc::<T>()
+);
+
+#[cfg(feature = "portable-atomic")]
+impl_arc!(
+    /// For executors shared through `portable-atomic-util`'s `Arc`, on targets without native
+    /// atomics.
+    PortableArc,
+    WeakOps::portable_arc::<T>()
+);
+
+/// For executors in a `static`, which need no reference counting.
+impl<T: BoxedLocalSpawn + 'static> IntoLocalSpawner for &'static T {
+    const EXTENSIONS: Extensions = Extensions::NONE
+        .with_boxed_tasks()
+        .with_weak(WeakOps::STATIC);
+
+    unsafe fn into_handle(self) -> *const () {
+        self as *const T as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        _: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        unsafe { crate::__private::box_spawn_dyn(slot, future_layout) }
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::__private::box_cancel_spawn(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        let future = unsafe { crate::__private::box_future(task_ptr_as_dyn_future) };
+        let this = unsafe { &*(handle as *const T) };
+        this.spawn_boxed(future)
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(handle: *const ()) -> Option<Self> {
+        Some(unsafe { &*(handle as *const T) })
+    }
+}
+
+unsafe impl<T: BoxedLocalSpawn + 'static> DowncastLocalSpawner for &'static T {
+    type Target = T;
+}
+
+/// Allocate memory for a future of `layout` the way `Box` would, so that it can later be taken
+/// over with `Box::from_raw`. Zero-sized futures get a dangling, well-aligned pointer.
+pub(crate) fn alloc_future(layout: Layout) -> Result<NonNull<()>> {
+    if layout.size() == 0 {
+        // A dangling but aligned pointer, without provenance as nothing is ever accessed through it.
+        let ptr = core::ptr::without_provenance_mut(layout.align());
+        return Ok(unsafe { NonNull::new_unchecked(ptr) });
+    }
+    // Safety: `layout` has a non-zero size.
+    let ptr = unsafe { alloc::alloc::alloc(layout) };
+    NonNull::new(ptr as *mut ()).ok_or(SpawnError::AllocFailed)
+}
+
+/// Free memory allocated by [`alloc_future`] for a future that was never written to it.
+///
+/// Safety: `ptr` must have been returned by `alloc_future(layout)`.
+pub(crate) unsafe fn dealloc_future(ptr: *mut (), layout: Layout) {
+    if layout.size() != 0 {
+        unsafe { alloc::alloc::dealloc(ptr as *mut u8, layout) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LocalSpawner;
+    use alloc::vec::Vec;
+    use core::{
+        cell::{Cell, RefCell},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[derive(Default)]
+    struct QueueExecutor {
+        queue: RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>>,
+    }
+
+    impl BoxedLocalSpawn for QueueExecutor {
+        fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+            self.queue.borrow_mut().push(future);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_boxed_local_spawn() {
+        let ex = Rc::new(QueueExecutor::default());
+        let spawner = LocalSpawner::new(ex.clone());
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        spawner.spawn(async move { ran2.set(true) }).unwrap();
+
+        let future = ex.queue.borrow_mut().pop().unwrap();
+        pollster::block_on(future);
+        assert!(ran.get());
+
+        drop(spawner);
+        assert_eq!(Rc::strong_count(&ex), 1);
+    }
+
+    #[derive(Default)]
+    struct CountingExecutor {
+        spawned: AtomicUsize,
+    }
+
+    impl BoxedLocalSpawn for CountingExecutor {
+        fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+            drop(future);
+            self.spawned.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_arc_and_static_handles() {
+        let ex = Arc::new(CountingExecutor::default());
+        let spawner = LocalSpawner::new(ex.clone());
+        spawner.clone().spawn(async {}).unwrap();
+        let weak = spawner.downgrade();
+        assert!(weak.upgrade().is_some());
+        drop((spawner, weak));
+        assert_eq!(ex.spawned.load(Ordering::Relaxed), 1);
+        assert_eq!(Arc::strong_count(&ex), 1);
+        assert_eq!(Arc::weak_count(&ex), 0);
+
+        static EX: CountingExecutor = CountingExecutor {
+            spawned: AtomicUsize::new(0),
+        };
+        let spawner = LocalSpawner::new(&EX);
+        spawner.spawn(async {}).unwrap();
+        assert!(spawner.downgrade().upgrade().is_some());
+        assert_eq!(EX.spawned.load(Ordering::Relaxed), 1);
+    }
+
+    #[cfg(feature = "portable-atomic")]
+    #[test]
+    fn test_portable_arc_handles() {
+        let ex = PortableArc::new(CountingExecutor::default());
+        let spawner = LocalSpawner::new(ex.clone());
+        spawner.clone().spawn(async {}).unwrap();
+        let weak = spawner.downgrade();
+        assert!(weak.upgrade().is_some());
+        drop((spawner, weak));
+        assert_eq!(ex.spawned.load(Ordering::Relaxed), 1);
+        assert_eq!(PortableArc::strong_count(&ex), 1);
+        assert_eq!(PortableArc::weak_count(&ex), 0);
+    }
+
+    #[test]
+    // Instrumented futures are wrapped, so they differ in size and allocation.
+    #[cfg(not(feature = "tracing"))]
+    fn test_spawn_boxed_reuses_allocation() {
+        let ex = Rc::new(QueueExecutor::default());
+        let spawner = LocalSpawner::new(ex.clone());
+
+        let future: Pin<Box<dyn Future<Output = ()>>> = Box::pin(async {});
+        let ptr = &*future as *const dyn Future<Output = ()> as *const ();
+        spawner.spawn_boxed(future).unwrap();
+
+        let queued = ex.queue.borrow_mut().pop().unwrap();
+        assert_eq!(&*queued as *const dyn Future<Output = ()> as *const (), ptr);
+    }
+
+    #[test]
+    fn test_zero_sized_future() {
+        struct Zst;
+
+        impl Future for Zst {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, _: &mut core::task::Context<'_>) -> core::task::Poll<()> {
+                core::task::Poll::Ready(())
+            }
+        }
+
+        let ex = Rc::new(QueueExecutor::default());
+        let spawner = LocalSpawner::new(ex.clone());
+        spawner.spawn(Zst).unwrap();
+
+        let future = ex.queue.borrow_mut().pop().unwrap();
+        pollster::block_on(future);
+    }
+}
@@ -0,0 +1,113 @@
+//! A single-threaded cancellation signal shared between a controller and the tasks it may cancel.
+
+use crate::{LocalSpawner, Result, TaskMeta, slab::Slab, tracker::TaskTracker};
+use alloc::rc::Rc;
+use core::{
+    cell::{Cell, RefCell},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+#[derive(Clone, Default)]
+pub(crate) struct CancelSignal {
+    inner: Rc<CancelInner>,
+}
+
+#[derive(Default)]
+struct CancelInner {
+    cancelled: Cell<bool>,
+    wakers: RefCell<Slab<Waker>>,
+}
+
+impl CancelSignal {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.get()
+    }
+
+    /// Mark the signal as cancelled and wake every task waiting on it so it gets polled (and
+    /// thus dropped) promptly.
+    pub(crate) fn cancel(&self) {
+        if self.inner.cancelled.replace(true) {
+            return;
+        }
+        let wakers = core::mem::take(&mut *self.inner.wakers.borrow_mut());
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    /// Wrap `future` so that it completes with `None` as soon as this signal is cancelled.
+    pub(crate) fn wrap<F: Future>(&self, future: F) -> Cancellable<F> {
+        Cancellable {
+            signal: self.clone(),
+            key: None,
+            future,
+        }
+    }
+}
+
+impl LocalSpawner {
+    /// Spawn `f` on behalf of a wrapper so that it's dropped once `signal` is cancelled, counting
+    /// as active in `tracker` if given until it completes or is dropped.
+    #[track_caller]
+    pub(crate) fn spawn_guarded<F: Future<Output = ()> + 'static>(
+        &self,
+        f: F,
+        signal: &CancelSignal,
+        tracker: Option<&TaskTracker>,
+    ) -> Result<()> {
+        let guard = tracker.map(TaskTracker::track);
+        let future = signal.wrap(f);
+        self.spawn_with_meta(
+            async move {
+                let _guard = guard;
+                future.await;
+            },
+            TaskMeta::new::<F>(),
+        )
+    }
+}
+
+pub(crate) struct Cancellable<F> {
+    signal: CancelSignal,
+    key: Option<usize>,
+    future: F,
+}
+
+impl<F: Future> Future for Cancellable<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: `future` is structurally pinned; the other fields are never pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.signal.is_cancelled() {
+            return Poll::Ready(None);
+        }
+
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        if let Poll::Ready(output) = future.poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+
+        let mut wakers = this.signal.inner.wakers.borrow_mut();
+        match this.key.and_then(|key| wakers.get_mut(key)) {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            Some(waker) => *waker = cx.waker().clone(),
+            None => this.key = Some(wakers.insert(cx.waker().clone())),
+        }
+        Poll::Pending
+    }
+}
+
+impl<F> Drop for Cancellable<F> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.signal.inner.wakers.borrow_mut().remove(key);
+        }
+    }
+}
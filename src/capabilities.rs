@@ -0,0 +1,86 @@
+use core::{fmt, ops};
+
+/// A set of flags describing what an executor supports, returned by
+/// [`LocalSpawner::capabilities`](crate::LocalSpawner::capabilities), so that libraries can pick
+/// code paths up front, e.g. only wrapping tasks in their own cancellation when the executor can't
+/// abort them.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    /// Spawning doesn't allocate anything beyond the executor's own task: the future is written
+    /// directly into the task instead of being boxed first.
+    pub const ZERO_ALLOC: Self = Self(1 << 0);
+
+    /// The executor can abort its tasks itself, see
+    /// [`Extensions::spawn_abortable`](crate::Extensions::spawn_abortable).
+    pub const SUPPORTS_ABORT: Self = Self(1 << 1);
+
+    /// The executor attaches task names, see [`Extensions::names`](crate::Extensions::names).
+    pub const SUPPORTS_NAMES: Self = Self(1 << 2);
+
+    /// The executor honors task priorities, see
+    /// [`Extensions::priorities`](crate::Extensions::priorities).
+    pub const SUPPORTS_PRIORITY: Self = Self(1 << 3);
+
+    /// The executor hands task outputs back through its own join handles, see
+    /// [`Extensions::spawn_joinable`](crate::Extensions::spawn_joinable).
+    pub const SUPPORTS_JOIN: Self = Self(1 << 4);
+
+    const ALL: [(Self, &'static str); 5] = [
+        (Self::ZERO_ALLOC, "ZERO_ALLOC"),
+        (Self::SUPPORTS_ABORT, "SUPPORTS_ABORT"),
+        (Self::SUPPORTS_NAMES, "SUPPORTS_NAMES"),
+        (Self::SUPPORTS_PRIORITY, "SUPPORTS_PRIORITY"),
+        (Self::SUPPORTS_JOIN, "SUPPORTS_JOIN"),
+    ];
+
+    /// No capabilities.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Whether every capability in `other` is also in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The capabilities in either `self` or `other`.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl ops::BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+impl ops::BitOrAssign for Capabilities {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = self.union(other);
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut set = f.debug_set();
+        for (flag, name) in Self::ALL {
+            if self.contains(flag) {
+                set.entry(&format_args!("{name}"));
+            }
+        }
+        set.finish()
+    }
+}
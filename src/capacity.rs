@@ -0,0 +1,60 @@
+use crate::{LocalSpawner, Result, SpawnError, TaskMeta};
+use core::{
+    future::Future,
+    task::{Context, Poll},
+};
+
+impl LocalSpawner {
+    /// Spawn a `Future` only if the executor has room for it right now, failing with
+    /// `SpawnError::QueueFull` otherwise. Executors that report their capacity (see
+    /// [`Extensions::poll_capacity`](crate::Extensions::poll_capacity)) are asked before anything
+    /// is allocated, others fail the same way [`spawn`](Self::spawn) does.
+    #[track_caller]
+    pub fn try_spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        let mut cx = Context::from_waker(core::task::Waker::noop());
+        if self.poll_capacity(&mut cx).is_pending() {
+            return Err(SpawnError::QueueFull);
+        }
+        self.spawn(f)
+    }
+
+    /// Spawn a `Future` once the executor has room for it, instead of failing with
+    /// `SpawnError::QueueFull`, so that producers on constrained targets neither busy-retry nor
+    /// drop work.
+    ///
+    /// Waiting requires the executor to report its capacity (see
+    /// [`Extensions::poll_capacity`](crate::Extensions::poll_capacity)). Other executors fail the
+    /// same way [`spawn`](Self::spawn) does.
+    #[track_caller]
+    pub fn spawn_when_ready<F: Future<Output = ()> + 'static>(
+        &self,
+        f: F,
+    ) -> impl Future<Output = Result<()>> + '_ {
+        let meta = TaskMeta::new::<F>();
+        let mut f = Some(f);
+        async move {
+            loop {
+                core::future::poll_fn(|cx| self.poll_capacity(cx)).await;
+                // The future is only taken once the executor has made room for it, so it's still
+                // there to retry with if the executor turned out to be full after all.
+                // Safety: `F` is `'static`.
+                let result = unsafe {
+                    self.spawn_fn_unchecked_with_meta(|| f.take().expect("taken once"), meta)
+                };
+                match result {
+                    Err(SpawnError::QueueFull)
+                        if f.is_some() && self.vtable.ext.poll_capacity.is_some() => {}
+                    result => return result,
+                }
+            }
+        }
+    }
+
+    /// `Ready` if the executor has room for another task or doesn't report its capacity.
+    fn poll_capacity(&self, cx: &mut Context<'_>) -> Poll<()> {
+        match self.vtable.ext.poll_capacity {
+            Some(poll_capacity) => unsafe { poll_capacity(self.handle, cx) },
+            None => Poll::Ready(()),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{LocalSpawner, Result, TaskMeta};
+use alloc::boxed::Box;
+use core::{
+    any::Any,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+/// Called with the metadata of a task that panicked and the panic's payload.
+pub type PanicHandler = fn(&TaskMeta, Box<dyn Any + Send>);
+
+/// A spawner that isolates panics in the tasks spawned through it, for executors where a panicking
+/// task tears down the whole executor or aborts the process.
+///
+/// Every poll of a spawned future runs inside `catch_unwind`. A task that panics is dropped and
+/// its panic is passed to the handler instead of unwinding into the executor. Create a
+/// `LocalSpawner` from a `CatchUnwindSpawner` to hand it to code expecting one; futures spawned
+/// through that are boxed first.
+#[derive(Clone)]
+pub struct CatchUnwindSpawner {
+    inner: LocalSpawner,
+    handler: PanicHandler,
+}
+
+impl CatchUnwindSpawner {
+    /// Route panics of tasks spawned onto `inner` to `handler`.
+    pub fn new(inner: LocalSpawner, handler: PanicHandler) -> Self {
+        Self { inner, handler }
+    }
+
+    /// Spawn a `Future`, catching its panics.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.spawn_caught(f, TaskMeta::new::<F>())
+    }
+
+    fn spawn_caught<F: Future<Output = ()> + 'static>(&self, f: F, meta: TaskMeta) -> Result<()> {
+        let future = CatchUnwind {
+            future: Some(f),
+            meta,
+            handler: self.handler,
+        };
+        self.inner.spawn_with_meta(future, meta)
+    }
+}
+
+crate::impl_local_spawner!(CatchUnwindSpawner, |this, future| {
+    this.spawn_caught(future, TaskMeta::new::<Pin<Box<dyn Future<Output = ()>>>>())
+});
+
+struct CatchUnwind<F> {
+    /// `None` once the future has panicked, after which it is never polled again.
+    future: Option<F>,
+    meta: TaskMeta,
+    handler: PanicHandler,
+}
+
+impl<F: Future<Output = ()>> Future for CatchUnwind<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `future` is structurally pinned and only ever dropped in place.
+        let this = unsafe { self.get_unchecked_mut() };
+        let Some(future) = this.future.as_mut() else {
+            return Poll::Ready(());
+        };
+        let future = unsafe { Pin::new_unchecked(future) };
+        match catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(poll) => poll,
+            Err(payload) => {
+                // Drop the future in place, which the pinning guarantee allows.
+                this.future = None;
+                (this.handler)(&this.meta, payload);
+                Poll::Ready(())
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+    use futures_executor::LocalPool;
+
+    std::thread_local! {
+        static CAUGHT: Cell<Option<&'static str>> = const { Cell::new(None) };
+    }
+
+    #[test]
+    fn test_panic_is_routed_to_handler() {
+        let mut ex = LocalPool::new();
+        let spawner =
+            CatchUnwindSpawner::new(LocalSpawner::new(Rc::new(ex.spawner())), |_, payload| {
+                CAUGHT.set(payload.downcast_ref::<&'static str>().copied())
+            });
+
+        spawner.spawn(async { panic!("task failed") }).unwrap();
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        spawner.spawn(async move { ran2.set(true) }).unwrap();
+        ex.run();
+
+        assert_eq!(CAUGHT.get(), Some("task failed"));
+        assert!(ran.get());
+    }
+}
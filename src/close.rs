@@ -0,0 +1,103 @@
+use crate::{Extensions, LocalSpawner, SpawnError};
+use alloc::vec::Vec;
+use core::{
+    cell::{Cell, RefCell},
+    task::{Poll, Waker},
+};
+
+impl LocalSpawner {
+    /// Wrap this spawner in one that can be [`close`](Self::close)d, for executors without native
+    /// support for it. Closing the returned spawner and its clones doesn't affect this one.
+    /// Futures spawned through it are boxed before they are handed to this spawner.
+    pub fn closable(self) -> LocalSpawner {
+        LocalSpawner::new(Closable {
+            inner: self,
+            closed: Cell::new(false),
+            wakers: RefCell::new(Vec::new()),
+        })
+    }
+}
+
+struct Closable {
+    inner: LocalSpawner,
+    closed: Cell<bool>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+crate::impl_local_spawner!(
+    Closable,
+    |this, future| {
+        if this.closed.get() {
+            return Err(SpawnError::Shutdown);
+        }
+        this.inner.spawn_boxed(future)
+    },
+    extensions = Extensions::NONE
+        .with_poll_closed(|handle, cx| {
+            let this = unsafe { &*(handle as *const Closable) };
+            if this.closed.get() || this.inner.is_closed() {
+                return Poll::Ready(());
+            }
+            this.wakers.borrow_mut().push(cx.waker().clone());
+            Poll::Pending
+        })
+        .with_close(|handle| {
+            let this = unsafe { &*(handle as *const Closable) };
+            this.closed.set(true);
+            for waker in core::mem::take(&mut *this.wakers.borrow_mut()) {
+                waker.wake();
+            }
+        }),
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Result;
+    use alloc::{boxed::Box, rc::Rc};
+    use core::{future::Future, pin::Pin, task::Context};
+
+    #[derive(Default)]
+    struct Queue(RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>>);
+
+    impl crate::BoxedLocalSpawn for Queue {
+        fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+            self.0.borrow_mut().push(future);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_closable() {
+        let queue = Rc::new(Queue::default());
+        let inner = LocalSpawner::new(queue.clone());
+        assert!(matches!(inner.close(), Err(SpawnError::Unsupported)));
+        assert!(!inner.is_closed());
+
+        let spawner = inner.clone().closable();
+        let library = spawner.clone();
+        library.spawn(async {}).unwrap();
+        assert!(!library.is_closed());
+
+        let mut closed = Box::pin(library.closed());
+        assert!(
+            closed
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+                .is_pending()
+        );
+        spawner.close().unwrap();
+        assert!(library.is_closed());
+        assert!(
+            closed
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+                .is_ready()
+        );
+        assert!(matches!(library.spawn(async {}), Err(SpawnError::Shutdown)));
+        assert_eq!(queue.0.borrow().len(), 1);
+
+        inner.spawn(async {}).unwrap();
+        assert_eq!(queue.0.borrow().len(), 2);
+    }
+}
@@ -0,0 +1,331 @@
+//! A reusable test suite for [`IntoLocalSpawner`](crate::IntoLocalSpawner) implementations.
+//!
+//! Executor integrations uphold an unsafe contract that the compiler can't check. Implement
+//! [`Harness`] for the executor under test and call [`check_all`] from a test to verify that
+//! futures of many sizes and alignments are emplaced and run correctly, that handle refcounts stay
+//! balanced, that cancelled spawns release their tasks, and that spawners outliving their executor
+//! fail gracefully:
+//!
+//! ```ignore
+//! struct MyHarness(my_executor::Executor);
+//!
+//! impl ispawn::compliance::Harness for MyHarness {
+//!     fn spawner(&mut self) -> ispawn::LocalSpawner {
+//!         ispawn::LocalSpawner::new(self.0.spawner())
+//!     }
+//!
+//!     fn run_until_stalled(&mut self) {
+//!         self.0.run_until_stalled();
+//!     }
+//! }
+//!
+//! #[test]
+//! fn my_executor_complies() {
+//!     ispawn::compliance::check_all(&mut MyHarness(my_executor::Executor::new()));
+//! }
+//! ```
+//!
+//! Every check panics with a description of the violation when it fails.
+
+use crate::{LocalSpawner, SpawnError};
+use alloc::rc::Rc;
+use core::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Drives the executor under test.
+pub trait Harness {
+    /// Create a `LocalSpawner` for the executor under test.
+    fn spawner(&mut self) -> LocalSpawner;
+
+    /// Run the executor until no spawned task can make further progress.
+    fn run_until_stalled(&mut self);
+
+    /// The number of strong references to the executor's handle, if the integration is reference
+    /// counted and the count can be observed. Enables [`check_refcounts`].
+    fn handle_refcount(&self) -> Option<usize> {
+        None
+    }
+
+    /// Shut the executor down, returning whether it could be. Enables [`check_shutdown`].
+    fn shut_down(&mut self) -> bool {
+        false
+    }
+}
+
+/// Run every check. [`check_shutdown`] runs last, since it shuts the executor down.
+pub fn check_all<H: Harness>(harness: &mut H) {
+    check_spawn_runs(harness);
+    check_layouts(harness);
+    check_zero_sized(harness);
+    check_refcounts(harness);
+    check_cancel_spawn(harness);
+    check_shutdown(harness);
+}
+
+/// A spawned future runs to completion.
+pub fn check_spawn_runs<H: Harness>(harness: &mut H) {
+    let spawner = harness.spawner();
+    let done = Rc::new(Cell::new(false));
+    let done2 = done.clone();
+    spawner
+        .spawn(async move { done2.set(true) })
+        .expect("spawn failed");
+    harness.run_until_stalled();
+    assert!(done.get(), "spawned future did not run to completion");
+    assert_eq!(
+        Rc::strong_count(&done),
+        1,
+        "completed future was not dropped"
+    );
+}
+
+/// Futures across a range of sizes and alignments are stored at properly aligned addresses, keep
+/// their state intact across polls, and are dropped after completing.
+pub fn check_layouts<H: Harness>(harness: &mut H) {
+    let spawner = harness.spawner();
+    let completed = Rc::new(Cell::new(0));
+
+    macro_rules! spawn_sizes {
+        ($align:ty, $($size:literal),*) => {
+            $(spawn_checked::<$align, $size>(&spawner, &completed);)*
+        };
+    }
+    macro_rules! spawn_layouts {
+        ($($align:ty),*) => {
+            $(spawn_sizes!($align, 1, 3, 8, 17, 64, 100, 256, 1000, 4096);)*
+        };
+    }
+    spawn_layouts!(A1, A2, A4, A8, A16, A32, A64, A128, A4096);
+
+    harness.run_until_stalled();
+    assert_eq!(
+        completed.get(),
+        9 * 9,
+        "not every spawned future ran to completion"
+    );
+    assert_eq!(
+        Rc::strong_count(&completed),
+        1,
+        "completed futures were not dropped"
+    );
+}
+
+/// Zero-sized futures can be spawned and run.
+pub fn check_zero_sized<H: Harness>(harness: &mut H) {
+    let spawner = harness.spawner();
+    ZST_POLLS.with(|p| p.set(0));
+    spawner.spawn(ZeroSized).expect("spawn failed");
+    harness.run_until_stalled();
+    assert_eq!(
+        ZST_POLLS.with(|p| p.get()),
+        1,
+        "zero-sized future was not polled exactly once"
+    );
+}
+
+/// Cloning and dropping spawners, and spawning through clones, leaves the handle's refcount where
+/// it started. Skipped if the harness can't observe the refcount.
+pub fn check_refcounts<H: Harness>(harness: &mut H) {
+    let spawner = harness.spawner();
+    let Some(baseline) = harness.handle_refcount() else {
+        return;
+    };
+
+    {
+        let clones: alloc::vec::Vec<_> = (0..8).map(|_| spawner.clone()).collect();
+        for clone in &clones {
+            clone.spawn(async {}).expect("spawn failed");
+        }
+    }
+    harness.run_until_stalled();
+    assert_eq!(
+        harness.handle_refcount(),
+        Some(baseline),
+        "handle refcount is unbalanced after cloning, spawning and dropping"
+    );
+
+    drop(spawner);
+    assert_eq!(
+        harness.handle_refcount(),
+        Some(baseline - 1),
+        "dropping the last spawner didn't release its reference"
+    );
+}
+
+/// A task whose future panics while it's being constructed is handed back through `cancel_spawn`
+/// without unbalancing the handle's refcount, and the spawner keeps working afterwards.
+pub fn check_cancel_spawn<H: Harness>(harness: &mut H) {
+    let spawner = harness.spawner();
+    let baseline = harness.handle_refcount();
+    let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+        // Unwinds without running the panic hook, to keep the test output clean.
+        spawner.spawn_fn(|| -> YieldNow { std::panic::resume_unwind(alloc::boxed::Box::new(())) })
+    }));
+    assert!(result.is_err(), "panic constructing the future was caught");
+    assert_eq!(
+        harness.handle_refcount(),
+        baseline,
+        "handle refcount is unbalanced after a cancelled spawn"
+    );
+    drop(spawner);
+    check_spawn_runs(harness);
+}
+
+/// Tasks still pending when the executor shuts down are dropped with it, spawning through a
+/// spawner that outlived the executor doesn't panic, and if it fails, it fails with
+/// `SpawnError::Shutdown`. Skipped if the harness can't shut the executor down.
+pub fn check_shutdown<H: Harness>(harness: &mut H) {
+    let spawner = harness.spawner();
+    let pending = Rc::new(());
+    let pending2 = pending.clone();
+    spawner
+        .spawn(async move {
+            let _pending = pending2;
+            core::future::pending::<()>().await;
+        })
+        .expect("spawn failed");
+    harness.run_until_stalled();
+    if !harness.shut_down() {
+        return;
+    }
+    assert_eq!(
+        Rc::strong_count(&pending),
+        1,
+        "pending task was not dropped when the executor shut down"
+    );
+
+    let clone = spawner.clone();
+    match clone.spawn(async {}) {
+        Ok(()) | Err(SpawnError::Shutdown) => {}
+        Err(e) => panic!("spawning after shutdown failed with {e:?} instead of `Shutdown`"),
+    }
+    drop(spawner);
+    drop(clone);
+}
+
+/// A payload of `SIZE` bytes aligned to `A`.
+#[repr(C)]
+struct Payload<A, const SIZE: usize> {
+    _align: [A; 0],
+    bytes: [u8; SIZE],
+}
+
+macro_rules! aligned {
+    ($($n:literal => $name:ident),*) => {
+        $(
+            #[repr(align($n))]
+            struct $name;
+        )*
+    };
+}
+aligned!(1 => A1, 2 => A2, 4 => A4, 8 => A8, 16 => A16, 32 => A32, 64 => A64, 128 => A128, 4096 => A4096);
+
+fn spawn_checked<A: 'static, const SIZE: usize>(
+    spawner: &LocalSpawner,
+    completed: &Rc<Cell<usize>>,
+) {
+    let mut payload = Payload::<A, SIZE> {
+        _align: [],
+        bytes: [0; SIZE],
+    };
+    for (i, byte) in payload.bytes.iter_mut().enumerate() {
+        *byte = pattern(i);
+    }
+
+    let completed = completed.clone();
+    spawner
+        .spawn(async move {
+            // Make the executor poll the future a second time before checking its state.
+            YieldNow(false).await;
+
+            let payload = &payload;
+            let align = core::mem::align_of::<Payload<A, SIZE>>();
+            assert_eq!(
+                (payload as *const Payload<A, SIZE>).addr() % align,
+                0,
+                "future of size >= {SIZE} is not aligned to {align}"
+            );
+            for (i, byte) in payload.bytes.iter().enumerate() {
+                assert_eq!(*byte, pattern(i), "future state was corrupted");
+            }
+            completed.set(completed.get() + 1);
+        })
+        .expect("spawn failed");
+}
+
+fn pattern(i: usize) -> u8 {
+    (i.wrapping_mul(31) ^ (i >> 8)) as u8
+}
+
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+std::thread_local! {
+    static ZST_POLLS: Cell<usize> = const { Cell::new(0) };
+}
+
+struct ZeroSized;
+
+impl Future for ZeroSized {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        ZST_POLLS.with(|p| p.set(p.get() + 1));
+        Poll::Ready(())
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+
+    struct FuturesExecutorHarness {
+        pool: Option<futures_executor::LocalPool>,
+        spawner: Rc<futures_executor::LocalSpawner>,
+    }
+
+    impl Harness for FuturesExecutorHarness {
+        fn spawner(&mut self) -> LocalSpawner {
+            LocalSpawner::new(self.spawner.clone())
+        }
+
+        fn run_until_stalled(&mut self) {
+            self.pool.as_mut().unwrap().run_until_stalled();
+        }
+
+        fn handle_refcount(&self) -> Option<usize> {
+            Some(Rc::strong_count(&self.spawner))
+        }
+
+        fn shut_down(&mut self) -> bool {
+            self.pool = None;
+            true
+        }
+    }
+
+    #[test]
+    fn test_futures_executor_complies() {
+        let pool = futures_executor::LocalPool::new();
+        let spawner = Rc::new(pool.spawner());
+        check_all(&mut FuturesExecutorHarness {
+            pool: Some(pool),
+            spawner,
+        });
+    }
+}
@@ -0,0 +1,95 @@
+//! Bridging CPU-bound work on a thread pool back into async code.
+
+use alloc::{boxed::Box, sync::Arc};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use std::{panic, sync::Mutex, thread};
+
+/// A pool of threads that CPU-bound jobs can be sent to, e.g. a rayon thread pool.
+pub trait ComputePool {
+    /// Run `job` on one of the pool's threads.
+    fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>);
+}
+
+/// A `ComputePool` that runs every job on a freshly spawned `std` thread.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ThreadPerJob;
+
+impl ComputePool for ThreadPerJob {
+    fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        thread::spawn(job);
+    }
+}
+
+/// Run `f` on `pool`, returning a future that resolves to its result on the awaiting executor.
+///
+/// If `f` panics, the panic is resumed when the returned future is polled.
+pub fn spawn_compute<P, F, T>(pool: &P, f: F) -> ComputeHandle<T>
+where
+    P: ComputePool + ?Sized,
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        result: None,
+        waker: None,
+    }));
+
+    let job_shared = shared.clone();
+    pool.execute(Box::new(move || {
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+        let waker = {
+            let mut shared = job_shared.lock().unwrap();
+            shared.result = Some(result);
+            shared.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }));
+
+    ComputeHandle { shared }
+}
+
+struct Shared<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// The result of a job started by [`spawn_compute`].
+pub struct ComputeHandle<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> Future for ComputeHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.result.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(panic)) => {
+                drop(shared);
+                panic::resume_unwind(panic)
+            }
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_spawn_compute() {
+        let sum = pollster::block_on(spawn_compute(&ThreadPerJob, || (1..=100u32).sum::<u32>()));
+        assert_eq!(sum, 5050);
+    }
+}
@@ -0,0 +1,135 @@
+//! Context values that flow from a `LocalSpawner` into every task spawned through it, e.g. a
+//! request ID or tenant, so middleware can read them on the other side of an erased spawn.
+
+use crate::LocalSpawner;
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::{
+    any::Any,
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+std::thread_local! {
+    /// The context values of the tasks being polled on this thread, innermost last.
+    static CONTEXT: RefCell<Vec<Rc<dyn Any>>> = const { RefCell::new(Vec::new()) };
+}
+
+impl LocalSpawner {
+    /// Wrap this spawner in one that attaches `value` to every task spawned through it, where
+    /// [`current_context`] returns a clone of it while the task is polled. Wrapping a spawner
+    /// again attaches both values, and of two values of the same type the one attached last wins.
+    ///
+    /// Futures spawned through the returned spawner are boxed before they are handed to this one.
+    pub fn with_context<T: Clone + 'static>(self, value: T) -> LocalSpawner {
+        LocalSpawner::new(WithContext {
+            inner: self,
+            value: Rc::new(value),
+        })
+    }
+}
+
+/// The context value of type `T` attached to the task being polled, see
+/// [`LocalSpawner::with_context`]. `None` outside of such a task.
+pub fn current_context<T: Clone + 'static>() -> Option<T> {
+    CONTEXT.with(|context| {
+        context
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|value| value.downcast_ref::<T>())
+            .cloned()
+    })
+}
+
+struct WithContext {
+    inner: LocalSpawner,
+    value: Rc<dyn Any>,
+}
+
+crate::impl_local_spawner!(WithContext, |this, future| {
+    this.inner.spawn_boxed(Box::pin(InContext {
+        value: this.value.clone(),
+        future,
+    }))
+});
+
+struct InContext {
+    value: Rc<dyn Any>,
+    future: Pin<Box<dyn Future<Output = ()>>>,
+}
+
+impl Future for InContext {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        CONTEXT.with(|context| context.borrow_mut().push(self.value.clone()));
+        // Pops the value again even if the future panics.
+        let _guard = PopOnDrop;
+        self.future.as_mut().poll(cx)
+    }
+}
+
+struct PopOnDrop;
+
+impl Drop for PopOnDrop {
+    fn drop(&mut self) {
+        CONTEXT.with(|context| context.borrow_mut().pop());
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use core::cell::Cell;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct RequestId(u64);
+
+    #[test]
+    fn test_context_flows_into_tasks() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+        let with_request = spawner.clone().with_context(RequestId(7));
+        let with_both = with_request.clone().with_context("tenant");
+
+        let seen = Rc::new(Cell::new(None));
+        let seen2 = seen.clone();
+        let nested = with_request.clone();
+        let seen_nested = Rc::new(Cell::new(None));
+        let seen_nested2 = seen_nested.clone();
+        with_request
+            .spawn(async move {
+                seen2.set(current_context::<RequestId>().map(|id| id.0));
+                nested
+                    .spawn(async move {
+                        seen_nested2.set(current_context::<RequestId>().map(|id| id.0));
+                    })
+                    .unwrap();
+            })
+            .unwrap();
+
+        let tenant = Rc::new(Cell::new(None));
+        let tenant2 = tenant.clone();
+        with_both
+            .spawn(async move {
+                tenant2.set(current_context::<&'static str>());
+                assert_eq!(current_context::<RequestId>(), Some(RequestId(7)));
+            })
+            .unwrap();
+
+        let outside = Rc::new(Cell::new(Some(0)));
+        let outside2 = outside.clone();
+        spawner
+            .spawn(async move { outside2.set(current_context::<RequestId>().map(|id| id.0)) })
+            .unwrap();
+
+        ex.run_until_stalled();
+        assert_eq!(seen.get(), Some(7));
+        assert_eq!(seen_nested.get(), Some(7));
+        assert_eq!(tenant.get(), Some("tenant"));
+        assert_eq!(outside.get(), None);
+        assert_eq!(current_context::<RequestId>(), None);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{Extensions, LocalSpawner};
+use alloc::{boxed::Box, rc::Rc};
+use core::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+impl LocalSpawner {
+    /// Wrap this spawner in one that reports its [`active_tasks`](Self::active_tasks), for
+    /// executors that don't track them natively. Only tasks spawned through the returned spawner
+    /// and its clones are counted. Futures spawned through it are boxed before they are handed to
+    /// this spawner.
+    pub fn counted(self) -> LocalSpawner {
+        LocalSpawner::new(Counted {
+            inner: self,
+            active: Rc::new(Cell::new(0)),
+        })
+    }
+}
+
+struct Counted {
+    inner: LocalSpawner,
+    active: Rc<Cell<usize>>,
+}
+
+crate::impl_local_spawner!(
+    Counted,
+    |this, future| {
+        this.active.set(this.active.get() + 1);
+        this.inner.spawn_boxed(Box::pin(CountedFuture {
+            future: Some(future),
+            active: this.active.clone(),
+        }))
+    },
+    extensions =
+        Extensions::NONE
+            .with_active_tasks(|handle| unsafe { &*(handle as *const Counted) }.active.get()),
+);
+
+/// Stops being counted when its future completes or is dropped, whichever comes first.
+struct CountedFuture {
+    future: Option<Pin<Box<dyn Future<Output = ()>>>>,
+    active: Rc<Cell<usize>>,
+}
+
+impl CountedFuture {
+    fn finish(&mut self) {
+        if self.future.take().is_some() {
+            self.active.set(self.active.get() - 1);
+        }
+    }
+}
+
+impl Future for CountedFuture {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let Some(future) = &mut self.future else {
+            return Poll::Ready(());
+        };
+        let poll = future.as_mut().poll(cx);
+        if poll.is_ready() {
+            self.finish();
+        }
+        poll
+    }
+}
+
+impl Drop for CountedFuture {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Result, SpawnError};
+    use alloc::vec::Vec;
+    use core::{cell::RefCell, task::Waker};
+
+    #[derive(Default)]
+    struct Queue {
+        futures: RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>>,
+        full: Cell<bool>,
+    }
+
+    impl crate::BoxedLocalSpawn for Queue {
+        fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+            if self.full.get() {
+                return Err(SpawnError::Shutdown);
+            }
+            self.futures.borrow_mut().push(future);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_counted() {
+        let queue = Rc::new(Queue::default());
+        let inner = LocalSpawner::new(queue.clone());
+        assert_eq!(inner.active_tasks(), None);
+
+        let spawner = inner.clone().counted();
+        assert_eq!(spawner.active_tasks(), Some(0));
+        spawner.spawn(async {}).unwrap();
+        spawner
+            .clone()
+            .spawn(core::future::pending::<()>())
+            .unwrap();
+        inner.spawn(async {}).unwrap();
+        assert_eq!(spawner.active_tasks(), Some(2));
+
+        queue.full.set(true);
+        assert!(spawner.spawn(async {}).is_err());
+        assert_eq!(spawner.active_tasks(), Some(2));
+
+        let mut futures = queue.futures.take();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(futures[0].as_mut().poll(&mut cx).is_ready());
+        assert_eq!(spawner.active_tasks(), Some(1));
+        assert!(futures[1].as_mut().poll(&mut cx).is_pending());
+        assert_eq!(spawner.active_tasks(), Some(1));
+        drop(futures);
+        assert_eq!(spawner.active_tasks(), Some(0));
+    }
+}
@@ -0,0 +1,84 @@
+//! A per-thread default `LocalSpawner`, so deeply nested library code can spawn without having a
+//! spawner threaded through to it.
+
+use crate::{LocalSpawner, Result, SpawnError, TaskMeta};
+use core::{cell::RefCell, future::Future};
+
+std::thread_local! {
+    static DEFAULT: RefCell<Option<LocalSpawner>> = const { RefCell::new(None) };
+}
+
+/// Make `spawner` this thread's default spawner until the returned guard is dropped, which
+/// restores the previous default.
+pub fn set_default(spawner: LocalSpawner) -> DefaultGuard {
+    let previous = DEFAULT.with(|default| default.borrow_mut().replace(spawner));
+    DefaultGuard { previous }
+}
+
+/// Run `f` with `spawner` as this thread's default spawner.
+pub fn with_default<R>(spawner: LocalSpawner, f: impl FnOnce() -> R) -> R {
+    let _guard = set_default(spawner);
+    f()
+}
+
+/// Spawn a `Future` on this thread's default spawner. Fails with `SpawnError::NoDefault` if there
+/// is none.
+#[track_caller]
+pub fn spawn<F: Future<Output = ()> + 'static>(f: F) -> Result<()> {
+    // Clone the spawner out, so that the executor may reenter this while spawning.
+    match DEFAULT.with(|default| default.borrow().clone()) {
+        Some(spawner) => spawner.spawn_with_meta(f, TaskMeta::new::<F>()),
+        None => Err(SpawnError::NoDefault),
+    }
+}
+
+/// Restores the previous default spawner when dropped, see [`set_default`].
+#[must_use = "the default spawner is reset when the guard is dropped"]
+pub struct DefaultGuard {
+    previous: Option<LocalSpawner>,
+}
+
+impl Drop for DefaultGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        // Drop the replaced spawner outside of the borrow, in case that spawns.
+        let _current =
+            DEFAULT.with(|default| core::mem::replace(&mut *default.borrow_mut(), previous));
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_nested_defaults() {
+        let mut outer_ex = futures_executor::LocalPool::new();
+        let mut inner_ex = futures_executor::LocalPool::new();
+        let outer = LocalSpawner::new(Rc::new(outer_ex.spawner()));
+        let inner = LocalSpawner::new(Rc::new(inner_ex.spawner()));
+
+        assert!(matches!(spawn(async {}), Err(SpawnError::NoDefault)));
+
+        let ran_outer = Rc::new(Cell::new(0));
+        let ran_inner = Rc::new(Cell::new(0));
+        let _guard = set_default(outer);
+        with_default(inner, || {
+            let ran_inner = ran_inner.clone();
+            spawn(async move { ran_inner.set(ran_inner.get() + 1) }).unwrap();
+        });
+        let ran_outer2 = ran_outer.clone();
+        spawn(async move { ran_outer2.set(ran_outer2.get() + 1) }).unwrap();
+
+        inner_ex.run_until_stalled();
+        assert_eq!(ran_inner.get(), 1);
+        assert_eq!(ran_outer.get(), 0);
+        outer_ex.run_until_stalled();
+        assert_eq!(ran_outer.get(), 1);
+
+        drop(_guard);
+        assert!(matches!(spawn(async {}), Err(SpawnError::NoDefault)));
+    }
+}
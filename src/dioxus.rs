@@ -1,36 +1,240 @@
-use crate::{IntoLocalSpawner, Result, SpawnCompleter, SpawnCompleterBuilder};
-use alloc::{alloc::Layout, boxed::Box};
+use crate::{
+    Extensions, IntoLocalSpawner, Result, SpawnCompleter, SpawnError, SpawnSlot, TaskMeta,
+    ZstLocalSpawner,
+};
+use alloc::{alloc::Layout, boxed::Box, rc::Rc};
 use core::future::Future;
+use dioxus::prelude::{Runtime, RuntimeGuard, ScopeId};
 
+/// Spawns onto the Dioxus scope that is current at the time of each spawn.
+///
+/// Spawning fails with [`SpawnError::Shutdown`] when there is no current scope, e.g. outside of a
+/// component. Use [`current`](Self::current) to capture the scope instead.
 #[derive(Copy, Clone, Debug)]
 pub struct DioxusSpawner;
 
+impl DioxusSpawner {
+    /// Capture the current runtime and scope, e.g. in a component, to keep spawning into that
+    /// scope from contexts without one, like handlers of messages from background threads.
+    ///
+    /// Fails with [`SpawnError::Shutdown`] when called outside of a Dioxus runtime or scope.
+    pub fn current() -> Result<DioxusScopeSpawner> {
+        let runtime = Runtime::current().map_err(|_| SpawnError::Shutdown)?;
+        let scope = dioxus::prelude::current_scope_id().map_err(|_| SpawnError::Shutdown)?;
+        Ok(DioxusScopeSpawner::new(runtime, scope))
+    }
+
+    /// Create a spawner that always spawns into `scope` of `runtime`, regardless of which scope is
+    /// current at the time of the spawn.
+    pub fn with_scope(runtime: Rc<Runtime>, scope: ScopeId) -> DioxusScopeSpawner {
+        DioxusScopeSpawner::new(runtime, scope)
+    }
+
+    /// Create a spawner that uses `spawn_forever`, so tasks outlive the current component scope.
+    pub const fn forever() -> DioxusForeverSpawner {
+        DioxusForeverSpawner
+    }
+}
+
 impl IntoLocalSpawner for DioxusSpawner {
+    const EXTENSIONS: Extensions = Extensions::NONE.with_boxed_tasks();
+
     unsafe fn into_handle(self) -> *const () {
         core::ptr::null()
     }
 
     unsafe fn spawn_dyn(
         _handle: *const (),
-        builder: SpawnCompleterBuilder,
+        slot: SpawnSlot,
         future_layout: Layout,
-    ) -> SpawnCompleter {
-        let future_ptr = unsafe { alloc::alloc::alloc(future_layout) } as *mut ();
+    ) -> Result<SpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
         let task_ptr = future_ptr;
-        builder.build(task_ptr, future_ptr)
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
     }
 
     unsafe fn finish_spawn(
         _handle: *const (),
         task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
     ) -> Result<()> {
         let future_box: Box<dyn Future<Output = ()>> =
             unsafe { Box::from_raw(task_ptr_as_dyn_future) };
-        let _ = dioxus::prelude::spawn(Box::into_pin(future_box));
-        Ok(())
+        // `dioxus::prelude::spawn` panics without a current scope.
+        let scope = dioxus::prelude::current_scope_id().map_err(|_| SpawnError::Shutdown)?;
+        match scope.push_future(Box::into_pin(future_box)) {
+            Some(_) => Ok(()),
+            None => Err(SpawnError::Shutdown),
+        }
     }
 
     unsafe fn on_clone(_handle: *const ()) {}
 
     unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(_handle: *const ()) -> Option<Self> {
+        Some(Self)
+    }
+}
+
+// Safety: `DioxusSpawner` never touches its handle.
+unsafe impl ZstLocalSpawner for DioxusSpawner {}
+
+/// Spawns with `dioxus::prelude::spawn_forever`: tasks are owned by the root scope and thus outlive
+/// the component that spawned them, living until the `VirtualDom` is dropped.
+#[derive(Copy, Clone, Debug)]
+pub struct DioxusForeverSpawner;
+
+impl IntoLocalSpawner for DioxusForeverSpawner {
+    const EXTENSIONS: Extensions = Extensions::NONE.with_boxed_tasks();
+
+    unsafe fn into_handle(self) -> *const () {
+        core::ptr::null()
+    }
+
+    unsafe fn spawn_dyn(
+        _handle: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        _handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        let future_box: Box<dyn Future<Output = ()>> =
+            unsafe { Box::from_raw(task_ptr_as_dyn_future) };
+        match dioxus::prelude::spawn_forever(Box::into_pin(future_box)) {
+            Some(_) => Ok(()),
+            None => Err(SpawnError::Shutdown),
+        }
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(_handle: *const ()) -> Option<Self> {
+        Some(Self)
+    }
+}
+
+// Safety: `DioxusForeverSpawner` never touches its handle.
+unsafe impl ZstLocalSpawner for DioxusForeverSpawner {}
+
+/// Spawns into a specific Dioxus scope, matching `Runtime::spawn` semantics: tasks are dropped
+/// when the component that owns the scope is dropped. Spawning fails with
+/// [`SpawnError::Shutdown`] once the scope is gone.
+///
+/// Unlike [`DioxusSpawner`], this can be used from outside of a component's render call, e.g. by
+/// background services that need to spawn UI-bound tasks into a particular component's scope.
+#[derive(Clone)]
+pub struct DioxusScopeSpawner {
+    runtime: Rc<Runtime>,
+    scope: ScopeId,
+}
+
+impl DioxusScopeSpawner {
+    pub fn new(runtime: Rc<Runtime>, scope: ScopeId) -> Self {
+        Self { runtime, scope }
+    }
+}
+
+impl IntoLocalSpawner for DioxusScopeSpawner {
+    const EXTENSIONS: Extensions = Extensions::NONE.with_boxed_tasks();
+
+    unsafe fn into_handle(self) -> *const () {
+        Rc::into_raw(Rc::new(self)) as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        _handle: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        let future_box: Box<dyn Future<Output = ()>> =
+            unsafe { Box::from_raw(task_ptr_as_dyn_future) };
+
+        let this = unsafe { &*(handle as *const DioxusScopeSpawner) };
+        // Enter the runtime, as the spawn may come from outside of it.
+        let _guard = RuntimeGuard::new(this.runtime.clone());
+        match this.scope.push_future(Box::into_pin(future_box)) {
+            Some(_) => Ok(()),
+            None => Err(SpawnError::Shutdown),
+        }
+    }
+
+    unsafe fn on_clone(handle: *const ()) {
+        unsafe { Rc::increment_strong_count(handle as *const DioxusScopeSpawner) }
+    }
+
+    unsafe fn on_drop(handle: *const ()) {
+        unsafe {
+            let _ = Rc::from_raw(handle as *const DioxusScopeSpawner);
+        }
+    }
+
+    unsafe fn from_handle(handle: *const ()) -> Option<Self> {
+        let this = unsafe { Rc::from_raw(handle as *const DioxusScopeSpawner) };
+        Some(Rc::unwrap_or_clone(this))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LocalSpawner;
+    use dioxus::prelude::{Element, VirtualDom};
+
+    fn app() -> Element {
+        Ok(Default::default())
+    }
+
+    #[test]
+    fn test_spawn_outside_runtime_fails() {
+        let spawner = LocalSpawner::new(DioxusSpawner);
+        assert!(matches!(spawner.spawn(async {}), Err(SpawnError::Shutdown)));
+        assert!(matches!(
+            DioxusSpawner::current(),
+            Err(SpawnError::Shutdown)
+        ));
+    }
+
+    #[test]
+    fn test_captured_scope_spawns_outside_runtime() {
+        let dom = VirtualDom::prebuilt(app);
+        let captured = dom.in_runtime(|| ScopeId::ROOT.in_runtime(DioxusSpawner::current));
+        let spawner = LocalSpawner::new(captured.unwrap());
+        spawner.spawn(async {}).unwrap();
+
+        drop(dom);
+        assert!(matches!(spawner.spawn(async {}), Err(SpawnError::Shutdown)));
+    }
 }
@@ -0,0 +1,112 @@
+use crate::{LocalSpawner, Result, TaskMeta};
+use alloc::{boxed::Box, rc::Rc};
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+type Shared = Rc<RefCell<Option<Pin<Box<dyn Future<Output = ()>>>>>>;
+
+impl LocalSpawner {
+    /// Spawn a `Future` with [`SpawnOptions::eager_poll`](crate::SpawnOptions::eager_poll).
+    pub(crate) fn spawn_eager<F: Future<Output = ()> + 'static>(
+        &self,
+        f: F,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        // Instrumented and hooked up here rather than while spawning, so that the eager poll runs
+        // inside them just like the executor's polls do.
+        let future = self.box_task(f, meta);
+
+        // The task is spawned before the future is polled, so that a spawner that doesn't accept
+        // it never runs any of it.
+        let shared: Shared = Rc::new(RefCell::new(Some(future)));
+        let task = Eager(shared.clone());
+        unsafe { self.place(move || task, meta) }?;
+        if let Some(hooks) = self.spawn_hooks {
+            (hooks.on_spawn)(&meta);
+        }
+
+        // Taken out while polling, so that a future that panics is dropped instead of being
+        // polled again by the executor.
+        let Some(mut future) = shared.borrow_mut().take() else {
+            return Ok(());
+        };
+        // Polling with a noop waker loses no wakeups: the executor polls the task once it gets to
+        // it, when the future registers the task's own waker instead.
+        let mut cx = Context::from_waker(Waker::noop());
+        if future.as_mut().poll(&mut cx).is_pending() {
+            *shared.borrow_mut() = Some(future);
+        }
+        Ok(())
+    }
+}
+
+/// The task of an eagerly polled future, which completes right away if the eager poll did.
+struct Eager(Shared);
+
+impl Future for Eager {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut future = self.0.borrow_mut();
+        let Some(inner) = future.as_mut() else {
+            return Poll::Ready(());
+        };
+        let poll = inner.as_mut().poll(cx);
+        if poll.is_ready() {
+            *future = None;
+        }
+        poll
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use crate::{LocalSpawner, SpawnError, SpawnOptions};
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_eager_poll() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+        let eager = SpawnOptions::DEFAULT.with_eager_poll();
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        spawner
+            .spawn_with_options(async move { ran2.set(true) }, eager)
+            .unwrap();
+        assert!(ran.get());
+
+        let (tx, mut rx) = localq::mpsc::channel::<u32>(1);
+        let received = Rc::new(Cell::new(None));
+        let received2 = received.clone();
+        spawner
+            .spawn_with_options(async move { received2.set(rx.recv().await.ok()) }, eager)
+            .unwrap();
+        tx.try_send(7).unwrap();
+        ex.run_until_stalled();
+        assert_eq!(received.get(), Some(7));
+        assert_eq!(Rc::strong_count(&received), 1);
+    }
+
+    #[test]
+    fn test_eager_poll_on_closed_spawner() {
+        let ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner())).closable();
+        spawner.close().unwrap();
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        let eager = SpawnOptions::DEFAULT.with_eager_poll();
+        assert!(matches!(
+            spawner.spawn_with_options(async move { ran2.set(true) }, eager),
+            Err(SpawnError::Shutdown)
+        ));
+        assert!(!ran.get());
+    }
+}
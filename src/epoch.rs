@@ -0,0 +1,97 @@
+use crate::{LocalSpawner, Result, cancel::CancelSignal};
+use alloc::rc::Rc;
+use core::{
+    cell::{Cell, RefCell},
+    future::Future,
+};
+
+/// A `LocalSpawner` wrapper that groups spawned tasks into epochs.
+///
+/// Every task is spawned under the current epoch. Calling [`EpochSpawner::advance_epoch`] cancels
+/// all tasks spawned under the current epoch at once (they are dropped the next time the executor
+/// polls them) and starts a new one. This is useful when e.g. a whole view or route is torn down
+/// and every in-flight fetch for it has to die.
+///
+/// Clones share the same epoch.
+#[derive(Clone)]
+pub struct EpochSpawner {
+    inner: LocalSpawner,
+    state: Rc<EpochState>,
+}
+
+struct EpochState {
+    epoch: Cell<u64>,
+    signal: RefCell<CancelSignal>,
+}
+
+impl EpochSpawner {
+    /// Create a new `EpochSpawner` starting at epoch 0.
+    pub fn new(inner: LocalSpawner) -> Self {
+        Self {
+            inner,
+            state: Rc::new(EpochState {
+                epoch: Cell::new(0),
+                signal: RefCell::new(CancelSignal::new()),
+            }),
+        }
+    }
+
+    /// Spawn a `Future` under the current epoch.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.inner
+            .spawn_guarded(f, &self.state.signal.borrow(), None)
+    }
+
+    /// The current epoch.
+    pub fn epoch(&self) -> u64 {
+        self.state.epoch.get()
+    }
+
+    /// Cancel every task spawned under the current epoch and start a new epoch, returning it.
+    pub fn advance_epoch(&self) -> u64 {
+        let old = self.state.signal.replace(CancelSignal::new());
+        old.cancel();
+
+        let epoch = self.state.epoch.get() + 1;
+        self.state.epoch.set(epoch);
+        epoch
+    }
+
+    /// The wrapped `LocalSpawner`.
+    pub fn inner(&self) -> &LocalSpawner {
+        &self.inner
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use crate::test::{local_pool, pending_task};
+
+    #[test]
+    fn test_advance_epoch_cancels_tasks() {
+        let (mut ex, spawner) = local_pool();
+        let spawner = EpochSpawner::new(spawner);
+
+        let (task, alive) = pending_task();
+        spawner.spawn(task).unwrap();
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 2);
+
+        assert_eq!(spawner.advance_epoch(), 1);
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 1);
+
+        let (result_tx, mut result_rx) = localq::mpsc::channel(1);
+        spawner
+            .spawn(async move {
+                result_tx.try_send(42).unwrap();
+            })
+            .unwrap();
+
+        let result = ex.run_until(async move { result_rx.recv().await });
+
+        assert_eq!(result.unwrap(), 42);
+    }
+}
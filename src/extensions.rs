@@ -0,0 +1,638 @@
+use crate::{Capabilities, InlineFuture, Result, TaskMeta};
+use core::{
+    alloc::Layout,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The optional part of a `LocalSpawner`'s vtable.
+///
+/// `IntoLocalSpawner` implementations only have to provide the core spawn contract. Everything
+/// else an executor may support is described here, and `ispawn` falls back to generic behavior for
+/// whatever an executor leaves out. New entries are added over time, so extensions are built up
+/// from [`Extensions::NONE`]:
+///
+/// ```
+/// # use core::alloc::Layout;
+/// # use ispawn::Extensions;
+/// const EXTENSIONS: Extensions = Extensions::NONE.with_inline_capacity(Layout::new::<[usize; 8]>());
+/// ```
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct Extensions {
+    /// The largest future layout `spawn_dyn` can store inline in a task. Futures whose layout
+    /// doesn't fit are boxed first (with the `alloc` feature), so that `spawn_dyn` only has to
+    /// make room for the box. `None` means that any layout fits.
+    pub inline_capacity: Option<Layout>,
+
+    /// Polls whether the executor has begun shutting down, registering the context's waker to be
+    /// woken when it does. Once this returns `Ready` it must keep doing so.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    pub poll_closed: Option<unsafe fn(handle: *const (), cx: &mut Context<'_>) -> Poll<()>>,
+
+    /// Stops the executor from accepting new tasks, for
+    /// [`LocalSpawner::close`](crate::LocalSpawner::close). Afterwards spawning must fail with
+    /// `SpawnError::Shutdown`, and `poll_closed` must return `Ready`, so it has to be provided as
+    /// well. Tasks that were already spawned are up to the executor.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    pub close: Option<unsafe fn(handle: *const ())>,
+
+    /// The number of tasks that have been spawned and haven't yet completed or been dropped, for
+    /// [`LocalSpawner::active_tasks`](crate::LocalSpawner::active_tasks).
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    pub active_tasks: Option<unsafe fn(handle: *const ()) -> usize>,
+
+    /// Whether `spawn_dyn` allocates every task as a `Box` of its future with the global
+    /// allocator, so that `task_ptr == future_ptr` and `finish_spawn` can take over a future that
+    /// was boxed elsewhere. [`LocalSpawner::spawn_boxed`](crate::LocalSpawner::spawn_boxed) then
+    /// hands its box over as is instead of allocating a task to move it into.
+    pub boxed_tasks: bool,
+
+    /// Support for [`WeakLocalSpawner`](crate::WeakLocalSpawner)s, which don't keep the executor
+    /// alive. Without it, a downgraded spawner holds on to a strong handle.
+    pub weak: Option<WeakOps>,
+
+    /// A human-readable name for the executor, e.g. `"tokio::LocalSet"`, shown by the spawner's
+    /// `Debug` and `Display` impls. `None` means the type name of the spawner is used instead.
+    pub name: Option<&'static str>,
+
+    /// Whether `finish_spawn` honors the [`Priority`](crate::Priority) of
+    /// [`TaskMeta::options`](crate::TaskMeta::options). Other executors spawn every task at the
+    /// same priority.
+    pub priorities: bool,
+
+    /// Whether `finish_spawn` attaches the name of
+    /// [`TaskMeta::options`](crate::TaskMeta::options) to the task, e.g. for the executor's task
+    /// dumps.
+    pub names: bool,
+
+    /// Capabilities the executor reports beyond those described by the other entries, e.g.
+    /// [`Capabilities::ZERO_ALLOC`].
+    pub capabilities: Capabilities,
+
+    /// Makes room for at least `additional` more tasks ahead of a batch of spawns, e.g. by growing
+    /// the run queue once, see [`LocalSpawner::spawn_iter`](crate::LocalSpawner::spawn_iter).
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    pub reserve: Option<unsafe fn(handle: *const (), additional: usize)>,
+
+    /// Polls whether the executor has room for another task, registering the context's waker to
+    /// be woken once it might, for executors with a fixed capacity that otherwise fail with
+    /// `SpawnError::QueueFull`. See
+    /// [`LocalSpawner::spawn_when_ready`](crate::LocalSpawner::spawn_when_ready).
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    pub poll_capacity: Option<unsafe fn(handle: *const (), cx: &mut Context<'_>) -> Poll<()>>,
+
+    /// Spawns a future that fits in an [`InlineFuture`] without a separate allocation for it,
+    /// instead of going through `spawn_dyn`. Meant for executors that can't split spawning into
+    /// two phases, but can spawn a concrete future type.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    pub spawn_inline: Option<SpawnInline>,
+
+    /// Creates a future that completes once `duration` has passed on the executor's timer, for
+    /// [`LocalSpawner::spawn_after`](crate::LocalSpawner::spawn_after). The future is only polled
+    /// by the executor, but may outlive `handle`.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    #[cfg(feature = "alloc")]
+    pub sleep: Option<unsafe fn(handle: *const (), duration: core::time::Duration) -> Sleep>,
+
+    /// Runs a blocking job on the executor's blocking thread pool, for
+    /// [`LocalSpawner::spawn_blocking`](crate::LocalSpawner::spawn_blocking). The job may outlive
+    /// `handle`.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    #[cfg(feature = "alloc")]
+    pub spawn_blocking: Option<SpawnBlocking>,
+
+    /// Spawns a boxed future as a task the executor can abort itself, for
+    /// [`LocalSpawner::spawn_abortable`](crate::LocalSpawner::spawn_abortable), returning a
+    /// function that aborts it. Aborting a task that already completed, or aborting it more than
+    /// once, must do nothing. Reported as [`Capabilities::SUPPORTS_ABORT`].
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    #[cfg(feature = "alloc")]
+    pub spawn_abortable: Option<SpawnAbortable>,
+
+    /// Spawns a boxed future as a task whose output the executor hands back through its own join
+    /// handle, for [`LocalSpawner::spawn_with_handle`](crate::LocalSpawner::spawn_with_handle).
+    /// The returned [`JoinTask`] resolves to the future's output, or to
+    /// [`JoinError::Cancelled`](crate::JoinError::Cancelled) if the task was dropped first, and
+    /// must detach the task when dropped. Reported as [`Capabilities::SUPPORTS_JOIN`].
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    #[cfg(feature = "alloc")]
+    pub spawn_joinable: Option<SpawnJoinable>,
+
+    /// Runs the executor on the current thread until `future` completes, for
+    /// [`LocalSpawner::block_on`](crate::LocalSpawner::block_on). Fails if the executor can't be
+    /// run from here, e.g. because it's already running.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    pub block_on: Option<BlockOn>,
+}
+
+/// A future returned by [`Extensions::sleep`].
+#[cfg(feature = "alloc")]
+pub type Sleep = core::pin::Pin<alloc::boxed::Box<dyn core::future::Future<Output = ()>>>;
+
+/// A job passed to [`Extensions::spawn_blocking`].
+#[cfg(feature = "alloc")]
+pub type BlockingJob = alloc::boxed::Box<dyn FnOnce() + Send + 'static>;
+
+/// A function aborting a task, returned by [`Extensions::spawn_abortable`].
+#[cfg(feature = "alloc")]
+pub type AbortTask = alloc::rc::Rc<dyn Fn()>;
+
+/// An executor's own join handle for a task spawned through [`Extensions::spawn_joinable`].
+#[cfg(feature = "alloc")]
+pub type JoinTask = Pin<
+    alloc::boxed::Box<
+        dyn core::future::Future<Output = core::result::Result<crate::TaskOutput, crate::JoinError>>,
+    >,
+>;
+
+#[cfg(feature = "alloc")]
+pub(crate) type SpawnBlocking = unsafe fn(handle: *const (), job: BlockingJob) -> Result<()>;
+
+#[cfg(feature = "alloc")]
+type SpawnJoinable = unsafe fn(
+    handle: *const (),
+    future: Pin<alloc::boxed::Box<dyn core::future::Future<Output = crate::TaskOutput>>>,
+    meta: TaskMeta,
+) -> Result<JoinTask>;
+
+#[cfg(feature = "alloc")]
+type SpawnAbortable = unsafe fn(
+    handle: *const (),
+    future: Pin<alloc::boxed::Box<dyn core::future::Future<Output = ()>>>,
+    meta: TaskMeta,
+) -> Result<AbortTask>;
+
+type BlockOn = unsafe fn(
+    handle: *const (),
+    future: Pin<&mut dyn core::future::Future<Output = ()>>,
+) -> Result<()>;
+
+type SpawnInline = unsafe fn(handle: *const (), future: InlineFuture, meta: TaskMeta) -> Result<()>;
+
+/// Weak reference counting of a spawner's handle, see [`Extensions::weak`].
+#[derive(Copy, Clone, Debug)]
+pub struct WeakOps {
+    /// Create a weak handle from a live handle.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    pub downgrade: unsafe fn(handle: *const ()) -> *const (),
+
+    /// Create a live handle from a weak handle, or return `None` if the spawner is gone.
+    ///
+    /// # Safety
+    ///
+    /// `weak` must have been returned by `downgrade` and not yet released by `drop_weak`.
+    pub upgrade: unsafe fn(weak: *const ()) -> Option<*const ()>,
+
+    /// # Safety
+    ///
+    /// `weak` must have been returned by `downgrade` and not yet released by `drop_weak`.
+    pub clone_weak: unsafe fn(weak: *const ()),
+
+    /// # Safety
+    ///
+    /// `weak` must have been returned by `downgrade`, and must not be used again by the caller
+    /// after this returns.
+    pub drop_weak: unsafe fn(weak: *const ()),
+}
+
+#[cfg(feature = "alloc")]
+impl WeakOps {
+    /// Weak reference counting for handles created with `Rc::<T>::into_raw`.
+    pub const fn rc<T: 'static>() -> Self {
+        use alloc::rc::{Rc, Weak};
+        use core::mem::ManuallyDrop;
+
+        unsafe fn downgrade<T>(handle: *const ()) -> *const () {
+            let rc = ManuallyDrop::new(unsafe { Rc::from_raw(handle as *const T) });
+            Weak::into_raw(Rc::downgrade(&rc)) as *const ()
+        }
+
+        unsafe fn upgrade<T>(weak: *const ()) -> Option<*const ()> {
+            let weak = ManuallyDrop::new(unsafe { Weak::from_raw(weak as *const T) });
+            weak.upgrade().map(|rc| Rc::into_raw(rc) as *const ())
+        }
+
+        unsafe fn clone_weak<T>(weak: *const ()) {
+            let weak = ManuallyDrop::new(unsafe { Weak::from_raw(weak as *const T) });
+            let _ = Weak::into_raw(Weak::clone(&weak));
+        }
+
+        unsafe fn drop_weak<T>(weak: *const ()) {
+            drop(unsafe { Weak::from_raw(weak as *const T) });
+        }
+
+        Self {
+            downgrade: downgrade::<T>,
+            upgrade: upgrade::<T>,
+            clone_weak: clone_weak::<T>,
+            drop_weak: drop_weak::<T>,
+        }
+    }
+
+    /// Weak reference counting for handles created with `Arc::<T>::into_raw`.
+    #[cfg(target_has_atomic = "ptr")]
+    pub const fn arc<T: 'static>() -> Self {
+        use alloc::sync::{Arc, Weak};
+        use core::mem::ManuallyDrop;
+
+        unsafe fn downgrade<T>(handle: *const ()) -> *const () {
+            let arc = ManuallyDrop::new(unsafe { Arc::from_raw(handle as *const T) });
+            Weak::into_raw(Arc::downgrade(&arc)) as *const ()
+        }
+
+        unsafe fn upgrade<T>(weak: *const ()) -> Option<*const ()> {
+            let weak = ManuallyDrop::new(unsafe { Weak::from_raw(weak as *const T) });
+            weak.upgrade().map(|arc| Arc::into_raw(arc) as *const ())
+        }
+
+        unsafe fn clone_weak<T>(weak: *const ()) {
+            let weak = ManuallyDrop::new(unsafe { Weak::from_raw(weak as *const T) });
+            let _ = Weak::into_raw(Weak::clone(&weak));
+        }
+
+        unsafe fn drop_weak<T>(weak: *const ()) {
+            drop(unsafe { Weak::from_raw(weak as *const T) });
+        }
+
+        Self {
+            downgrade: downgrade::<T>,
+            upgrade: upgrade::<T>,
+            clone_weak: clone_weak::<T>,
+            drop_weak: drop_weak::<T>,
+        }
+    }
+
+    /// Weak reference counting for handles created with `portable-atomic-util`'s
+    /// `Arc::<T>::into_raw`.
+    #[cfg(feature = "portable-atomic")]
+    pub const fn portable_arc<T: 'static>() -> Self {
+        use core::mem::ManuallyDrop;
+        use portable_atomic_util::{Arc, Weak};
+
+        unsafe fn downgrade<T>(handle: *const ()) -> *const () {
+            let arc = ManuallyDrop::new(unsafe { Arc::from_raw(handle as *const T) });
+            Weak::into_raw(Arc::downgrade(&arc)) as *const ()
+        }
+
+        unsafe fn upgrade<T>(weak: *const ()) -> Option<*const ()> {
+            let weak = ManuallyDrop::new(unsafe { Weak::from_raw(weak as *const T) });
+            weak.upgrade().map(|arc| Arc::into_raw(arc) as *const ())
+        }
+
+        unsafe fn clone_weak<T>(weak: *const ()) {
+            let weak = ManuallyDrop::new(unsafe { Weak::from_raw(weak as *const T) });
+            let _ = Weak::into_raw(Weak::clone(&weak));
+        }
+
+        unsafe fn drop_weak<T>(weak: *const ()) {
+            drop(unsafe { Weak::from_raw(weak as *const T) });
+        }
+
+        Self {
+            downgrade: downgrade::<T>,
+            upgrade: upgrade::<T>,
+            clone_weak: clone_weak::<T>,
+            drop_weak: drop_weak::<T>,
+        }
+    }
+}
+
+impl WeakOps {
+    /// Weak handles for handles that are never released, such as `&'static T`, which can always be
+    /// upgraded.
+    pub const STATIC: Self = Self {
+        downgrade: |handle| handle,
+        upgrade: |weak| Some(weak),
+        clone_weak: |_| {},
+        drop_weak: |_| {},
+    };
+}
+
+impl Extensions {
+    /// No optional capabilities.
+    pub const NONE: Self = Self {
+        inline_capacity: None,
+        poll_closed: None,
+        close: None,
+        active_tasks: None,
+        boxed_tasks: false,
+        weak: None,
+        name: None,
+        priorities: false,
+        names: false,
+        capabilities: Capabilities::empty(),
+        reserve: None,
+        poll_capacity: None,
+        spawn_inline: None,
+        #[cfg(feature = "alloc")]
+        sleep: None,
+        #[cfg(feature = "alloc")]
+        spawn_blocking: None,
+        #[cfg(feature = "alloc")]
+        spawn_abortable: None,
+        #[cfg(feature = "alloc")]
+        spawn_joinable: None,
+        block_on: None,
+    };
+
+    /// Set [`Extensions::inline_capacity`].
+    pub const fn with_inline_capacity(mut self, capacity: Layout) -> Self {
+        self.inline_capacity = Some(capacity);
+        self
+    }
+
+    /// Set [`Extensions::poll_closed`].
+    pub const fn with_poll_closed(
+        mut self,
+        poll_closed: unsafe fn(handle: *const (), cx: &mut Context<'_>) -> Poll<()>,
+    ) -> Self {
+        self.poll_closed = Some(poll_closed);
+        self
+    }
+
+    /// Set [`Extensions::close`].
+    pub const fn with_close(mut self, close: unsafe fn(handle: *const ())) -> Self {
+        self.close = Some(close);
+        self
+    }
+
+    /// Set [`Extensions::active_tasks`].
+    pub const fn with_active_tasks(
+        mut self,
+        active_tasks: unsafe fn(handle: *const ()) -> usize,
+    ) -> Self {
+        self.active_tasks = Some(active_tasks);
+        self
+    }
+
+    /// Set [`Extensions::boxed_tasks`].
+    pub const fn with_boxed_tasks(mut self) -> Self {
+        self.boxed_tasks = true;
+        self
+    }
+
+    /// Set [`Extensions::weak`].
+    pub const fn with_weak(mut self, weak: WeakOps) -> Self {
+        self.weak = Some(weak);
+        self
+    }
+
+    /// Set [`Extensions::name`].
+    pub const fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Set [`Extensions::priorities`].
+    pub const fn with_priorities(mut self) -> Self {
+        self.priorities = true;
+        self
+    }
+
+    /// Set [`Extensions::names`].
+    pub const fn with_names(mut self) -> Self {
+        self.names = true;
+        self
+    }
+
+    /// Add to [`Extensions::capabilities`].
+    pub const fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = self.capabilities.union(capabilities);
+        self
+    }
+
+    /// Set [`Extensions::reserve`].
+    pub const fn with_reserve(
+        mut self,
+        reserve: unsafe fn(handle: *const (), additional: usize),
+    ) -> Self {
+        self.reserve = Some(reserve);
+        self
+    }
+
+    /// Set [`Extensions::poll_capacity`].
+    pub const fn with_poll_capacity(
+        mut self,
+        poll_capacity: unsafe fn(handle: *const (), cx: &mut Context<'_>) -> Poll<()>,
+    ) -> Self {
+        self.poll_capacity = Some(poll_capacity);
+        self
+    }
+
+    /// Set [`Extensions::spawn_inline`].
+    pub const fn with_spawn_inline(
+        mut self,
+        spawn_inline: unsafe fn(
+            handle: *const (),
+            future: InlineFuture,
+            meta: TaskMeta,
+        ) -> Result<()>,
+    ) -> Self {
+        self.spawn_inline = Some(spawn_inline);
+        self
+    }
+
+    /// Set [`Extensions::sleep`].
+    #[cfg(feature = "alloc")]
+    pub const fn with_sleep(
+        mut self,
+        sleep: unsafe fn(handle: *const (), duration: core::time::Duration) -> Sleep,
+    ) -> Self {
+        self.sleep = Some(sleep);
+        self
+    }
+
+    /// Set [`Extensions::spawn_blocking`].
+    #[cfg(feature = "alloc")]
+    pub const fn with_spawn_blocking(mut self, spawn_blocking: SpawnBlocking) -> Self {
+        self.spawn_blocking = Some(spawn_blocking);
+        self
+    }
+
+    /// Set [`Extensions::spawn_abortable`].
+    #[cfg(feature = "alloc")]
+    pub const fn with_spawn_abortable(mut self, spawn_abortable: SpawnAbortable) -> Self {
+        self.spawn_abortable = Some(spawn_abortable);
+        self
+    }
+
+    /// Set [`Extensions::spawn_joinable`].
+    #[cfg(feature = "alloc")]
+    pub const fn with_spawn_joinable(mut self, spawn_joinable: SpawnJoinable) -> Self {
+        self.spawn_joinable = Some(spawn_joinable);
+        self
+    }
+
+    /// Set [`Extensions::block_on`].
+    pub const fn with_block_on(mut self, block_on: BlockOn) -> Self {
+        self.block_on = Some(block_on);
+        self
+    }
+
+    /// Everything the executor reports supporting, including what the other entries describe.
+    pub(crate) fn all_capabilities(&self) -> Capabilities {
+        let mut capabilities = self.capabilities;
+        if self.names {
+            capabilities |= Capabilities::SUPPORTS_NAMES;
+        }
+        if self.priorities {
+            capabilities |= Capabilities::SUPPORTS_PRIORITY;
+        }
+        #[cfg(feature = "alloc")]
+        if self.spawn_abortable.is_some() {
+            capabilities |= Capabilities::SUPPORTS_ABORT;
+        }
+        #[cfg(feature = "alloc")]
+        if self.spawn_joinable.is_some() {
+            capabilities |= Capabilities::SUPPORTS_JOIN;
+        }
+        capabilities
+    }
+
+    pub(crate) fn fits_inline(&self, layout: Layout) -> bool {
+        fits_inline(self.inline_capacity, layout)
+    }
+}
+
+/// The optional part of a [`Spawner`](crate::Spawner)'s vtable, the `Send` counterpart of
+/// [`Extensions`].
+///
+/// Only entries that make sense for a spawner shared across threads are here: the others either
+/// hand back `!Send` values or have no `Spawner` API behind them.
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct SendExtensions {
+    /// See [`Extensions::inline_capacity`].
+    pub inline_capacity: Option<Layout>,
+
+    /// See [`Extensions::name`].
+    pub name: Option<&'static str>,
+
+    /// See [`Extensions::priorities`].
+    pub priorities: bool,
+
+    /// See [`Extensions::names`].
+    pub names: bool,
+
+    /// See [`Extensions::capabilities`].
+    pub capabilities: Capabilities,
+
+    /// Runs a blocking job on the executor's blocking thread pool, for
+    /// [`Spawner::spawn_blocking`](crate::Spawner::spawn_blocking). The job may outlive `handle`.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    #[cfg(feature = "alloc")]
+    pub spawn_blocking: Option<SpawnBlocking>,
+}
+
+impl SendExtensions {
+    /// No optional capabilities.
+    pub const NONE: Self = Self {
+        inline_capacity: None,
+        name: None,
+        priorities: false,
+        names: false,
+        capabilities: Capabilities::empty(),
+        #[cfg(feature = "alloc")]
+        spawn_blocking: None,
+    };
+
+    /// Set [`SendExtensions::inline_capacity`].
+    pub const fn with_inline_capacity(mut self, capacity: Layout) -> Self {
+        self.inline_capacity = Some(capacity);
+        self
+    }
+
+    /// Set [`SendExtensions::name`].
+    pub const fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Set [`SendExtensions::priorities`].
+    pub const fn with_priorities(mut self) -> Self {
+        self.priorities = true;
+        self
+    }
+
+    /// Set [`SendExtensions::names`].
+    pub const fn with_names(mut self) -> Self {
+        self.names = true;
+        self
+    }
+
+    /// Add to [`SendExtensions::capabilities`].
+    pub const fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = self.capabilities.union(capabilities);
+        self
+    }
+
+    /// Set [`SendExtensions::spawn_blocking`].
+    #[cfg(feature = "alloc")]
+    pub const fn with_spawn_blocking(mut self, spawn_blocking: SpawnBlocking) -> Self {
+        self.spawn_blocking = Some(spawn_blocking);
+        self
+    }
+
+    /// Everything the executor reports supporting, including what the other entries describe.
+    pub(crate) fn all_capabilities(&self) -> Capabilities {
+        let mut capabilities = self.capabilities;
+        if self.names {
+            capabilities |= Capabilities::SUPPORTS_NAMES;
+        }
+        if self.priorities {
+            capabilities |= Capabilities::SUPPORTS_PRIORITY;
+        }
+        capabilities
+    }
+
+    pub(crate) fn fits_inline(&self, layout: Layout) -> bool {
+        fits_inline(self.inline_capacity, layout)
+    }
+}
+
+fn fits_inline(capacity: Option<Layout>, layout: Layout) -> bool {
+    match capacity {
+        Some(capacity) => layout.size() <= capacity.size() && layout.align() <= capacity.align(),
+        None => true,
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{LocalSpawner, Result, TaskMeta};
+use core::{fmt::Debug, future::Future};
+
+/// Called with the metadata of a task that failed and the error it returned.
+pub type ErrorHandler = fn(&TaskMeta, &dyn Debug);
+
+/// A spawner for fallible background tasks, which forwards the errors they return to a handler,
+/// e.g. one that logs them.
+///
+/// This saves every library that spawns `Result`-returning futures from writing its own "log and
+/// swallow" wrapper.
+#[derive(Clone)]
+pub struct FallibleSpawner {
+    inner: LocalSpawner,
+    handler: ErrorHandler,
+}
+
+impl FallibleSpawner {
+    /// Forward errors of tasks spawned onto `inner` to `handler`.
+    pub fn new(inner: LocalSpawner, handler: ErrorHandler) -> Self {
+        Self { inner, handler }
+    }
+
+    /// Report errors to stderr.
+    #[cfg(feature = "std")]
+    pub fn logging(inner: LocalSpawner) -> Self {
+        Self::new(inner, |meta, error| {
+            std::eprintln!(
+                "ispawn: task `{}` spawned at {} failed: {error:?}",
+                meta.type_name(),
+                meta.location(),
+            )
+        })
+    }
+
+    /// Spawn a `Future` whose error, if it returns one, is passed to the handler.
+    #[track_caller]
+    pub fn spawn_result<F, E>(&self, f: F) -> Result<()>
+    where
+        F: Future<Output = core::result::Result<(), E>> + 'static,
+        E: Debug,
+    {
+        let meta = TaskMeta::new::<F>();
+        let handler = self.handler;
+        self.inner.spawn_with_meta(
+            async move {
+                if let Err(e) = f.await {
+                    handler(&meta, &e);
+                }
+            },
+            meta,
+        )
+    }
+
+    /// Spawn an infallible `Future`.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.inner.spawn_with_meta(f, TaskMeta::new::<F>())
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor", feature = "std"))]
+mod test {
+    use super::*;
+    use alloc::{format, rc::Rc, string::String};
+    use core::cell::RefCell;
+    use futures_executor::LocalPool;
+
+    std::thread_local! {
+        static ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+    }
+
+    #[test]
+    fn test_errors_are_forwarded() {
+        let mut ex = LocalPool::new();
+        let spawner = FallibleSpawner::new(LocalSpawner::new(Rc::new(ex.spawner())), |_, error| {
+            ERROR.set(Some(format!("{error:?}")))
+        });
+
+        spawner.spawn_result(async { Ok::<(), &str>(()) }).unwrap();
+        ex.run();
+        assert_eq!(ERROR.take(), None);
+
+        spawner
+            .spawn_result(async { Err("connection reset") })
+            .unwrap();
+        ex.run();
+        assert_eq!(ERROR.take().as_deref(), Some("\"connection reset\""));
+    }
+}
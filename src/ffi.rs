@@ -0,0 +1,379 @@
+//! A C-compatible form of `LocalSpawner`, for handing spawners across dynamic-library or FFI
+//! boundaries, e.g. from a host application to plugins loaded as `cdylib`s.
+//!
+//! `LocalSpawner`'s own vtable uses the Rust ABI and Rust types, which aren't stable between
+//! separately compiled binaries. [`LocalSpawner::into_ffi`] instead wraps a spawner into an
+//! [`FfiLocalSpawner`], whose vtable only has `extern "C"` functions taking `#[repr(C)]` types, and
+//! [`LocalSpawner::from_ffi`] turns one back into a `LocalSpawner` on the other side. Futures cross
+//! the boundary as [`FfiFuture`]s, polled with [`FfiWaker`]s.
+//!
+//! The layout of every type in this module is part of the ABI, versioned by [`FFI_ABI_VERSION`].
+//! A panic can't unwind through the `extern "C"` functions, and aborts the process instead.
+
+use crate::{BoxedLocalSpawn, LocalSpawner, Result, SpawnError};
+use alloc::{boxed::Box, rc::Rc};
+use core::{
+    future::Future,
+    mem::ManuallyDrop,
+    pin::Pin,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+/// The version of the ABI of this module, checked by [`LocalSpawner::from_ffi`].
+pub const FFI_ABI_VERSION: u32 = 1;
+
+/// A spawner that can cross FFI boundaries, created by [`LocalSpawner::into_ffi`].
+///
+/// Like `LocalSpawner`, it must only be used on the thread it was created on.
+#[repr(C)]
+pub struct FfiLocalSpawner {
+    pub handle: *const (),
+    pub vtable: &'static FfiLocalSpawnerVtable,
+}
+
+#[repr(C)]
+pub struct FfiLocalSpawnerVtable {
+    /// [`FFI_ABI_VERSION`] of the side that created the spawner.
+    pub abi_version: u32,
+    /// Spawn `future`, taking ownership of it either way. Returns one of the `FFI_SPAWN_*`
+    /// status codes.
+    pub spawn: unsafe extern "C" fn(handle: *const (), future: FfiFuture) -> u32,
+    /// Create a new handle to the same spawner.
+    pub clone: unsafe extern "C" fn(handle: *const ()) -> *const (),
+    pub drop: unsafe extern "C" fn(handle: *const ()),
+}
+
+/// The future was spawned.
+pub const FFI_SPAWN_OK: u32 = 0;
+/// See [`SpawnError::Shutdown`].
+pub const FFI_SPAWN_SHUTDOWN: u32 = 1;
+/// See [`SpawnError::QueueFull`].
+pub const FFI_SPAWN_QUEUE_FULL: u32 = 2;
+/// See [`SpawnError::AllocFailed`].
+pub const FFI_SPAWN_ALLOC_FAILED: u32 = 3;
+/// Any other error.
+pub const FFI_SPAWN_OTHER: u32 = 4;
+
+/// An owned, type-erased future that can cross FFI boundaries.
+#[repr(C)]
+pub struct FfiFuture {
+    pub data: *mut (),
+    /// Poll the future, returning whether it has completed. `waker` is only borrowed for the
+    /// duration of the call. Once this returns `true`, the future is only dropped.
+    pub poll: unsafe extern "C" fn(data: *mut (), waker: *const FfiWaker) -> bool,
+    pub drop: unsafe extern "C" fn(data: *mut ()),
+}
+
+/// An owned or borrowed waker that can cross FFI boundaries.
+#[repr(C)]
+pub struct FfiWaker {
+    pub data: *const (),
+    pub vtable: &'static FfiWakerVtable,
+}
+
+#[repr(C)]
+pub struct FfiWakerVtable {
+    /// Create an owned waker waking the same task.
+    pub clone: unsafe extern "C" fn(data: *const ()) -> FfiWaker,
+    /// Wake the task and release an owned waker.
+    pub wake: unsafe extern "C" fn(data: *const ()),
+    pub wake_by_ref: unsafe extern "C" fn(data: *const ()),
+    /// Release an owned waker.
+    pub drop: unsafe extern "C" fn(data: *const ()),
+}
+
+impl LocalSpawner {
+    /// Wrap the spawner into an [`FfiLocalSpawner`] with a stable ABI.
+    pub fn into_ffi(self) -> FfiLocalSpawner {
+        unsafe extern "C" fn spawn(handle: *const (), future: FfiFuture) -> u32 {
+            let spawner = unsafe { &*(handle as *const LocalSpawner) };
+            match spawner.spawn(FfiTask(future)) {
+                Ok(()) => FFI_SPAWN_OK,
+                Err(SpawnError::Shutdown) => FFI_SPAWN_SHUTDOWN,
+                Err(SpawnError::QueueFull) => FFI_SPAWN_QUEUE_FULL,
+                Err(SpawnError::AllocFailed) => FFI_SPAWN_ALLOC_FAILED,
+                Err(_) => FFI_SPAWN_OTHER,
+            }
+        }
+
+        unsafe extern "C" fn clone(handle: *const ()) -> *const () {
+            let spawner = unsafe { &*(handle as *const LocalSpawner) };
+            Box::into_raw(Box::new(spawner.clone())) as *const ()
+        }
+
+        unsafe extern "C" fn drop(handle: *const ()) {
+            core::mem::drop(unsafe { Box::from_raw(handle as *mut LocalSpawner) });
+        }
+
+        static VTABLE: FfiLocalSpawnerVtable = FfiLocalSpawnerVtable {
+            abi_version: FFI_ABI_VERSION,
+            spawn,
+            clone,
+            drop,
+        };
+
+        FfiLocalSpawner {
+            handle: Box::into_raw(Box::new(self)) as *const (),
+            vtable: &VTABLE,
+        }
+    }
+
+    /// Create a `LocalSpawner` from an [`FfiLocalSpawner`], e.g. one handed over by a host
+    /// application. Every spawned future is boxed into an [`FfiFuture`].
+    ///
+    /// Fails with `SpawnError::Unsupported` if the other side uses a different
+    /// [`FFI_ABI_VERSION`], in which case `spawner` is leaked, since it can't be released safely.
+    ///
+    /// # Safety
+    ///
+    /// `spawner` must have been created by [`into_ffi`](Self::into_ffi), or uphold the same
+    /// contract, on the current thread.
+    pub unsafe fn from_ffi(spawner: FfiLocalSpawner) -> Result<Self> {
+        if spawner.vtable.abi_version != FFI_ABI_VERSION {
+            core::mem::forget(spawner);
+            return Err(SpawnError::Unsupported);
+        }
+        Ok(Self::new(Rc::new(spawner)))
+    }
+}
+
+impl BoxedLocalSpawn for FfiLocalSpawner {
+    fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+        match unsafe { (self.vtable.spawn)(self.handle, FfiFuture::new(future)) } {
+            FFI_SPAWN_OK => Ok(()),
+            FFI_SPAWN_SHUTDOWN => Err(SpawnError::Shutdown),
+            FFI_SPAWN_QUEUE_FULL => Err(SpawnError::QueueFull),
+            FFI_SPAWN_ALLOC_FAILED => Err(SpawnError::AllocFailed),
+            _ => Err(SpawnError::Other),
+        }
+    }
+}
+
+impl Clone for FfiLocalSpawner {
+    fn clone(&self) -> Self {
+        Self {
+            handle: unsafe { (self.vtable.clone)(self.handle) },
+            vtable: self.vtable,
+        }
+    }
+}
+
+impl Drop for FfiLocalSpawner {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.drop)(self.handle) }
+    }
+}
+
+impl FfiFuture {
+    fn new(future: Pin<Box<dyn Future<Output = ()>>>) -> Self {
+        type Boxed = Pin<Box<dyn Future<Output = ()>>>;
+
+        unsafe extern "C" fn poll(data: *mut (), waker: *const FfiWaker) -> bool {
+            let future = unsafe { &mut *(data as *mut Boxed) };
+            let waker = ManuallyDrop::new(unsafe { borrowed_waker(waker) });
+            future
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_ready()
+        }
+
+        unsafe extern "C" fn drop(data: *mut ()) {
+            core::mem::drop(unsafe { Box::from_raw(data as *mut Boxed) });
+        }
+
+        Self {
+            data: Box::into_raw(Box::new(future)) as *mut (),
+            poll,
+            drop,
+        }
+    }
+}
+
+/// An [`FfiFuture`] received from the other side, polled with wakers lent to it.
+struct FfiTask(FfiFuture);
+
+impl Future for FfiTask {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let waker = FfiWaker::borrow(cx.waker());
+        match unsafe { (self.0.poll)(self.0.data, &waker) } {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for FfiTask {
+    fn drop(&mut self) {
+        unsafe { (self.0.drop)(self.0.data) }
+    }
+}
+
+impl FfiWaker {
+    /// Lend `waker` to the other side for the duration of a poll.
+    fn borrow(waker: &Waker) -> Self {
+        unsafe extern "C" fn clone(data: *const ()) -> FfiWaker {
+            FfiWaker::owned(unsafe { &*(data as *const Waker) }.clone())
+        }
+
+        unsafe extern "C" fn wake_by_ref(data: *const ()) {
+            unsafe { &*(data as *const Waker) }.wake_by_ref();
+        }
+
+        // A borrowed waker is never woken by value or released.
+        unsafe extern "C" fn noop(_: *const ()) {}
+
+        static VTABLE: FfiWakerVtable = FfiWakerVtable {
+            clone,
+            wake: wake_by_ref,
+            wake_by_ref,
+            drop: noop,
+        };
+
+        Self {
+            data: waker as *const Waker as *const (),
+            vtable: &VTABLE,
+        }
+    }
+
+    fn owned(waker: Waker) -> Self {
+        unsafe extern "C" fn clone(data: *const ()) -> FfiWaker {
+            FfiWaker::owned(unsafe { &*(data as *const Waker) }.clone())
+        }
+
+        unsafe extern "C" fn wake(data: *const ()) {
+            unsafe { Box::from_raw(data as *mut Waker) }.wake();
+        }
+
+        unsafe extern "C" fn wake_by_ref(data: *const ()) {
+            unsafe { &*(data as *const Waker) }.wake_by_ref();
+        }
+
+        unsafe extern "C" fn drop(data: *const ()) {
+            core::mem::drop(unsafe { Box::from_raw(data as *mut Waker) });
+        }
+
+        static VTABLE: FfiWakerVtable = FfiWakerVtable {
+            clone,
+            wake,
+            wake_by_ref,
+            drop,
+        };
+
+        Self {
+            data: Box::into_raw(Box::new(waker)) as *const (),
+            vtable: &VTABLE,
+        }
+    }
+}
+
+/// A `Waker` for an `FfiWaker` lent by the other side, which must not be dropped.
+unsafe fn borrowed_waker(waker: *const FfiWaker) -> Waker {
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        let waker = unsafe { &*(data as *const FfiWaker) };
+        owned_raw_waker(unsafe { (waker.vtable.clone)(waker.data) })
+    }
+
+    unsafe fn wake_by_ref(data: *const ()) {
+        let waker = unsafe { &*(data as *const FfiWaker) };
+        unsafe { (waker.vtable.wake_by_ref)(waker.data) }
+    }
+
+    unsafe fn noop(_: *const ()) {}
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake_by_ref, wake_by_ref, noop);
+    unsafe { Waker::from_raw(RawWaker::new(waker as *const (), &VTABLE)) }
+}
+
+/// A `RawWaker` taking ownership of an `FfiWaker` from the other side.
+fn owned_raw_waker(waker: FfiWaker) -> RawWaker {
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        let waker = unsafe { &*(data as *const FfiWaker) };
+        owned_raw_waker(unsafe { (waker.vtable.clone)(waker.data) })
+    }
+
+    unsafe fn wake(data: *const ()) {
+        let waker = unsafe { Box::from_raw(data as *mut FfiWaker) };
+        unsafe { (waker.vtable.wake)(waker.data) }
+    }
+
+    unsafe fn wake_by_ref(data: *const ()) {
+        let waker = unsafe { &*(data as *const FfiWaker) };
+        unsafe { (waker.vtable.wake_by_ref)(waker.data) }
+    }
+
+    unsafe fn drop(data: *const ()) {
+        let waker = unsafe { Box::from_raw(data as *mut FfiWaker) };
+        unsafe { (waker.vtable.drop)(waker.data) }
+    }
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+    RawWaker::new(Box::into_raw(Box::new(waker)) as *const (), &VTABLE)
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use core::cell::Cell;
+    use futures_executor::LocalPool;
+
+    #[test]
+    fn test_round_trip() {
+        let mut pool = LocalPool::new();
+        let host = LocalSpawner::new(Rc::new(pool.spawner()));
+        let plugin = unsafe { LocalSpawner::from_ffi(host.into_ffi()) }.unwrap();
+
+        let ran = Rc::new(Cell::new(0));
+        let (tx, mut rx) = localq::mpsc::channel::<()>(1);
+        let ran2 = ran.clone();
+        plugin
+            .spawn(async move {
+                ran2.set(1);
+                rx.recv().await.unwrap();
+                ran2.set(2);
+            })
+            .unwrap();
+        pool.run_until_stalled();
+        assert_eq!(ran.get(), 1);
+
+        // Waking through a cloned `FfiWaker` gets the task polled again.
+        tx.try_send(()).unwrap();
+        pool.run_until_stalled();
+        assert_eq!(ran.get(), 2);
+        assert_eq!(Rc::strong_count(&ran), 1);
+    }
+
+    #[test]
+    fn test_abi_version_mismatch() {
+        static VTABLE: FfiLocalSpawnerVtable = FfiLocalSpawnerVtable {
+            abi_version: FFI_ABI_VERSION + 1,
+            spawn: {
+                unsafe extern "C" fn spawn(_: *const (), _: FfiFuture) -> u32 {
+                    unreachable!()
+                }
+                spawn
+            },
+            clone: {
+                unsafe extern "C" fn clone(_: *const ()) -> *const () {
+                    unreachable!()
+                }
+                clone
+            },
+            drop: {
+                unsafe extern "C" fn drop(_: *const ()) {
+                    unreachable!()
+                }
+                drop
+            },
+        };
+        let spawner = FfiLocalSpawner {
+            handle: core::ptr::null(),
+            vtable: &VTABLE,
+        };
+        assert!(matches!(
+            unsafe { LocalSpawner::from_ffi(spawner) },
+            Err(SpawnError::Unsupported)
+        ));
+    }
+}
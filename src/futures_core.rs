@@ -0,0 +1,88 @@
+use crate::{LocalSpawner, Result, TaskMeta};
+use core::future::{Future, poll_fn};
+use futures_core::Stream;
+
+impl LocalSpawner {
+    /// Spawn every `Future` produced by `stream`, resolving once the stream ends.
+    ///
+    /// Resolves to the error of the first future that fails to spawn, without polling the stream
+    /// any further, so that producers are held back rather than having their futures dropped.
+    #[track_caller]
+    pub fn spawn_all<S>(&self, stream: S) -> impl Future<Output = Result<()>>
+    where
+        S: Stream,
+        S::Item: Future<Output = ()> + 'static,
+    {
+        let meta = TaskMeta::new::<S::Item>();
+        async move {
+            let mut stream = core::pin::pin!(stream);
+            while let Some(f) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+                self.spawn_with_meta(f, meta)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use crate::SpawnError;
+    use alloc::rc::Rc;
+    use core::{
+        cell::Cell,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+    use futures_executor::LocalPool;
+
+    /// Yields `n` futures that each bump `ran`.
+    struct Counter {
+        n: usize,
+        ran: Rc<Cell<usize>>,
+    }
+
+    impl Stream for Counter {
+        type Item = core::future::Ready<()>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if self.n == 0 {
+                return Poll::Ready(None);
+            }
+            self.n -= 1;
+            self.ran.set(self.ran.get() + 1);
+            Poll::Ready(Some(core::future::ready(())))
+        }
+    }
+
+    #[test]
+    fn test_spawn_all() {
+        let mut pool = LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(pool.spawner()));
+
+        let pulled = Rc::new(Cell::new(0));
+        let stream = Counter {
+            n: 3,
+            ran: pulled.clone(),
+        };
+        pool.run_until(spawner.spawn_all(stream)).unwrap();
+        assert_eq!(pulled.get(), 3);
+        pool.run();
+    }
+
+    #[test]
+    fn test_spawn_all_stops_on_error() {
+        let pool = LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(pool.spawner()));
+        drop(pool);
+
+        let pulled = Rc::new(Cell::new(0));
+        let stream = Counter {
+            n: 3,
+            ran: pulled.clone(),
+        };
+        let result = futures_executor::block_on(spawner.spawn_all(stream));
+        assert!(matches!(result, Err(SpawnError::Shutdown)));
+        assert_eq!(pulled.get(), 1);
+    }
+}
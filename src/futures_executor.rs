@@ -1,59 +1,58 @@
-use crate::{IntoLocalSpawner, Result, SpawnCompleter, SpawnCompleterBuilder, SpawnError};
-use alloc::{alloc::Layout, boxed::Box, rc::Rc};
-use core::future::Future;
+use crate::{Extensions, SpawnError};
+use alloc::rc::{Rc, Weak};
+use core::cell::RefCell;
+use futures_executor::LocalPool;
 
-impl IntoLocalSpawner for Rc<futures_executor::LocalSpawner> {
-    unsafe fn into_handle(self) -> *const () {
-        Rc::into_raw(self) as *const ()
-    }
-
-    unsafe fn spawn_dyn(
-        _: *const (),
-        builder: SpawnCompleterBuilder,
-        future_layout: Layout,
-    ) -> SpawnCompleter {
-        let future_ptr = unsafe { alloc::alloc::alloc(future_layout) } as *mut ();
-        let task_ptr = future_ptr;
-        builder.build(task_ptr, future_ptr)
-    }
-
-    unsafe fn finish_spawn(
-        handle: *const (),
-        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
-    ) -> Result<()> {
-        use futures_task::LocalSpawn;
+crate::impl_local_spawner!(Rc<futures_executor::LocalSpawner>, |this, future| {
+    use futures_task::LocalSpawn;
 
-        let future_box: Box<dyn Future<Output = ()>> =
-            unsafe { Box::from_raw(task_ptr_as_dyn_future) };
-        let future_obj: futures_task::LocalFutureObj<()> = future_box.into();
-
-        let this = unsafe { &*(handle as *const futures_executor::LocalSpawner) };
-        this.spawn_local_obj(future_obj).map_err(|e| {
-            if e.is_shutdown() {
-                SpawnError::Shutdown
-            } else {
-                SpawnError::Other
-            }
-        })
-    }
+    this.spawn_local_obj(future.into())
+        .map_err(crate::futures_task::map_error)
+});
 
-    unsafe fn on_clone(handle: *const ()) {
-        unsafe {
-            Rc::increment_strong_count(handle as *const futures_executor::LocalSpawner);
-        }
-    }
+/// Spawns onto a `LocalPool` that it can also run, for
+/// [`LocalSpawner::block_on`](crate::LocalSpawner::block_on). Spawning fails with
+/// [`SpawnError::Shutdown`] once the pool is dropped.
+///
+/// Blocking fails with [`SpawnError::Other`] while the pool is borrowed, e.g. from within one of
+/// its tasks. Like `LocalPool::run_until`, it panics when called from within another executor.
+#[derive(Clone)]
+pub struct LocalPoolSpawner {
+    spawner: futures_executor::LocalSpawner,
+    pool: Weak<RefCell<LocalPool>>,
+}
 
-    unsafe fn on_drop(handle: *const ()) {
-        unsafe {
-            drop(Rc::from_raw(
-                handle as *const futures_executor::LocalSpawner,
-            ));
+impl LocalPoolSpawner {
+    pub fn new(pool: &Rc<RefCell<LocalPool>>) -> Self {
+        Self {
+            spawner: pool.borrow().spawner(),
+            pool: Rc::downgrade(pool),
         }
     }
 }
 
+crate::impl_local_spawner!(
+    LocalPoolSpawner,
+    |this, future| {
+        use futures_task::LocalSpawn;
+
+        this.spawner
+            .spawn_local_obj(future.into())
+            .map_err(crate::futures_task::map_error)
+    },
+    extensions = Extensions::NONE.with_block_on(|handle, future| {
+        let this = unsafe { &*(handle as *const LocalPoolSpawner) };
+        let pool = this.pool.upgrade().ok_or(SpawnError::Shutdown)?;
+        let mut pool = pool.try_borrow_mut().map_err(|_| SpawnError::Other)?;
+        pool.run_until(future);
+        Ok(())
+    }),
+);
+
 #[cfg(test)]
 mod test {
+    use super::*;
+
     #[test]
     fn test_futures_executor() {
         let mut ex = futures_executor::LocalPool::new();
@@ -71,4 +70,46 @@ mod test {
 
         assert_eq!(result.unwrap(), 42);
     }
+
+    #[test]
+    fn test_into_inner() {
+        let ex = futures_executor::LocalPool::new();
+        let spawner = alloc::rc::Rc::new(ex.spawner());
+        let erased = crate::LocalSpawner::new(spawner.clone());
+        let clone = erased.clone();
+
+        let recovered = erased
+            .into_inner::<alloc::rc::Rc<futures_executor::LocalSpawner>>()
+            .unwrap_or_else(|_| panic!("type matches"));
+        assert!(alloc::rc::Rc::ptr_eq(&recovered, &spawner));
+        assert_eq!(alloc::rc::Rc::strong_count(&spawner), 3);
+
+        drop(clone);
+        assert_eq!(alloc::rc::Rc::strong_count(&spawner), 2);
+    }
+
+    #[test]
+    fn test_block_on_local_pool() {
+        let pool = Rc::new(RefCell::new(LocalPool::new()));
+        let spawner = crate::LocalSpawner::new(LocalPoolSpawner::new(&pool));
+
+        let (result_tx, mut result_rx) = localq::mpsc::channel(1);
+        spawner
+            .spawn(async move {
+                result_tx.try_send(42).unwrap();
+            })
+            .unwrap();
+        let result = spawner.block_on(async { result_rx.recv().await }).unwrap();
+        assert_eq!(result.unwrap(), 42);
+
+        let nested = spawner.clone();
+        let result = spawner.block_on(async move { nested.block_on(async {}).is_err() });
+        assert!(result.unwrap());
+
+        drop(pool);
+        assert!(matches!(
+            spawner.block_on(async {}),
+            Err(SpawnError::Shutdown)
+        ));
+    }
 }
@@ -0,0 +1,153 @@
+use crate::{
+    BoxedLocalSpawn, IntoSpawner, LocalSpawner, Result, SendSpawnCompleter, SendSpawnSlot,
+    SpawnError, Spawner, TaskMeta,
+};
+use alloc::{alloc::Layout, boxed::Box, rc::Rc, sync::Arc};
+use core::{future::Future, pin::Pin};
+use futures_task::{FutureObj, LocalSpawn, Spawn};
+
+impl LocalSpawner {
+    /// Create a `LocalSpawner` from anything implementing `futures_task::LocalSpawn`, such as an
+    /// `Rc` of an executor's spawner, for executors `ispawn` has no dedicated integration for.
+    ///
+    /// Every spawned future is boxed into a `LocalFutureObj`.
+    pub fn from_local_spawn<S: LocalSpawn + 'static>(spawner: S) -> Self {
+        Self::new(Rc::new(LocalSpawnAdapter(spawner)))
+    }
+}
+
+struct LocalSpawnAdapter<S>(S);
+
+impl<S: LocalSpawn> BoxedLocalSpawn for LocalSpawnAdapter<S> {
+    fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+        self.0.spawn_local_obj(future.into()).map_err(map_error)
+    }
+}
+
+impl Spawner {
+    /// Create a `Spawner` from anything implementing `futures_task::Spawn`, such as
+    /// `futures_executor::ThreadPool`, for executors `ispawn` has no dedicated integration for.
+    ///
+    /// Every spawned future is boxed into a `FutureObj`.
+    pub fn from_spawn<S: Spawn + Send + Sync + 'static>(spawner: S) -> Self {
+        Self::new(SpawnAdapter(Arc::new(spawner)))
+    }
+}
+
+struct SpawnAdapter<S>(Arc<S>);
+
+impl<S: Spawn + Send + Sync + 'static> IntoSpawner for SpawnAdapter<S> {
+    unsafe fn into_handle(self) -> *const () {
+        Arc::into_raw(self.0) as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        _: *const (),
+        slot: SendSpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SendSpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut (dyn Future<Output = ()> + Send),
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        let future_box: Box<dyn Future<Output = ()> + Send> =
+            unsafe { Box::from_raw(task_ptr_as_dyn_future) };
+
+        let this = unsafe { &*(handle as *const S) };
+        this.spawn_obj(FutureObj::new(future_box))
+            .map_err(map_error)
+    }
+
+    unsafe fn on_clone(handle: *const ()) {
+        unsafe { Arc::increment_strong_count(handle as *const S) }
+    }
+
+    unsafe fn on_drop(handle: *const ()) {
+        unsafe {
+            let _ = Arc::from_raw(handle as *const S);
+        }
+    }
+}
+
+pub(crate) fn map_error(e: futures_task::SpawnError) -> SpawnError {
+    if e.is_shutdown() {
+        SpawnError::Shutdown
+    } else {
+        SpawnError::Executor(Box::new(e))
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_from_local_spawn() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::from_local_spawn(Rc::new(ex.spawner()));
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        spawner.spawn(async move { ran2.set(true) }).unwrap();
+        ex.run_until_stalled();
+        assert!(ran.get());
+
+        drop(ex);
+        assert!(matches!(spawner.spawn(async {}), Err(SpawnError::Shutdown)));
+    }
+
+    /// Runs every future to completion on a thread of its own, standing in for `ThreadPool`.
+    #[cfg(feature = "std")]
+    struct ThreadPerFuture;
+
+    #[cfg(feature = "std")]
+    impl Spawn for ThreadPerFuture {
+        fn spawn_obj(
+            &self,
+            future: FutureObj<'static, ()>,
+        ) -> core::result::Result<(), futures_task::SpawnError> {
+            std::thread::spawn(move || futures_executor::block_on(future));
+            Ok(())
+        }
+    }
+
+    struct ShutDown;
+
+    impl Spawn for ShutDown {
+        fn spawn_obj(
+            &self,
+            _: FutureObj<'static, ()>,
+        ) -> core::result::Result<(), futures_task::SpawnError> {
+            Err(futures_task::SpawnError::shutdown())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_spawn() {
+        let spawner = Spawner::from_spawn(ThreadPerFuture);
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawner
+            .clone()
+            .spawn(async move { tx.send(std::thread::current().id()).unwrap() })
+            .unwrap();
+        assert_ne!(rx.recv().unwrap(), std::thread::current().id());
+    }
+
+    #[test]
+    fn test_from_spawn_shutdown() {
+        let spawner = Spawner::from_spawn(ShutDown);
+        assert!(matches!(spawner.spawn(async {}), Err(SpawnError::Shutdown)));
+    }
+}
@@ -0,0 +1,171 @@
+use core::{
+    alloc::Layout,
+    future::Future,
+    marker::{PhantomData, PhantomPinned},
+    mem::MaybeUninit,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A small future stored inline rather than in an allocation of its own, handed to executors that
+/// support [`Extensions::spawn_inline`](crate::Extensions::spawn_inline).
+///
+/// Shims for executors that can only spawn concrete future types, like `tokio::task::LocalSet`,
+/// otherwise have to box every future before handing it over. Spawning an `InlineFuture` instead
+/// leaves the executor's own task as the only allocation.
+pub struct InlineFuture {
+    storage: Storage,
+    poll: unsafe fn(*mut (), &mut Context<'_>) -> Poll<()>,
+    drop: unsafe fn(*mut ()),
+    /// Set once the stored future has completed and been dropped.
+    done: bool,
+    // The stored future may be `!Send`, and may rely on not being moved once it is pinned.
+    _marker: PhantomData<*mut ()>,
+    _pinned: PhantomPinned,
+}
+
+#[repr(C, align(16))]
+struct Storage(MaybeUninit<[usize; 8]>);
+
+impl InlineFuture {
+    /// The largest future layout an `InlineFuture` can hold.
+    pub const CAPACITY: Layout = Layout::new::<Storage>();
+
+    /// Whether an `F` fits in an `InlineFuture`.
+    pub(crate) fn fits<F>() -> bool {
+        let layout = Layout::new::<F>();
+        layout.size() <= Self::CAPACITY.size() && layout.align() <= Self::CAPACITY.align()
+    }
+
+    /// Store `future` inline.
+    ///
+    /// Safety: `F` must fit (see `fits`), and if `F` isn't `'static`, the caller must ensure that
+    /// everything it borrows outlives the `InlineFuture`.
+    pub(crate) unsafe fn new<F: Future<Output = ()>>(future: F) -> Self {
+        debug_assert!(Self::fits::<F>());
+
+        unsafe fn poll<F: Future<Output = ()>>(ptr: *mut (), cx: &mut Context<'_>) -> Poll<()> {
+            unsafe { Pin::new_unchecked(&mut *(ptr as *mut F)) }.poll(cx)
+        }
+
+        unsafe fn drop<F>(ptr: *mut ()) {
+            unsafe { (ptr as *mut F).drop_in_place() }
+        }
+
+        let mut storage = Storage(MaybeUninit::uninit());
+        unsafe { (storage.0.as_mut_ptr() as *mut F).write(future) };
+        Self {
+            storage,
+            poll: poll::<F>,
+            drop: drop::<F>,
+            done: false,
+            _marker: PhantomData,
+            _pinned: PhantomPinned,
+        }
+    }
+}
+
+impl Future for InlineFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: the stored future is structurally pinned, and never moved out.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.done {
+            return Poll::Ready(());
+        }
+        let ptr = this.storage.0.as_mut_ptr() as *mut ();
+        let poll = unsafe { (this.poll)(ptr, cx) };
+        if poll.is_ready() {
+            this.done = true;
+            unsafe { (this.drop)(ptr) };
+        }
+        poll
+    }
+}
+
+impl Drop for InlineFuture {
+    fn drop(&mut self) {
+        if !self.done {
+            unsafe { (self.drop)(self.storage.0.as_mut_ptr() as *mut ()) }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::{
+        Extensions, IntoLocalSpawner, LocalSpawner, Result, SpawnCompleter, SpawnError, SpawnSlot,
+        TaskMeta,
+    };
+    use alloc::{boxed::Box, rc::Rc, vec::Vec};
+    use core::{cell::RefCell, task::Waker};
+
+    std::thread_local! {
+        static SPAWNED: RefCell<Vec<Pin<Box<InlineFuture>>>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Only spawns futures that fit inline, queueing them for the test to poll.
+    struct InlineOnly;
+
+    impl IntoLocalSpawner for InlineOnly {
+        const EXTENSIONS: Extensions = Extensions::NONE.with_spawn_inline(|_, future, _| {
+            SPAWNED.with_borrow_mut(|spawned| spawned.push(Box::pin(future)));
+            Ok(())
+        });
+
+        unsafe fn into_handle(self) -> *const () {
+            core::ptr::null()
+        }
+
+        unsafe fn spawn_dyn(_: *const (), _: SpawnSlot, _: Layout) -> Result<SpawnCompleter> {
+            Err(SpawnError::Other)
+        }
+
+        unsafe fn finish_spawn(
+            _: *const (),
+            _: *mut dyn Future<Output = ()>,
+            _: TaskMeta,
+        ) -> Result<()> {
+            unreachable!()
+        }
+
+        unsafe fn on_clone(_: *const ()) {}
+
+        unsafe fn on_drop(_: *const ()) {}
+    }
+
+    #[test]
+    fn test_small_futures_are_spawned_inline() {
+        let spawner = LocalSpawner::new(InlineOnly);
+
+        let ran = Rc::new(RefCell::new(0));
+        let ran2 = ran.clone();
+        spawner
+            .spawn(async move { *ran2.borrow_mut() += 1 })
+            .unwrap();
+        let large = [0u8; 1024];
+        assert!(
+            spawner
+                .spawn(async move {
+                    let _ = &large;
+                })
+                .is_err()
+        );
+
+        let mut spawned = SPAWNED.take();
+        assert_eq!(spawned.len(), 1);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(spawned[0].as_mut().poll(&mut cx).is_ready());
+        assert_eq!(*ran.borrow(), 1);
+        assert_eq!(Rc::strong_count(&ran), 1);
+
+        // An unpolled future is dropped along with its `InlineFuture`.
+        let ran2 = ran.clone();
+        spawner.spawn(async move { drop(ran2) }).unwrap();
+        assert_eq!(Rc::strong_count(&ran), 2);
+        drop(SPAWNED.take());
+        assert_eq!(Rc::strong_count(&ran), 1);
+    }
+}
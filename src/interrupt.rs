@@ -0,0 +1,261 @@
+//! A spawner that interrupt handlers can spawn through, handing its tasks over to the main loop's
+//! executor.
+
+use crate::{
+    Capabilities, DowncastLocalSpawner, Extensions, IntoLocalSpawner, LocalSpawner, Result,
+    SpawnCompleter, SpawnError, SpawnSlot, TaskMeta,
+    sync::atomic::{AtomicU8, Ordering},
+};
+use core::{
+    alloc::Layout,
+    cell::UnsafeCell,
+    future::Future,
+    mem::MaybeUninit,
+    pin::Pin,
+    ptr::NonNull,
+    task::{Context, Poll},
+};
+
+/// A queue of up to `N` tasks, each holding a future of up to the size and alignment of `S`,
+/// whose spawners are safe to use from interrupt handlers: spawning claims a slot with a single
+/// atomic compare-and-swap, and never blocks or allocates. It fails with `SpawnError::QueueFull`
+/// while every slot is taken, and larger futures are boxed first (with the `alloc` feature) or
+/// rejected with `SpawnError::Other`.
+///
+/// The tasks don't run until the main loop [`drain`](Self::drain)s them into its executor, e.g. a
+/// [`StaticLocalSpawner`](crate::StaticLocalSpawner), which then polls them in place.
+pub struct InterruptSpawner<const N: usize, S = [usize; 16]> {
+    slots: [Slot<S>; N],
+}
+
+struct Slot<S> {
+    state: AtomicU8,
+    /// Written while the slot is `RESERVED`, and read while it's `TAKEN`.
+    task: UnsafeCell<Option<Task>>,
+    storage: UnsafeCell<MaybeUninit<S>>,
+}
+
+/// A spawned future in its slot's storage, and the metadata it was spawned with.
+type Task = (NonNull<dyn Future<Output = ()>>, TaskMeta);
+
+const FREE: u8 = 0;
+/// Taken by `spawn_dyn`, waiting for `finish_spawn`.
+const RESERVED: u8 = 1;
+/// Spawned, waiting to be drained.
+const READY: u8 = 2;
+/// Handed over to the main loop's executor.
+const TAKEN: u8 = 3;
+
+// Safety: the contract of `new` keeps every task on the thread it was spawned on.
+unsafe impl<const N: usize, S> Sync for InterruptSpawner<N, S> {}
+
+impl<const N: usize, S> InterruptSpawner<N, S> {
+    /// Create a new `InterruptSpawner` with every slot free.
+    ///
+    /// # Safety
+    ///
+    /// Tasks are spawned from interrupt handlers and polled by the main loop, so they don't have
+    /// to be `Send`. Every spawner of this `InterruptSpawner` must only be used on the core that
+    /// drains it, as on a single-core microcontroller.
+    pub const unsafe fn new() -> Self {
+        Self {
+            slots: [const {
+                Slot {
+                    state: AtomicU8::new(FREE),
+                    task: UnsafeCell::new(None),
+                    storage: UnsafeCell::new(MaybeUninit::uninit()),
+                }
+            }; N],
+        }
+    }
+
+    /// A `LocalSpawner` for this queue.
+    pub fn spawner(&'static self) -> LocalSpawner
+    where
+        S: 'static,
+    {
+        LocalSpawner::new(self)
+    }
+
+    /// Hand every task spawned since the last drain over to `spawner`, returning how many.
+    ///
+    /// Each task is spawned as a pointer-sized future that polls the original in its slot, so it
+    /// fits any executor, and the slot is freed once the task completes or is dropped. Draining
+    /// stops at the first spawn error, leaving the failed task and the rest for the next drain, as
+    /// well as any task the executor drops without ever polling it.
+    pub fn drain(&'static self, spawner: &LocalSpawner) -> Result<usize>
+    where
+        S: 'static,
+    {
+        let mut drained = 0;
+        for slot in &self.slots {
+            if slot
+                .state
+                .compare_exchange(READY, TAKEN, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                continue;
+            }
+            let Some((_, meta)) = (unsafe { *slot.task.get() }) else {
+                continue;
+            };
+            spawner.spawn_with_meta(
+                Drained {
+                    slot,
+                    polled: false,
+                },
+                meta,
+            )?;
+            drained += 1;
+        }
+        Ok(drained)
+    }
+}
+
+/// A task handed over to the main loop's executor, still stored in its slot.
+struct Drained<S: 'static> {
+    slot: &'static Slot<S>,
+    polled: bool,
+}
+
+impl<S> Future for Drained<S> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.polled = true;
+        let Some((future, _)) = (unsafe { *self.slot.task.get() }) else {
+            return Poll::Ready(());
+        };
+        // Safety: the future never moves out of its slot.
+        unsafe { Pin::new_unchecked(&mut *future.as_ptr()) }.poll(cx)
+    }
+}
+
+impl<S> Drop for Drained<S> {
+    fn drop(&mut self) {
+        if !self.polled {
+            // Rejected by the executor, or dropped before it ever ran: drain it again next time.
+            self.slot.state.store(READY, Ordering::Release);
+            return;
+        }
+        if let Some((future, _)) = unsafe { (*self.slot.task.get()).take() } {
+            unsafe { core::ptr::drop_in_place(future.as_ptr()) };
+        }
+        self.slot.state.store(FREE, Ordering::Release);
+    }
+}
+
+impl<const N: usize, S: 'static> IntoLocalSpawner for &'static InterruptSpawner<N, S> {
+    const EXTENSIONS: Extensions = Extensions::NONE
+        .with_inline_capacity(Layout::new::<S>())
+        .with_capabilities(Capabilities::ZERO_ALLOC);
+
+    unsafe fn into_handle(self) -> *const () {
+        self as *const InterruptSpawner<N, S> as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        handle: *const (),
+        spawn_slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        let this = unsafe { &*(handle as *const InterruptSpawner<N, S>) };
+        let slot_layout = Layout::new::<S>();
+        if future_layout.size() > slot_layout.size() || future_layout.align() > slot_layout.align()
+        {
+            return Err(SpawnError::Other);
+        }
+        let slot = this
+            .slots
+            .iter()
+            .find(|slot| {
+                slot.state
+                    .compare_exchange(FREE, RESERVED, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            })
+            .ok_or(SpawnError::QueueFull)?;
+
+        let task_ptr = NonNull::from(slot).cast::<()>();
+        let future_ptr = unsafe { NonNull::new_unchecked(slot.storage.get()) }.cast::<()>();
+        Ok(spawn_slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), _future_layout: Layout) {
+        let slot = unsafe { &*(task_ptr as *const Slot<S>) };
+        slot.state.store(FREE, Ordering::Release);
+    }
+
+    unsafe fn finish_spawn(
+        _handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        // `task_ptr_as_dyn_future` points to the start of the slot, with the future's vtable.
+        let slot = unsafe { &*(task_ptr_as_dyn_future as *const Slot<S>) };
+        let offset = unsafe { slot.storage.get().byte_offset_from(slot) } as usize;
+        let future = unsafe { NonNull::new_unchecked(task_ptr_as_dyn_future.byte_add(offset)) };
+        unsafe { *slot.task.get() = Some((future, meta)) };
+        slot.state.store(READY, Ordering::Release);
+        Ok(())
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(handle: *const ()) -> Option<Self> {
+        Some(unsafe { &*(handle as *const InterruptSpawner<N, S>) })
+    }
+}
+
+unsafe impl<const N: usize, S: 'static> DowncastLocalSpawner for &'static InterruptSpawner<N, S> {
+    type Target = InterruptSpawner<N, S>;
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::*;
+    use crate::StaticLocalSpawner;
+    use alloc::{boxed::Box, rc::Rc};
+    use core::cell::Cell;
+
+    fn leak<T>(value: T) -> &'static T {
+        Box::leak(Box::new(value))
+    }
+
+    #[test]
+    fn test_drain() {
+        // Safety: the test spawns and drains on one thread.
+        let queue = leak(unsafe { InterruptSpawner::<2, [usize; 4]>::new() });
+        let ex = leak(StaticLocalSpawner::<1, [usize; 4]>::new());
+        let spawner = ex.spawner();
+
+        let ran = Rc::new(Cell::new(0));
+        for _ in 0..2 {
+            let ran = ran.clone();
+            queue
+                .spawner()
+                .spawn(async move { ran.set(ran.get() + 1) })
+                .unwrap();
+        }
+        assert!(matches!(
+            queue.spawner().spawn(async {}),
+            Err(SpawnError::QueueFull)
+        ));
+
+        // The executor only has room for one of them at a time.
+        assert!(matches!(queue.drain(&spawner), Err(SpawnError::QueueFull)));
+        ex.run_until_stalled();
+        assert_eq!(ran.get(), 1);
+
+        assert_eq!(queue.drain(&spawner).unwrap(), 1);
+        ex.run_until_stalled();
+        assert_eq!(ran.get(), 2);
+        assert_eq!(Rc::strong_count(&ran), 1);
+
+        queue.spawner().spawn(async {}).unwrap();
+        assert_eq!(queue.drain(&spawner).unwrap(), 1);
+        ex.run_until_stalled();
+        assert_eq!(queue.drain(&spawner).unwrap(), 0);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{LocalSpawner, Result, SpawnError, TaskMeta};
+use core::{future::Future, time::Duration};
+
+impl LocalSpawner {
+    /// Spawn a task that repeatedly runs the `Future` returned by `f`, waiting `period` on the
+    /// executor's timer after each run, e.g. for heartbeats or cache refreshes. Fails with
+    /// `SpawnError::Unsupported` if the executor has no timer (see
+    /// [`Extensions::sleep`](crate::Extensions::sleep)), in which case
+    /// [`spawn_interval_with`](Self::spawn_interval_with) can be given a sleep function instead.
+    ///
+    /// The task holds a [`WeakLocalSpawner`](crate::WeakLocalSpawner) to reach the timer, and
+    /// stops once every `LocalSpawner` of the executor is gone.
+    #[track_caller]
+    pub fn spawn_interval<G, F>(&self, period: Duration, mut f: G) -> Result<()>
+    where
+        G: FnMut() -> F + 'static,
+        F: Future<Output = ()> + 'static,
+    {
+        if self.vtable.ext.sleep.is_none() {
+            return Err(SpawnError::Unsupported);
+        }
+        let weak = self.downgrade();
+        self.spawn_with_meta(
+            async move {
+                loop {
+                    f().await;
+                    let Some(spawner) = weak.upgrade() else {
+                        return;
+                    };
+                    let sleep = spawner.vtable.ext.sleep.unwrap();
+                    // Safety: `spawner` keeps the handle alive.
+                    let sleep = unsafe { sleep(spawner.handle, period) };
+                    drop(spawner);
+                    sleep.await;
+                }
+            },
+            TaskMeta::new::<F>(),
+        )
+    }
+
+    /// Spawn a task that repeatedly runs the `Future` returned by `f`, awaiting `sleep(period)`
+    /// after each run, for executors without a timer of their own.
+    #[track_caller]
+    pub fn spawn_interval_with<G, F, S, SF>(
+        &self,
+        period: Duration,
+        mut sleep: S,
+        mut f: G,
+    ) -> Result<()>
+    where
+        G: FnMut() -> F + 'static,
+        F: Future<Output = ()> + 'static,
+        S: FnMut(Duration) -> SF + 'static,
+        SF: Future<Output = ()> + 'static,
+    {
+        self.spawn_with_meta(
+            async move {
+                loop {
+                    f().await;
+                    sleep(period).await;
+                }
+            },
+            TaskMeta::new::<F>(),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::{
+        cell::Cell,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+    use futures_executor::LocalPool;
+
+    /// Completes on its second poll, waking itself in between.
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_spawn_interval_with() {
+        let mut ex = LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+
+        let ticks = Rc::new(Cell::new(0));
+        let ticks2 = ticks.clone();
+        spawner
+            .spawn_interval_with(
+                Duration::from_secs(1),
+                |_| YieldNow(false),
+                move || {
+                    ticks2.set(ticks2.get() + 1);
+                    async {}
+                },
+            )
+            .unwrap();
+
+        ex.run_until(async {
+            while ticks.get() < 3 {
+                YieldNow(false).await;
+            }
+        });
+        assert!(ticks.get() >= 3);
+    }
+
+    #[test]
+    fn test_spawn_interval_without_timer_is_unsupported() {
+        let ex = LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+        assert!(matches!(
+            spawner.spawn_interval(Duration::from_secs(1), || async {}),
+            Err(SpawnError::Unsupported)
+        ));
+    }
+}
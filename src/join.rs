@@ -0,0 +1,331 @@
+use crate::JoinTask;
+use alloc::{boxed::Box, rc::Rc};
+use core::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    pin::Pin,
+    ptr::NonNull,
+    task::{Context, Poll, Waker},
+};
+
+/// The reason a task's output is unavailable to its [`JoinHandle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JoinError {
+    /// The task was dropped before it completed, e.g. because it panicked or its executor shut
+    /// down.
+    Cancelled,
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinError::Cancelled => f.write_str("the task was dropped before it completed"),
+        }
+    }
+}
+
+impl core::error::Error for JoinError {}
+
+/// An owned handle to a task spawned with
+/// [`LocalSpawner::spawn_with_handle`](crate::LocalSpawner::spawn_with_handle), resolving to the
+/// task's output.
+///
+/// Dropping the handle detaches the task: it keeps running, and its output is dropped.
+pub struct JoinHandle<T> {
+    inner: Inner<T>,
+}
+
+enum Inner<T> {
+    Oneshot(OutputHandle<T>),
+    /// The executor's own join handle, see `Extensions::spawn_joinable`.
+    Native {
+        task: JoinTask,
+        /// Set by the `ErasedOutput` wrapping the task once it completes or is dropped.
+        finished: Rc<Cell<bool>>,
+        output: PhantomData<fn() -> T>,
+    },
+}
+
+struct Shared<T> {
+    state: State<T>,
+    waker: Option<Waker>,
+}
+
+enum State<T> {
+    Running,
+    Done(T),
+    Taken,
+    Cancelled,
+}
+
+impl<T> JoinHandle<T> {
+    pub(crate) fn native(task: JoinTask, finished: Rc<Cell<bool>>) -> Self {
+        Self {
+            inner: Inner::Native {
+                task,
+                finished,
+                output: PhantomData,
+            },
+        }
+    }
+
+    /// Whether the task has finished, either by completing or by being dropped.
+    pub fn is_finished(&self) -> bool {
+        match &self.inner {
+            Inner::Oneshot(handle) => handle.is_finished(),
+            Inner::Native { finished, .. } => finished.get(),
+        }
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.get_mut().inner {
+            Inner::Oneshot(handle) => Pin::new(handle).poll(cx),
+            // Safety: the task was spawned by `spawn_with_handle` from an `ErasedOutput<F>` whose
+            // output is a `T`.
+            Inner::Native { task, .. } => task
+                .as_mut()
+                .poll(cx)
+                .map(|output| output.map(|output| unsafe { output.take::<T>() })),
+        }
+    }
+}
+
+/// An owned handle to a task spawned with
+/// [`LocalSpawner::spawn_with_output`](crate::LocalSpawner::spawn_with_output), resolving to the
+/// task's output, which the task sends it through a oneshot channel.
+///
+/// Dropping the handle detaches the task: it keeps running, and its output is dropped.
+pub struct OutputHandle<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+impl<T> OutputHandle<T> {
+    /// Whether the task has finished, either by completing or by being dropped.
+    pub fn is_finished(&self) -> bool {
+        !matches!(self.shared.borrow().state, State::Running)
+    }
+}
+
+impl<T> Future for OutputHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.borrow_mut();
+        match mem::replace(&mut shared.state, State::Taken) {
+            State::Running => {
+                shared.state = State::Running;
+                match &mut shared.waker {
+                    Some(waker) if waker.will_wake(cx.waker()) => {}
+                    waker => *waker = Some(cx.waker().clone()),
+                }
+                Poll::Pending
+            }
+            State::Done(output) => Poll::Ready(Ok(output)),
+            State::Cancelled => Poll::Ready(Err(JoinError::Cancelled)),
+            State::Taken => panic!("`OutputHandle` polled after completion"),
+        }
+    }
+}
+
+impl<T> From<OutputHandle<T>> for JoinHandle<T> {
+    fn from(handle: OutputHandle<T>) -> Self {
+        Self {
+            inner: Inner::Oneshot(handle),
+        }
+    }
+}
+
+/// Wrap `future` so that its output is sent to the returned `OutputHandle`.
+pub(crate) fn join_pair<F: Future>(future: F) -> (Joined<F>, OutputHandle<F::Output>) {
+    let shared = Rc::new(RefCell::new(Shared {
+        state: State::Running,
+        waker: None,
+    }));
+    let joined = Joined {
+        shared: shared.clone(),
+        done: false,
+        future,
+    };
+    (joined, OutputHandle { shared })
+}
+
+pub(crate) struct Joined<F: Future> {
+    shared: Rc<RefCell<Shared<F::Output>>>,
+    done: bool,
+    future: F,
+}
+
+impl<F: Future> Joined<F> {
+    fn finish(&mut self, state: State<F::Output>) {
+        self.done = true;
+        let waker = {
+            let mut shared = self.shared.borrow_mut();
+            shared.state = state;
+            shared.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<F: Future> Future for Joined<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `future` is structurally pinned; the other fields are never pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        match future.poll(cx) {
+            Poll::Ready(output) => {
+                this.finish(State::Done(output));
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<F: Future> Drop for Joined<F> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.finish(State::Cancelled);
+        }
+    }
+}
+
+/// The output of a task spawned through
+/// [`Extensions::spawn_joinable`](crate::Extensions::spawn_joinable), with its type erased so
+/// that executors can hand it back through their own join handles. Dropping it drops the output.
+pub struct TaskOutput {
+    output: NonNull<()>,
+    layout: Layout,
+    drop: unsafe fn(NonNull<()>),
+}
+
+impl TaskOutput {
+    fn new<T>(output: T) -> Self {
+        unsafe fn drop_output<T>(output: NonNull<()>) {
+            drop(unsafe { Box::from_raw(output.as_ptr() as *mut T) });
+        }
+
+        Self {
+            output: NonNull::from(Box::leak(Box::new(output))).cast(),
+            layout: Layout::new::<T>(),
+            drop: drop_output::<T>,
+        }
+    }
+
+    /// The layout of the output.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Safety: the output must be a `T`.
+    unsafe fn take<T>(self) -> T {
+        debug_assert_eq!(self.layout, Layout::new::<T>());
+        let this = ManuallyDrop::new(self);
+        *unsafe { Box::from_raw(this.output.as_ptr() as *mut T) }
+    }
+}
+
+impl Drop for TaskOutput {
+    fn drop(&mut self) {
+        unsafe { (self.drop)(self.output) }
+    }
+}
+
+/// Wrap `future` to erase its output into a [`TaskOutput`] for `Extensions::spawn_joinable`,
+/// returning a flag set once it completes or is dropped.
+pub(crate) fn erase_output<F: Future>(future: F) -> (ErasedOutput<F>, Rc<Cell<bool>>) {
+    let finished = Rc::new(Cell::new(false));
+    let erased = ErasedOutput {
+        finished: finished.clone(),
+        future,
+    };
+    (erased, finished)
+}
+
+pub(crate) struct ErasedOutput<F> {
+    finished: Rc<Cell<bool>>,
+    future: F,
+}
+
+impl<F: Future> Future for ErasedOutput<F> {
+    type Output = TaskOutput;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<TaskOutput> {
+        // Safety: `future` is structurally pinned; the other field is never pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        future.poll(cx).map(|output| {
+            this.finished.set(true);
+            TaskOutput::new(output)
+        })
+    }
+}
+
+impl<F> Drop for ErasedOutput<F> {
+    fn drop(&mut self) {
+        self.finished.set(true);
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use crate::LocalSpawner;
+
+    #[test]
+    fn test_join_handle_output() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+
+        let handle = spawner.spawn_with_handle(async { 42 }).unwrap();
+        assert!(!handle.is_finished());
+        assert_eq!(ex.run_until(handle), Ok(42));
+
+        let handle = spawner
+            .spawn_with_handle(core::future::pending::<()>())
+            .unwrap();
+        drop(ex);
+        assert!(handle.is_finished());
+        assert_eq!(pollster::block_on(handle), Err(JoinError::Cancelled));
+    }
+
+    #[test]
+    fn test_output_handle() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+
+        let handle = spawner.spawn_with_output(async { 42 }).unwrap();
+        assert!(!handle.is_finished());
+        assert_eq!(ex.run_until(handle), Ok(42));
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        drop(
+            spawner
+                .spawn_with_output(async move { ran2.set(true) })
+                .unwrap(),
+        );
+        ex.run_until_stalled();
+        assert!(ran.get());
+
+        let handle = spawner
+            .spawn_with_output(core::future::pending::<()>())
+            .unwrap();
+        drop(ex);
+        assert!(handle.is_finished());
+        assert_eq!(pollster::block_on(handle), Err(JoinError::Cancelled));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{LocalSpawner, Result, TaskMeta, slab::Slab};
+use alloc::rc::Rc;
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Called once for every task that was still running when the last clone of a
+/// [`LeakDetectingSpawner`] was dropped.
+pub type LeakReporter = fn(&TaskMeta);
+
+/// A development aid that reports tasks which were spawned but never completed, catching forgotten
+/// background loops.
+///
+/// When the last clone of the spawner is dropped, every task spawned through it that is still
+/// running is passed to the reporter along with its future type and spawn location. Detection is
+/// only active in debug builds; with `debug_assertions` disabled this is a plain pass-through to
+/// the wrapped spawner.
+#[derive(Clone)]
+pub struct LeakDetectingSpawner {
+    inner: LocalSpawner,
+    guard: Rc<LeakGuard>,
+}
+
+struct LeakGuard {
+    live: Rc<RefCell<Slab<TaskMeta>>>,
+    reporter: LeakReporter,
+}
+
+impl LeakDetectingSpawner {
+    /// Report leaked tasks to stderr.
+    #[cfg(feature = "std")]
+    pub fn new(inner: LocalSpawner) -> Self {
+        Self::with_reporter(inner, |meta| {
+            std::eprintln!(
+                "ispawn: task `{}` spawned at {} was never completed",
+                meta.type_name(),
+                meta.location(),
+            )
+        })
+    }
+
+    /// Report leaked tasks to `reporter`.
+    pub fn with_reporter(inner: LocalSpawner, reporter: LeakReporter) -> Self {
+        Self {
+            inner,
+            guard: Rc::new(LeakGuard {
+                live: Default::default(),
+                reporter,
+            }),
+        }
+    }
+
+    /// Spawn a `Future`, tracking it until it completes.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        let meta = TaskMeta::new::<F>();
+        if !cfg!(debug_assertions) {
+            return self.inner.spawn_with_meta(f, meta);
+        }
+
+        let live = self.guard.live.clone();
+        let key = live.borrow_mut().insert(meta);
+        let future = LeakTracked {
+            live,
+            key: Some(key),
+            future: f,
+        };
+        self.inner.spawn_with_meta(future, meta)
+    }
+
+    /// The number of tasks spawned through this spawner that haven't completed yet. Always 0 in
+    /// release builds.
+    pub fn live_tasks(&self) -> usize {
+        self.guard.live.borrow().len()
+    }
+}
+
+impl Drop for LeakGuard {
+    fn drop(&mut self) {
+        for meta in self.live.borrow().iter() {
+            (self.reporter)(meta);
+        }
+    }
+}
+
+struct LeakTracked<F> {
+    live: Rc<RefCell<Slab<TaskMeta>>>,
+    key: Option<usize>,
+    future: F,
+}
+
+impl<F: Future<Output = ()>> Future for LeakTracked<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `future` is structurally pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let ready = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx);
+        if ready.is_ready()
+            && let Some(key) = this.key.take()
+        {
+            this.live.borrow_mut().remove(key);
+        }
+        ready
+    }
+}
+
+impl<F> Drop for LeakTracked<F> {
+    fn drop(&mut self) {
+        // Dropping a task without completing it (e.g. cancelling it) doesn't count as a leak.
+        if let Some(key) = self.key {
+            self.live.borrow_mut().remove(key);
+        }
+    }
+}
+
+#[cfg(all(test, debug_assertions, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static LEAKED: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    fn test_reports_leaked_tasks() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner =
+            LeakDetectingSpawner::with_reporter(LocalSpawner::new(Rc::new(ex.spawner())), |meta| {
+                assert!(meta.location().file().ends_with("leak.rs"));
+                LEAKED.fetch_add(1, Ordering::Relaxed);
+            });
+
+        spawner.spawn(async {}).unwrap();
+        spawner.spawn(core::future::pending()).unwrap();
+        assert_eq!(spawner.live_tasks(), 2);
+
+        ex.run_until_stalled();
+        assert_eq!(spawner.live_tasks(), 1);
+
+        drop(spawner);
+        assert_eq!(LEAKED.load(Ordering::Relaxed), 1);
+    }
+}
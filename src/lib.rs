@@ -34,68 +34,922 @@
 
 #![no_std]
 
-#[cfg(any(
-    feature = "async-executor",
-    feature = "dioxus",
-    feature = "futures-executor",
-    feature = "tokio",
-    feature = "wasm-bindgen",
-))]
+#[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
-use core::{alloc::Layout, future::Future};
+use core::{
+    alloc::Layout,
+    any::TypeId,
+    future::Future,
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    panic::Location,
+    pin::Pin,
+    ptr::NonNull,
+    task::{Context, Poll},
+};
 
+#[cfg(feature = "alloc")]
+pub use abort::{AbortHandle, CancelToken, Task};
+pub use alloc_hooks::AllocHooks;
+#[cfg(feature = "async-task")]
+pub use async_task::AsyncTaskSpawner;
+#[cfg(feature = "alloc")]
+pub use bounded::BoundedSpawner;
+#[cfg(feature = "alloc")]
+pub use boxed::BoxedLocalSpawn;
+pub use capabilities::Capabilities;
+#[cfg(feature = "std")]
+pub use catch_unwind::{CatchUnwindSpawner, PanicHandler};
+#[cfg(feature = "std")]
+pub use context::current_context;
+#[cfg(feature = "std")]
+pub use default::{DefaultGuard, set_default, spawn, with_default};
 #[cfg(feature = "dioxus")]
-pub use dioxus::DioxusSpawner;
+pub use dioxus::{DioxusForeverSpawner, DioxusScopeSpawner, DioxusSpawner};
+#[cfg(feature = "alloc")]
+pub use epoch::EpochSpawner;
+#[cfg(feature = "alloc")]
+pub use extensions::{AbortTask, BlockingJob, JoinTask, Sleep};
+pub use extensions::{Extensions, SendExtensions, WeakOps};
+pub use fallible::{ErrorHandler, FallibleSpawner};
+#[cfg(feature = "futures-executor")]
+pub use futures_executor::LocalPoolSpawner;
+pub use inline::InlineFuture;
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
+pub use interrupt::InterruptSpawner;
+#[cfg(feature = "alloc")]
+pub use join::{JoinError, JoinHandle, OutputHandle, TaskOutput};
+#[cfg(feature = "alloc")]
+pub use leak::{LeakDetectingSpawner, LeakReporter};
+#[cfg(feature = "alloc")]
+pub use local_scope::LocalScope;
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub use macros::__private;
+#[cfg(feature = "alloc")]
+pub use metered::{MeteredSpawner, Metrics};
+pub use options::{Priority, SpawnOptions};
+#[cfg(feature = "alloc")]
+pub use owned_scope::{OwnedScope, OwnedScopeSpawner};
+#[cfg(feature = "std")]
+pub use remote::RemoteSpawner;
+#[cfg(feature = "alloc")]
+pub use router::RouterSpawner;
+#[cfg(feature = "alloc")]
+pub use spawn_ext::LocalSpawnExt;
+pub use spawn_hooks::SpawnHooks;
+#[cfg(feature = "alloc")]
+pub use spawn_or_return::SpawnFailed;
+pub use spawner::{IntoSpawner, SendSpawnCompleter, SendSpawnSlot, Spawner};
+#[cfg(feature = "alloc")]
+pub use spawner_set::{BroadcastHandle, SpawnerSet};
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
+pub use static_spawner::StaticLocalSpawner;
+#[cfg(feature = "alloc")]
+pub use structured::StructuredSpawner;
+#[cfg(feature = "alloc")]
+pub use task_scope::TaskScope;
+#[cfg(feature = "alloc")]
+pub use task_set::TaskSet;
+#[cfg(feature = "std")]
+pub use thread_bound::ThreadBoundSpawner;
+#[cfg(feature = "tokio")]
+pub use tokio::TokioLocalSetSpawner;
+#[cfg(all(feature = "wasm-bindgen", feature = "std"))]
+pub use wasm_bindgen::TaskPanicHook;
 #[cfg(feature = "wasm-bindgen")]
-pub use wasm_bindgen::WasmBindgenSpawner;
+pub use wasm_bindgen::{
+    MacrotaskWasmBindgenSpawner, SlottedWasmBindgenSpawner, WasmBindgenSpawner,
+};
+pub use weak::WeakLocalSpawner;
 
+#[cfg(feature = "alloc")]
+mod abort;
+mod alloc_hooks;
+mod ambient;
 #[cfg(feature = "async-executor")]
 mod async_executor;
+#[cfg(feature = "async-task")]
+mod async_task;
+mod block_on;
+#[cfg(feature = "alloc")]
+mod blocking;
+#[cfg(feature = "alloc")]
+mod bounded;
+#[cfg(feature = "alloc")]
+mod boxed;
+#[cfg(feature = "alloc")]
+mod cancel;
+mod capabilities;
+mod capacity;
+#[cfg(feature = "std")]
+mod catch_unwind;
+#[cfg(feature = "alloc")]
+mod close;
+#[cfg(feature = "compliance")]
+pub mod compliance;
+#[cfg(feature = "std")]
+pub mod compute;
+#[cfg(feature = "std")]
+mod context;
+#[cfg(feature = "alloc")]
+mod counted;
+#[cfg(feature = "std")]
+mod default;
 #[cfg(feature = "dioxus")]
 mod dioxus;
+#[cfg(feature = "alloc")]
+mod eager;
+#[cfg(feature = "alloc")]
+mod epoch;
+mod extensions;
+mod fallible;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "futures-core")]
+mod futures_core;
 #[cfg(feature = "futures-executor")]
 mod futures_executor;
+#[cfg(feature = "futures-task")]
+mod futures_task;
+mod inline;
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
+mod interrupt;
+#[cfg(feature = "alloc")]
+mod interval;
+#[cfg(feature = "alloc")]
+mod join;
+#[cfg(feature = "alloc")]
+mod leak;
+#[cfg(feature = "alloc")]
+mod local_scope;
+#[cfg(feature = "alloc")]
+mod macros;
+#[cfg(feature = "alloc")]
+mod metered;
+mod on_complete;
+mod options;
+#[cfg(feature = "alloc")]
+mod owned_scope;
+pub mod raw;
+#[cfg(all(
+    feature = "reference-executor",
+    any(target_has_atomic = "ptr", feature = "portable-atomic")
+))]
+pub mod reference;
+#[cfg(feature = "std")]
+mod remote;
+#[cfg(feature = "alloc")]
+mod router;
+#[cfg(feature = "alloc")]
+mod slab;
+#[cfg(feature = "wasm-bindgen")]
+mod slots;
+#[cfg(feature = "alloc")]
+mod spawn_ext;
+mod spawn_hooks;
+#[cfg(feature = "alloc")]
+mod spawn_or_return;
+mod spawner;
+#[cfg(feature = "alloc")]
+mod spawner_set;
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
+mod static_spawner;
+#[cfg(feature = "alloc")]
+mod structured;
+#[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
+mod sync;
+#[cfg(feature = "alloc")]
+mod task_scope;
+#[cfg(feature = "alloc")]
+mod task_set;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "std")]
+mod thread_bound;
 #[cfg(feature = "tokio")]
 mod tokio;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "alloc")]
+mod tracker;
 #[cfg(feature = "wasm-bindgen")]
 mod wasm_bindgen;
+mod weak;
 
 #[derive(Debug)]
 pub enum SpawnError {
     Shutdown,
     Other,
+    /// The spawner only accepts tasks attached to a scope, group or tracker, like
+    /// `StructuredSpawner`.
+    DetachedForbidden,
+    /// The executor's task queue is at capacity.
+    QueueFull,
+    /// Memory for the task couldn't be allocated.
+    AllocFailed,
+    /// The executor doesn't support what was asked of it, e.g. [`LocalSpawner::spawn_after`]
+    /// without a timer.
+    Unsupported,
+    /// No default spawner is set on this thread, see [`set_default`].
+    #[cfg(feature = "std")]
+    NoDefault,
+    /// The executor failed to spawn the task, with an error of its own describing why.
+    #[cfg(feature = "alloc")]
+    Executor(alloc::boxed::Box<dyn core::error::Error + Send + Sync + 'static>),
+}
+
+impl core::fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SpawnError::Shutdown => f.write_str("the executor has shut down"),
+            SpawnError::Other => f.write_str("the executor failed to spawn the task"),
+            SpawnError::DetachedForbidden => {
+                f.write_str("the spawner forbids spawning detached tasks")
+            }
+            SpawnError::QueueFull => f.write_str("the executor's task queue is full"),
+            SpawnError::AllocFailed => f.write_str("failed to allocate memory for the task"),
+            SpawnError::Unsupported => f.write_str("the executor doesn't support this operation"),
+            #[cfg(feature = "std")]
+            SpawnError::NoDefault => f.write_str("no default spawner is set on this thread"),
+            #[cfg(feature = "alloc")]
+            SpawnError::Executor(e) => write!(f, "the executor failed to spawn the task: {e}"),
+        }
+    }
+}
+
+impl core::error::Error for SpawnError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "alloc")]
+            SpawnError::Executor(e) => Some(&**e),
+            _ => None,
+        }
+    }
 }
 
 pub type Result<T> = core::result::Result<T, SpawnError>;
 
+/// Information about a task being spawned, forwarded to the executor along with its future.
+#[derive(Copy, Clone, Debug)]
+pub struct TaskMeta {
+    type_name: &'static str,
+    layout: Layout,
+    location: &'static Location<'static>,
+    options: SpawnOptions,
+}
+
+impl TaskMeta {
+    #[track_caller]
+    pub(crate) fn new<F>() -> Self {
+        Self {
+            type_name: core::any::type_name::<F>(),
+            layout: Layout::new::<F>(),
+            location: Location::caller(),
+            options: SpawnOptions::DEFAULT,
+        }
+    }
+
+    pub(crate) fn with_options(mut self, options: SpawnOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The type name of the spawned future.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The layout of the spawned future, before it's wrapped or boxed on its way to the executor.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// The source location the task was spawned from.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// The options the task was spawned with.
+    pub fn options(&self) -> &SpawnOptions {
+        &self.options
+    }
+}
+
 // A thread-local spawner that can spawn `Future`s which are `!Send`.
 pub struct LocalSpawner {
     handle: *const (),
     vtable: &'static LocalSpawnerVtable,
+    alloc_hooks: Option<&'static AllocHooks>,
+    spawn_hooks: Option<&'static SpawnHooks>,
 }
 
 impl LocalSpawner {
     // Create a new `LocalSpawner`.
-    pub fn new<T: IntoLocalSpawner>(inner: T) -> Self {
+    pub fn new<T: IntoLocalSpawner + 'static>(inner: T) -> Self {
         Self {
-            handle: unsafe { T::into_handle(inner) } as *const (),
+            handle: unsafe { T::into_handle(inner) },
             vtable: LocalSpawnerVtable::get::<T>(),
+            alloc_hooks: None,
+            spawn_hooks: None,
         }
     }
 
+    /// Create a `LocalSpawner` for a stateless spawner in a const context, e.g. to initialize a
+    /// `const` or a `thread_local!` with `const { .. }`.
+    ///
+    /// `LocalSpawner` isn't `Sync`, so it can't be stored in a `static`. A `const` serves the same
+    /// purpose: creating a spawner this way neither allocates nor touches a handle, so each use of
+    /// the `const` costs nothing.
+    ///
+    /// ```
+    /// # #[cfg(feature = "dioxus")]
+    /// # {
+    /// use ispawn::{DioxusSpawner, LocalSpawner};
+    ///
+    /// const SPAWNER: LocalSpawner = LocalSpawner::from_zst::<DioxusSpawner>();
+    /// # }
+    /// ```
+    pub const fn from_zst<T: ZstLocalSpawner + 'static>() -> Self {
+        const { assert!(core::mem::size_of::<T>() == 0) };
+        Self {
+            handle: core::ptr::null(),
+            vtable: LocalSpawnerVtable::get::<T>(),
+            alloc_hooks: None,
+            spawn_hooks: None,
+        }
+    }
+
+    /// Create a `LocalSpawner` from a spawner that isn't `'static`, which `into_inner` can't
+    /// recover.
+    ///
+    /// Safety: see `ScopedLocalSpawner::new_unchecked`.
+    #[cfg(feature = "async-executor")]
+    unsafe fn new_non_static<T: IntoLocalSpawner>(inner: T) -> Self {
+        Self {
+            handle: unsafe { T::into_handle(inner) },
+            vtable: LocalSpawnerVtable::get_non_static::<T>(),
+            alloc_hooks: None,
+            spawn_hooks: None,
+        }
+    }
+
+    /// Recover the spawner this `LocalSpawner` was created from, e.g. to call executor-specific
+    /// teardown APIs at shutdown. For reference-counted handles like `Rc<LocalSet>`, other clones
+    /// of the `LocalSpawner` keep their references.
+    ///
+    /// Hands the `LocalSpawner` back if it wasn't created from a `T`, or if `T` doesn't support
+    /// being recovered (see [`IntoLocalSpawner::from_handle`]).
+    pub fn into_inner<T: IntoLocalSpawner + 'static>(self) -> core::result::Result<T, Self> {
+        if self.vtable.type_id.map(|type_id| type_id()) != Some(TypeId::of::<T>()) {
+            return Err(self);
+        }
+        let this = ManuallyDrop::new(self);
+        // Safety: the handle was created by `T::into_handle`, and `this` won't release it.
+        match unsafe { T::from_handle(this.handle) } {
+            Some(inner) => Ok(inner),
+            None => Err(ManuallyDrop::into_inner(this)),
+        }
+    }
+
+    /// Borrow the value behind the handle of the spawner this `LocalSpawner` was created from,
+    /// e.g. the `LocalSet` of an `Rc<LocalSet>` to call `run_until` on it. Returns `None` if it
+    /// wasn't created from a `T`.
+    pub fn downcast_ref<T: DowncastLocalSpawner + 'static>(&self) -> Option<&T::Target> {
+        if self.vtable.type_id.map(|type_id| type_id()) != Some(TypeId::of::<T>()) {
+            return None;
+        }
+        // Safety: the handle was created by `T::into_handle` and is live for as long as `self`.
+        Some(unsafe { &*(self.handle as *const T::Target) })
+    }
+
+    /// Decompose the spawner into its handle and vtable, e.g. to store it in memory not managed by
+    /// Rust such as a C struct or a JS-owned object. The handle keeps the reference the spawner
+    /// held, which is released once the spawner is reconstructed with
+    /// [`from_raw_parts`](Self::from_raw_parts) and dropped.
+    ///
+    /// Hooks installed with [`with_alloc_hooks`](Self::with_alloc_hooks) or
+    /// [`with_spawn_hooks`](Self::with_spawn_hooks) are carried along in an allocation of their
+    /// own with the `alloc` feature, and lost without it.
+    pub fn into_raw_parts(self) -> (*const (), &'static VtableHandle) {
+        let this = ManuallyDrop::new(self);
+        #[cfg(feature = "alloc")]
+        if this.alloc_hooks.is_some() || this.spawn_hooks.is_some() {
+            let parts = alloc::boxed::Box::new(raw_parts::WithHooks {
+                handle: this.handle,
+                vtable: this.vtable,
+                alloc_hooks: this.alloc_hooks,
+                spawn_hooks: this.spawn_hooks,
+            });
+            let handle = alloc::boxed::Box::into_raw(parts) as *const ();
+            return (handle, &raw_parts::WITH_HOOKS);
+        }
+        (this.handle, VtableHandle::new(this.vtable))
+    }
+
+    /// Reconstruct a spawner decomposed by [`into_raw_parts`](Self::into_raw_parts).
+    ///
+    /// # Safety
+    ///
+    /// `handle` and `vtable` must have been returned together by a single call to
+    /// `into_raw_parts`, on the same thread, and may only be used to reconstruct a spawner once.
+    pub unsafe fn from_raw_parts(handle: *const (), vtable: &'static VtableHandle) -> Self {
+        #[cfg(feature = "alloc")]
+        if core::ptr::eq(vtable, &raw_parts::WITH_HOOKS) {
+            // Safety: `into_raw_parts` boxed the parts, and they're only reconstructed once.
+            let parts = unsafe { alloc::boxed::Box::from_raw(handle as *mut raw_parts::WithHooks) };
+            return Self {
+                handle: parts.handle,
+                vtable: parts.vtable,
+                alloc_hooks: parts.alloc_hooks,
+                spawn_hooks: parts.spawn_hooks,
+            };
+        }
+        Self {
+            handle,
+            vtable: &vtable.0,
+            alloc_hooks: None,
+            spawn_hooks: None,
+        }
+    }
+
+    /// The optional capabilities of the underlying executor.
+    pub fn extensions(&self) -> &'static Extensions {
+        &self.vtable.ext
+    }
+
+    /// What the underlying executor supports, including the capabilities described by its
+    /// [`Extensions`].
+    pub fn capabilities(&self) -> Capabilities {
+        self.vtable.ext.all_capabilities()
+    }
+
+    /// The name of the underlying executor (see [`Extensions::name`]), or the type name of the
+    /// spawner this `LocalSpawner` was created from.
+    pub fn name(&self) -> &'static str {
+        self.vtable.ext.name.unwrap_or_else(self.vtable.type_name)
+    }
+
+    /// Resolves when the underlying executor begins shutting down, so long-lived tasks can flush
+    /// and deregister instead of being dropped mid-operation.
+    ///
+    /// Never resolves if the executor can't report shutdown (see [`Extensions::poll_closed`]).
+    pub fn closed(&self) -> Closed<'_> {
+        Closed { spawner: self }
+    }
+
+    /// Stop the executor from accepting new tasks, e.g. during application teardown so that
+    /// libraries holding a spawner stop enqueueing work: every later spawn through any of its
+    /// spawners fails with `SpawnError::Shutdown`. Fails with `SpawnError::Unsupported` if the
+    /// executor can't be closed (see [`Extensions::close`]), in which case it can be wrapped with
+    /// [`closable`](Self::closable) first.
+    pub fn close(&self) -> Result<()> {
+        let Some(close) = self.vtable.ext.close else {
+            return Err(SpawnError::Unsupported);
+        };
+        unsafe { close(self.handle) };
+        Ok(())
+    }
+
+    /// Whether the executor has begun shutting down, e.g. because it was [`close`](Self::close)d.
+    /// Always `false` if the executor can't report shutdown (see [`Extensions::poll_closed`]).
+    pub fn is_closed(&self) -> bool {
+        match self.vtable.ext.poll_closed {
+            Some(poll_closed) => unsafe {
+                poll_closed(
+                    self.handle,
+                    &mut Context::from_waker(core::task::Waker::noop()),
+                )
+                .is_ready()
+            },
+            None => false,
+        }
+    }
+
+    /// The number of tasks spawned onto the executor that haven't yet completed or been dropped,
+    /// e.g. to wait for work to drain during shutdown or to shed load. `None` if the executor
+    /// doesn't track it (see [`Extensions::active_tasks`]), in which case it can be wrapped with
+    /// [`counted`](Self::counted) first.
+    pub fn active_tasks(&self) -> Option<usize> {
+        let active_tasks = self.vtable.ext.active_tasks?;
+        Some(unsafe { active_tasks(self.handle) })
+    }
+
     // Spawn a `Future`.
+    #[track_caller]
     pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.spawn_with_meta(f, TaskMeta::new::<F>())
+    }
+
+    /// Spawn a `Future` with the given [`SpawnOptions`], e.g. at a low priority. Executors that
+    /// don't support an option spawn the task as usual.
+    #[track_caller]
+    pub fn spawn_with_options<F: Future<Output = ()> + 'static>(
+        &self,
+        f: F,
+        options: SpawnOptions,
+    ) -> Result<()> {
+        let meta = TaskMeta::new::<F>().with_options(options);
+        #[cfg(feature = "alloc")]
+        if options.eager_poll {
+            return self.spawn_eager(f, meta);
+        }
+        self.spawn_with_meta(f, meta)
+    }
+
+    /// Spawn every `Future` of `futures`, stopping at the first one that fails to spawn. The
+    /// futures after it are dropped without being spawned.
+    ///
+    /// Executors that support [`Extensions::reserve`] make room for the whole batch up front.
+    #[track_caller]
+    pub fn spawn_iter<I>(&self, futures: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Future<Output = ()> + 'static,
+    {
+        let futures = futures.into_iter();
+        if let Some(reserve) = self.vtable.ext.reserve {
+            unsafe { reserve(self.handle, futures.size_hint().0) };
+        }
+        let meta = TaskMeta::new::<I::Item>();
+        for f in futures {
+            self.spawn_with_meta(f, meta)?;
+        }
+        Ok(())
+    }
+
+    /// Spawn a `Future` with a name (see [`SpawnOptions::name`]), so it can be told apart from
+    /// other tasks when debugging.
+    #[track_caller]
+    pub fn spawn_named<F: Future<Output = ()> + 'static>(
+        &self,
+        name: &'static str,
+        f: F,
+    ) -> Result<()> {
+        self.spawn_with_options(f, SpawnOptions::DEFAULT.with_name(name))
+    }
+
+    /// Spawn a `Future` that isn't `'static`, like `async_task::spawn_unchecked`.
+    ///
+    /// # Safety
+    ///
+    /// The executor may poll and drop the task at any point until it shuts down, so the caller
+    /// must ensure that everything `F` borrows outlives the task itself - not just its completion,
+    /// as the executor may drop a completed task some time later. A task that is still running
+    /// when the borrowed data goes away, or one that is leaked by the executor, is undefined
+    /// behavior.
+    #[track_caller]
+    pub unsafe fn spawn_unchecked<F: Future<Output = ()>>(&self, f: F) -> Result<()> {
+        // Safety: upheld by the caller.
+        unsafe { self.spawn_unchecked_with_meta(f, TaskMeta::new::<F>()) }
+    }
+
+    /// Spawn the `Future` returned by `f`, which is only called once memory for the task has been
+    /// allocated. For executors implementing the two-phase protocol this lets the future's state be
+    /// constructed directly in the task rather than on the stack first and then copied over.
+    #[track_caller]
+    pub fn spawn_fn<F, Fut>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        // Safety: `Fut` is `'static`.
+        unsafe { self.spawn_fn_unchecked_with_meta(f, TaskMeta::new::<Fut>()) }
+    }
+
+    /// Spawn the `Future` returned by `f`, which is handed a clone of this spawner so the task can
+    /// spawn children of its own.
+    #[track_caller]
+    pub fn spawn_with_spawner<F, Fut>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(LocalSpawner) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.spawn_fn(|| f(self.clone()))
+    }
+
+    /// Spawn an already boxed `Future`. If the executor allocates its tasks as boxes (see
+    /// [`Extensions::boxed_tasks`]) the box is handed over as is, otherwise it is spawned like any
+    /// other future. With the `tracing` feature it is always spawned like any other future, so
+    /// that it can be instrumented.
+    #[cfg(feature = "alloc")]
+    #[track_caller]
+    pub fn spawn_boxed(&self, f: Pin<alloc::boxed::Box<dyn Future<Output = ()>>>) -> Result<()> {
+        let meta = TaskMeta::new::<Pin<alloc::boxed::Box<dyn Future<Output = ()>>>>();
+        self.spawn_boxed_with_meta(f, meta)
+    }
+
+    /// Box `f` for an extension that spawns boxed futures, instrumented and hooked up the way
+    /// spawning it would.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn box_task<F: Future + 'static>(
+        &self,
+        f: F,
+        meta: TaskMeta,
+    ) -> Pin<alloc::boxed::Box<dyn Future<Output = F::Output>>> {
+        #[cfg(feature = "tracing")]
+        let f = tracing::Instrument::instrument(f, trace::task_span(&meta));
+        match self.spawn_hooks {
+            Some(hooks) => alloc::boxed::Box::pin(spawn_hooks::Hooked::new(hooks, meta, f)),
+            None => alloc::boxed::Box::pin(f),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    pub(crate) fn spawn_boxed_with_meta(
+        &self,
+        f: Pin<alloc::boxed::Box<dyn Future<Output = ()>>>,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        if cfg!(feature = "tracing") || self.spawn_hooks.is_some() || !self.vtable.ext.boxed_tasks {
+            return self.spawn_with_meta(f, meta);
+        }
+        // Safety: the executor takes over the box as a pinned task, so the future is never moved.
+        let task_ptr = alloc::boxed::Box::into_raw(unsafe { Pin::into_inner_unchecked(f) });
+        unsafe { (self.vtable.finish_spawn)(self.handle, task_ptr, meta) }
+    }
+
+    /// Spawn a `Future` that starts once `delay` has passed, e.g. to retry an operation with
+    /// backoff. Fails with `SpawnError::Unsupported` if the executor has no timer (see
+    /// [`Extensions::sleep`]).
+    #[cfg(feature = "alloc")]
+    #[track_caller]
+    pub fn spawn_after<F: Future<Output = ()> + 'static>(
+        &self,
+        delay: core::time::Duration,
+        f: F,
+    ) -> Result<()> {
+        let Some(sleep) = self.vtable.ext.sleep else {
+            return Err(SpawnError::Unsupported);
+        };
+        let sleep = unsafe { sleep(self.handle, delay) };
+        self.spawn_with_meta(
+            async move {
+                sleep.await;
+                f.await
+            },
+            TaskMeta::new::<F>(),
+        )
+    }
+
+    /// Spawn a `Future`, returning a [`JoinHandle`] that resolves to its output.
+    ///
+    /// Executors with join handles of their own (see [`Extensions::spawn_joinable`]) are handed the
+    /// boxed future, whose output comes back through their handle, unless the spawner allocates
+    /// futures with [`AllocHooks`]. Others spawn it like
+    /// [`spawn_with_output`](Self::spawn_with_output).
+    #[cfg(feature = "alloc")]
+    #[track_caller]
+    pub fn spawn_with_handle<F>(&self, f: F) -> Result<JoinHandle<F::Output>>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        if let Some(spawn_joinable) = self.vtable.ext.spawn_joinable
+            && self.alloc_hooks.is_none()
+        {
+            let meta = TaskMeta::new::<F>();
+            let (future, finished) = join::erase_output(f);
+            let future = self.box_task(future, meta);
+            let task = unsafe { spawn_joinable(self.handle, future, meta) }?;
+            if let Some(hooks) = self.spawn_hooks {
+                (hooks.on_spawn)(&meta);
+            }
+            return Ok(JoinHandle::native(task, finished));
+        }
+        self.spawn_with_output(f).map(JoinHandle::from)
+    }
+
+    /// Spawn a `Future`, returning an [`OutputHandle`] that resolves to its output.
+    ///
+    /// The future is wrapped to send its output through a oneshot channel to the handle, so this
+    /// works the same on every executor, including those without join handles of their own.
+    #[cfg(feature = "alloc")]
+    #[track_caller]
+    pub fn spawn_with_output<F, T>(&self, f: F) -> Result<OutputHandle<T>>
+    where
+        F: Future<Output = T> + 'static,
+        T: 'static,
+    {
+        let (future, handle) = join::join_pair(f);
+        self.spawn_with_meta(future, TaskMeta::new::<F>())?;
+        Ok(handle)
+    }
+
+    /// Spawn a `Future`, returning an [`AbortHandle`] that cancels it.
+    ///
+    /// Executors that can abort their tasks themselves (see [`Extensions::spawn_abortable`]) are
+    /// handed the boxed future, unless the spawner allocates futures with [`AllocHooks`]. Others
+    /// spawn it wrapped in a future that checks whether it was aborted every time it's polled.
+    #[cfg(feature = "alloc")]
+    #[track_caller]
+    pub fn spawn_abortable<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<AbortHandle> {
+        if let Some(spawn_abortable) = self.vtable.ext.spawn_abortable
+            && self.alloc_hooks.is_none()
+        {
+            let meta = TaskMeta::new::<F>();
+            let future = self.box_task(f, meta);
+            let abort = unsafe { spawn_abortable(self.handle, future, meta) }?;
+            if let Some(hooks) = self.spawn_hooks {
+                (hooks.on_spawn)(&meta);
+            }
+            return Ok(AbortHandle::native(abort));
+        }
+        let signal = cancel::CancelSignal::new();
+        self.spawn_guarded(f, &signal, None)?;
+        Ok(AbortHandle::new(signal))
+    }
+
+    /// Spawn a `Future`, returning a [`Task`] guard that cancels it when dropped unless it is
+    /// [`detach`](Task::detach)ed, so background tasks owned by a struct go away with it.
+    #[cfg(feature = "alloc")]
+    #[track_caller]
+    pub fn spawn_owned<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<Task> {
+        let signal = cancel::CancelSignal::new();
+        self.spawn_guarded(f, &signal, None)?;
+        Ok(Task::new(signal))
+    }
+
+    /// Spawn a `Future` that is dropped once `token` is cancelled, the next time its executor polls
+    /// it, so a group of tasks can be cancelled together on any executor.
+    #[cfg(feature = "alloc")]
+    #[track_caller]
+    pub fn spawn_cancellable<F: Future<Output = ()> + 'static>(
+        &self,
+        token: &CancelToken,
+        f: F,
+    ) -> Result<()> {
+        self.spawn_guarded(f, token.signal(), None)
+    }
+
+    /// Run `f` with a [`LocalScope`] whose children may borrow data from the enclosing scope,
+    /// resolving to `f`'s output once every child has finished.
+    ///
+    /// The children run concurrently with each other, but inside the returned future rather than
+    /// as tasks of their own on the executor. Nothing but that future ever polls them, so the
+    /// borrowed data can't be accessed after it goes away, even if the future is leaked. Dropping
+    /// the future drops the children that are still running.
+    #[cfg(feature = "alloc")]
+    pub async fn scope<'env, R>(&self, f: impl FnOnce(&LocalScope<'env>) -> R) -> R {
+        let scope = LocalScope::new(self.clone());
+        let output = f(&scope);
+        scope.join().await;
+        output
+    }
+
+    /// Spawn a `Future` on behalf of a wrapper, forwarding the metadata of the future the wrapper
+    /// was originally given rather than that of the wrapping future.
+    pub(crate) fn spawn_with_meta<F: Future<Output = ()> + 'static>(
+        &self,
+        f: F,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        unsafe { self.spawn_unchecked_with_meta(f, meta) }
+    }
+
+    /// Safety: the caller must ensure that `f` outlives the spawned task, i.e. that the executor
+    /// behind this spawner can only hold on to tasks for as long as everything `F` borrows.
+    pub(crate) unsafe fn spawn_unchecked_with_meta<F: Future<Output = ()>>(
+        &self,
+        f: F,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        unsafe { self.spawn_fn_unchecked_with_meta(move || f, meta) }
+    }
+
+    /// Safety: see `spawn_unchecked_with_meta`.
+    unsafe fn spawn_fn_unchecked_with_meta<F: Future<Output = ()>>(
+        &self,
+        f: impl FnOnce() -> F,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        let f = {
+            let span = trace::task_span(&meta);
+            move || tracing::Instrument::instrument(f(), span)
+        };
+        if let Some(hooks) = self.spawn_hooks {
+            let result =
+                unsafe { self.place(move || spawn_hooks::Hooked::new(hooks, meta, f()), meta) };
+            if result.is_ok() {
+                (hooks.on_spawn)(&meta);
+            }
+            return result;
+        }
+        unsafe { self.place(f, meta) }
+    }
+
+    /// Safety: see `spawn_unchecked_with_meta`.
+    unsafe fn place<F: Future<Output = ()>>(
+        &self,
+        f: impl FnOnce() -> F,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        if let Some(hooks) = self.alloc_hooks {
+            let future = alloc_hooks::HookedBox::new(hooks, f)?;
+            return unsafe { self.emplace(move || future, meta) };
+        }
+
+        if let Some(spawn_inline) = self.vtable.ext.spawn_inline
+            && InlineFuture::fits::<F>()
+        {
+            // Safety: `F` fits, and outlives the task as upheld by the caller.
+            let future = unsafe { InlineFuture::new(f()) };
+            return unsafe { spawn_inline(self.handle, future, meta) };
+        }
+
+        if !self.vtable.ext.fits_inline(Layout::new::<F>()) {
+            #[cfg(feature = "alloc")]
+            return unsafe { self.emplace(move || alloc::boxed::Box::pin(f()), meta) };
+
+            // Without an allocator there is nowhere else to put the future.
+            #[cfg(not(feature = "alloc"))]
+            return Err(SpawnError::Other);
+        }
+
+        unsafe { self.emplace(f, meta) }
+    }
+
+    /// Safety: see `spawn_unchecked_with_meta`.
+    unsafe fn emplace<F: Future<Output = ()>>(
+        &self,
+        f: impl FnOnce() -> F,
+        meta: TaskMeta,
+    ) -> Result<()> {
         // Safety: we create copies of the `handle` pointer here, but the underlying memory is only
         // ever referenced immutably.
 
-        let builder = SpawnCompleterBuilder {
+        let slot = SpawnSlot {
             handle: self.handle,
             vtable: self.vtable,
+            future_layout: Layout::new::<F>(),
         };
         unsafe {
-            let spawn_completer = (self.vtable.spawn_dyn)(self.handle, builder, Layout::new::<F>());
-            spawn_completer.spawn(f)
+            let spawn_completer = (self.vtable.spawn_dyn)(self.handle, slot, Layout::new::<F>())?;
+            spawn_completer.spawn(f, meta)
+        }
+    }
+}
+
+/// A thread-local spawner that can spawn `Future`s which borrow data living for `'a`, for executors
+/// that themselves only live for `'a`.
+///
+/// `'a` is invariant: a `ScopedLocalSpawner<'a>` can neither be used to spawn futures that borrow
+/// shorter-lived data, nor escape `'a`.
+#[derive(Clone)]
+pub struct ScopedLocalSpawner<'a> {
+    inner: LocalSpawner,
+    _marker: PhantomData<fn(&'a ()) -> &'a ()>,
+}
+
+impl<'a> ScopedLocalSpawner<'a> {
+    /// Safety: the caller must ensure that the executor behind `inner` may hold futures borrowing
+    /// data that lives for `'a`, and drops them by the time `'a` ends.
+    #[cfg(feature = "async-executor")]
+    unsafe fn new_unchecked<T: IntoLocalSpawner>(inner: T) -> Self {
+        Self {
+            inner: unsafe { LocalSpawner::new_non_static(inner) },
+            _marker: PhantomData,
+        }
+    }
+
+    // Spawn a `Future`.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'a>(&self, f: F) -> Result<()> {
+        // Safety: upheld by `new_unchecked`'s caller.
+        unsafe {
+            self.inner
+                .spawn_unchecked_with_meta(f, TaskMeta::new::<F>())
+        }
+    }
+
+    /// Spawn the `Future` returned by `f`, which is handed a clone of this spawner so the task can
+    /// spawn children of its own.
+    #[track_caller]
+    pub fn spawn_with_spawner<F, Fut>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(ScopedLocalSpawner<'a>) -> Fut,
+        Fut: Future<Output = ()> + 'a,
+    {
+        self.spawn(f(self.clone()))
+    }
+}
+
+/// The future returned by [`LocalSpawner::closed`].
+pub struct Closed<'a> {
+    spawner: &'a LocalSpawner,
+}
+
+impl Future for Closed<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        match self.spawner.vtable.ext.poll_closed {
+            Some(poll_closed) => unsafe { poll_closed(self.spawner.handle, cx) },
+            None => Poll::Pending,
         }
     }
 }
@@ -108,10 +962,27 @@ impl Clone for LocalSpawner {
         Self {
             handle: self.handle,
             vtable: self.vtable,
+            alloc_hooks: self.alloc_hooks,
+            spawn_hooks: self.spawn_hooks,
         }
     }
 }
 
+impl core::fmt::Debug for LocalSpawner {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LocalSpawner")
+            .field("executor", &self.name())
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl core::fmt::Display for LocalSpawner {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} ({:p})", self.name(), self.handle)
+    }
+}
+
 impl Drop for LocalSpawner {
     fn drop(&mut self) {
         unsafe {
@@ -121,94 +992,631 @@ impl Drop for LocalSpawner {
 }
 
 /// The methods of this trait are meant only for internal use in `ispawn`. Implement it to support
-/// creating an `ispawn::LocalSpawner` from an executor's thread-local spawner.
+/// creating an `ispawn::LocalSpawner` from an executor's thread-local spawner, see the [`raw`]
+/// module for the protocol.
 pub trait IntoLocalSpawner {
-    /// Safety: the implementer must ensure that the memory behind the returned pointer is 'static.
+    /// # Safety
+    ///
+    /// The implementer must ensure that the memory behind the returned pointer is 'static.
     unsafe fn into_handle(self) -> *const ();
 
+    /// Optional capabilities beyond the core contract of the methods below.
+    const EXTENSIONS: Extensions = Extensions::NONE;
+
+    /// Fails with `SpawnError::AllocFailed` if memory for the task can't be allocated.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been returned by `into_handle` and not yet released by `on_drop`.
     unsafe fn spawn_dyn(
         handle: *const (),
-        builder: SpawnCompleterBuilder,
+        slot: SpawnSlot,
         future_layout: Layout,
-    ) -> SpawnCompleter;
+    ) -> Result<SpawnCompleter>;
 
+    /// # Safety
+    ///
+    /// `handle` must be live, and `task_ptr_as_dyn_future` must point to the initialized future
+    /// of a task allocated by `spawn_dyn` on the same handle.
     unsafe fn finish_spawn(
         handle: *const (),
         task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        meta: TaskMeta,
     ) -> Result<()>;
 
+    /// Release a task allocated by `spawn_dyn` whose future was never written, e.g. because
+    /// constructing the future panicked. The default leaks the task.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live, and `task_ptr` must have been returned by `spawn_dyn` on the same
+    /// handle for a future of `future_layout`, and not yet passed to `finish_spawn`.
+    unsafe fn cancel_spawn(handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        let _ = (handle, task_ptr, future_layout);
+    }
+
+    /// # Safety
+    ///
+    /// `handle` must be live.
     unsafe fn on_clone(handle: *const ());
 
+    /// # Safety
+    ///
+    /// `handle` must be live, and must not be used again by the caller after this returns.
     unsafe fn on_drop(handle: *const ());
+
+    /// Convert a handle back into the value it was created from, taking over the reference the
+    /// handle held. Returns `None` if that isn't supported, which is the default.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been returned by `into_handle` and not yet released by `on_drop`. If
+    /// this returns `Some`, the caller must not use `handle` again.
+    unsafe fn from_handle(handle: *const ()) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = handle;
+        None
+    }
+}
+
+/// A spawner whose handle points to a value that [`LocalSpawner::downcast_ref`] can hand out.
+///
+/// # Safety
+///
+/// The handle returned by [`IntoLocalSpawner::into_handle`] must point to a valid `Target` until
+/// it is released by `on_drop`.
+pub unsafe trait DowncastLocalSpawner: IntoLocalSpawner {
+    /// The type of the value behind the handle, e.g. `T` for an `Rc<T>`.
+    type Target;
 }
 
+/// A zero-sized spawner without any state, which [`LocalSpawner::from_zst`] can create without a
+/// value and without calling [`IntoLocalSpawner::into_handle`].
+///
+/// # Safety
+///
+/// `into_handle` must return a null pointer, and every other method of [`IntoLocalSpawner`] must
+/// be sound to call with a null handle.
+pub unsafe trait ZstLocalSpawner: IntoLocalSpawner {}
+
+/// A task allocated by [`IntoLocalSpawner::spawn_dyn`], waiting for its future to be written.
+/// Created with [`SpawnSlot::build`], see the [`raw`] module for the protocol.
+///
+/// Dropping a `SpawnCompleter` without spawning releases the task through
+/// [`IntoLocalSpawner::cancel_spawn`].
 pub struct SpawnCompleter {
     handle: *const (),
     vtable: &'static LocalSpawnerVtable,
-    task_ptr: *mut (),
-    future_ptr: *mut (),
+    task_ptr: NonNull<()>,
+    future_ptr: NonNull<()>,
+    future_layout: Layout,
 }
 
-pub struct SpawnCompleterBuilder {
+/// The request to allocate a task for a future of [`layout`](Self::layout), passed to
+/// [`IntoLocalSpawner::spawn_dyn`], see the [`raw`] module for the protocol.
+pub struct SpawnSlot {
     handle: *const (),
     vtable: &'static LocalSpawnerVtable,
+    future_layout: Layout,
 }
 
-impl SpawnCompleterBuilder {
-    pub fn build(self, task_ptr: *mut (), future_ptr: *mut ()) -> SpawnCompleter {
+impl SpawnSlot {
+    /// The layout of the future the task has to make room for.
+    pub fn layout(&self) -> Layout {
+        self.future_layout
+    }
+
+    /// Hand over the allocated task.
+    ///
+    /// `future_ptr` is where the future will be written, and must be valid for writes of
+    /// [`layout`](Self::layout) and aligned to it, which is only checked in debug builds.
+    /// `task_ptr` is what `finish_spawn` and `cancel_spawn` will be given back: `finish_spawn`
+    /// receives it as a `*mut dyn Future` with the future's vtable, from which it has to find the
+    /// future again, e.g. by offsetting it by the distance between the two pointers with
+    /// `byte_add`, which keeps both the vtable and the pointer's provenance.
+    pub fn build(self, task_ptr: NonNull<()>, future_ptr: NonNull<()>) -> SpawnCompleter {
+        debug_assert!(
+            future_ptr
+                .addr()
+                .get()
+                .is_multiple_of(self.future_layout.align()),
+            "future pointer isn't aligned to the future's layout"
+        );
         SpawnCompleter {
             handle: self.handle,
             vtable: self.vtable,
             task_ptr,
             future_ptr,
+            future_layout: self.future_layout,
         }
     }
 }
 
 impl SpawnCompleter {
+    /// Write the future returned by `f` into the task and finish spawning it.
+    ///
     /// Safety: The caller must ensure that `F` has the same layout that was used to create this
     /// `SpawnCompleter`.
-    unsafe fn spawn<F: Future<Output = ()> + 'static>(self, f: F) -> Result<()> {
+    ///
+    /// If `F` is not `'static`, the caller must also ensure that `F` outlives the spawned task.
+    unsafe fn spawn<F: Future<Output = ()>>(
+        self,
+        f: impl FnOnce() -> F,
+        meta: TaskMeta,
+    ) -> Result<()> {
         unsafe {
-            core::ptr::write(self.future_ptr as *mut F, f);
+            // If `f` panics, dropping `self` hands the task back to the executor.
+            core::ptr::write(self.future_ptr.cast::<F>().as_ptr(), f());
+            let this = ManuallyDrop::new(self);
 
             // Learned this trick from here:
             //   https://www.reddit.com/r/rust/comments/hcofkh/comment/fvgpv5e
             // This seems pretty dubious, but it works today. It is dubious because `self.task_ptr` is
             // not an instance of F. But it will have the same `dyn Future` vtable as F. So the
             // intermediate cast to `*mut F` is just used to get the right vtable.
-            (self.vtable.finish_spawn)(
-                self.handle,
-                self.task_ptr as *mut F as *mut dyn Future<Output = ()>,
-            )
+            let task_ptr =
+                this.task_ptr.cast::<F>().as_ptr() as *mut (dyn Future<Output = ()> + '_);
+            // Erasing the lifetime is upheld by the caller.
+            let task_ptr: *mut dyn Future<Output = ()> = core::mem::transmute(task_ptr);
+            (this.vtable.finish_spawn)(this.handle, task_ptr, meta)
+        }
+    }
+}
+
+/// A `SpawnCompleter` that is dropped without spawning releases its task.
+impl Drop for SpawnCompleter {
+    fn drop(&mut self) {
+        unsafe {
+            (self.vtable.cancel_spawn)(self.handle, self.task_ptr.as_ptr(), self.future_layout)
+        }
+    }
+}
+
+/// The opaque vtable of a [`LocalSpawner`], as returned by [`LocalSpawner::into_raw_parts`].
+#[repr(transparent)]
+pub struct VtableHandle(LocalSpawnerVtable);
+
+impl VtableHandle {
+    fn new(vtable: &'static LocalSpawnerVtable) -> &'static Self {
+        // Safety: `VtableHandle` is a transparent wrapper around `LocalSpawnerVtable`.
+        unsafe { &*(vtable as *const LocalSpawnerVtable as *const Self) }
+    }
+}
+
+/// The raw parts of a spawner with hooks, which don't fit in a handle and vtable alone.
+#[cfg(feature = "alloc")]
+mod raw_parts {
+    use super::*;
+
+    pub(super) struct WithHooks {
+        pub(super) handle: *const (),
+        pub(super) vtable: &'static LocalSpawnerVtable,
+        pub(super) alloc_hooks: Option<&'static AllocHooks>,
+        pub(super) spawn_hooks: Option<&'static SpawnHooks>,
+    }
+
+    /// Marks the handle of a pair of raw parts as a boxed `WithHooks`. Only compared by address,
+    /// never called through.
+    pub(super) static WITH_HOOKS: VtableHandle =
+        VtableHandle(LocalSpawnerVtable::new::<Marker>(None));
+
+    struct Marker;
+
+    impl IntoLocalSpawner for Marker {
+        unsafe fn into_handle(self) -> *const () {
+            unreachable!()
+        }
+
+        unsafe fn spawn_dyn(_: *const (), _: SpawnSlot, _: Layout) -> Result<SpawnCompleter> {
+            unreachable!()
+        }
+
+        unsafe fn finish_spawn(
+            _: *const (),
+            _: *mut dyn Future<Output = ()>,
+            _: TaskMeta,
+        ) -> Result<()> {
+            unreachable!()
+        }
+
+        unsafe fn on_clone(_: *const ()) {
+            unreachable!()
+        }
+
+        unsafe fn on_drop(_: *const ()) {
+            unreachable!()
         }
     }
 }
 
 struct LocalSpawnerVtable {
+    ext: Extensions,
+
+    /// The type the spawner was created from, if it is `'static`.
+    type_id: Option<fn() -> TypeId>,
+
+    type_name: fn() -> &'static str,
+
     spawn_dyn: unsafe fn(
         handle: *const (),
-        builder: SpawnCompleterBuilder,
+        slot: SpawnSlot,
         future_layout: Layout,
-    ) -> SpawnCompleter,
+    ) -> Result<SpawnCompleter>,
 
     finish_spawn: unsafe fn(
         handle: *const (),
         task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        meta: TaskMeta,
     ) -> Result<()>,
 
+    cancel_spawn: unsafe fn(handle: *const (), task_ptr: *mut (), future_layout: Layout),
+
     on_clone: unsafe fn(handle: *const ()),
 
     on_drop: unsafe fn(handle: *const ()),
 }
 
 impl LocalSpawnerVtable {
-    fn get<T: IntoLocalSpawner>() -> &'static Self {
-        &LocalSpawnerVtable {
+    const fn get<T: IntoLocalSpawner + 'static>() -> &'static Self {
+        &const { Self::new::<T>(Some(TypeId::of::<T>)) }
+    }
+
+    #[cfg(feature = "async-executor")]
+    fn get_non_static<T: IntoLocalSpawner>() -> &'static Self {
+        &const { Self::new::<T>(None) }
+    }
+
+    const fn new<T: IntoLocalSpawner>(type_id: Option<fn() -> TypeId>) -> Self {
+        LocalSpawnerVtable {
+            ext: T::EXTENSIONS,
+            type_id,
+            type_name: core::any::type_name::<T>,
             spawn_dyn: T::spawn_dyn,
             finish_spawn: T::finish_spawn,
+            cancel_spawn: T::cancel_spawn,
             on_clone: T::on_clone,
             on_drop: T::on_drop,
         }
     }
 }
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use alloc::boxed::Box;
+    use core::cell::Cell;
+
+    // Per thread, since tests run in parallel and every test spawns onto a `CappedSpawner`.
+    std::thread_local! {
+        static LAST_LAYOUT_SIZE: Cell<usize> = const { Cell::new(0) };
+        static CANCELLED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// A `LocalPool` and a `LocalSpawner` onto it.
+    #[cfg(feature = "futures-executor")]
+    pub(crate) fn local_pool() -> (::futures_executor::LocalPool, LocalSpawner) {
+        let ex = ::futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(alloc::rc::Rc::new(ex.spawner()));
+        (ex, spawner)
+    }
+
+    /// A task that never completes, and an `Rc` it holds on to, whose strong count drops back to
+    /// one once the task is dropped.
+    #[cfg(feature = "futures-executor")]
+    pub(crate) fn pending_task() -> (impl Future<Output = ()> + 'static, alloc::rc::Rc<()>) {
+        let alive = alloc::rc::Rc::new(());
+        let alive2 = alive.clone();
+        let task = async move {
+            let _alive = alive2;
+            core::future::pending::<()>().await;
+        };
+        (task, alive)
+    }
+
+    /// Records the layout of every spawn and drops the future without running it.
+    struct CappedSpawner;
+
+    impl IntoLocalSpawner for CappedSpawner {
+        const EXTENSIONS: Extensions =
+            Extensions::NONE.with_inline_capacity(Layout::new::<[usize; 4]>());
+
+        unsafe fn into_handle(self) -> *const () {
+            core::ptr::null()
+        }
+
+        unsafe fn spawn_dyn(
+            _: *const (),
+            slot: SpawnSlot,
+            future_layout: Layout,
+        ) -> Result<SpawnCompleter> {
+            LAST_LAYOUT_SIZE.set(future_layout.size());
+            let future_ptr = crate::boxed::alloc_future(future_layout)?;
+            let task_ptr = future_ptr;
+            Ok(slot.build(task_ptr, future_ptr))
+        }
+
+        unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+            CANCELLED.set(CANCELLED.get() + 1);
+            unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+        }
+
+        unsafe fn finish_spawn(
+            _: *const (),
+            task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+            _meta: TaskMeta,
+        ) -> Result<()> {
+            drop(unsafe { Box::from_raw(task_ptr_as_dyn_future) });
+            Ok(())
+        }
+
+        unsafe fn on_clone(_: *const ()) {}
+
+        unsafe fn on_drop(_: *const ()) {}
+    }
+
+    unsafe impl ZstLocalSpawner for CappedSpawner {}
+
+    #[test]
+    // Instrumented futures are wrapped, so they differ in size and allocation.
+    #[cfg(not(feature = "tracing"))]
+    fn test_oversized_futures_are_boxed() {
+        let spawner = LocalSpawner::new(CappedSpawner);
+
+        let small = [0u8; 8];
+        let small_future = async move {
+            let _ = &small;
+        };
+        let small_size = core::mem::size_of_val(&small_future);
+        spawner.spawn(small_future).unwrap();
+        assert_eq!(LAST_LAYOUT_SIZE.get(), small_size);
+
+        let large = [0u8; 1024];
+        spawner
+            .spawn(async move {
+                let _ = &large;
+            })
+            .unwrap();
+        assert_eq!(LAST_LAYOUT_SIZE.get(), core::mem::size_of::<Box<()>>());
+    }
+
+    #[test]
+    fn test_spawn_fn() {
+        let spawner = LocalSpawner::new(CappedSpawner);
+
+        let state = [0u8; 16];
+        let mut called = false;
+        spawner
+            .spawn_fn(|| {
+                called = true;
+                async move {
+                    let _ = &state;
+                }
+            })
+            .unwrap();
+        assert!(called);
+    }
+
+    #[test]
+    fn test_spawn_after_without_timer_is_unsupported() {
+        let spawner = LocalSpawner::new(CappedSpawner);
+        let delay = core::time::Duration::from_secs(1);
+        assert!(matches!(
+            spawner.spawn_after(delay, async {}),
+            Err(SpawnError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn test_panicking_spawn_fn_cancels_task() {
+        let spawner = LocalSpawner::new(CappedSpawner);
+        let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+            spawner.spawn_fn(|| -> core::future::Ready<()> { panic!("constructing the future") })
+        }));
+        assert!(result.is_err());
+        assert_eq!(CANCELLED.get(), 1);
+    }
+
+    #[test]
+    fn test_spawn_unchecked() {
+        let spawner = LocalSpawner::new(CappedSpawner);
+
+        // `CappedSpawner` drops tasks before `finish_spawn` returns.
+        let borrowed = [0u8; 16];
+        let borrowed = &borrowed;
+        unsafe {
+            spawner.spawn_unchecked(async move {
+                let _ = borrowed;
+            })
+        }
+        .unwrap();
+    }
+
+    #[test]
+    fn test_spawn_with_options_forwards_priority() {
+        use std::{cell::Cell, rc::Rc};
+
+        /// Records the priority of every spawn and drops the future without running it.
+        struct PrioritySpawner(Rc<Cell<Option<Priority>>>);
+
+        impl IntoLocalSpawner for PrioritySpawner {
+            const EXTENSIONS: Extensions = Extensions::NONE.with_priorities();
+
+            unsafe fn into_handle(self) -> *const () {
+                Rc::into_raw(self.0) as *const ()
+            }
+
+            unsafe fn spawn_dyn(
+                _: *const (),
+                slot: SpawnSlot,
+                future_layout: Layout,
+            ) -> Result<SpawnCompleter> {
+                let future_ptr = crate::boxed::alloc_future(future_layout)?;
+                Ok(slot.build(future_ptr, future_ptr))
+            }
+
+            unsafe fn finish_spawn(
+                handle: *const (),
+                task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+                meta: TaskMeta,
+            ) -> Result<()> {
+                let last = unsafe { &*(handle as *const Cell<Option<Priority>>) };
+                last.set(Some(meta.options().priority));
+                drop(unsafe { Box::from_raw(task_ptr_as_dyn_future) });
+                Ok(())
+            }
+
+            unsafe fn on_clone(handle: *const ()) {
+                unsafe { Rc::increment_strong_count(handle as *const Cell<Option<Priority>>) }
+            }
+
+            unsafe fn on_drop(handle: *const ()) {
+                unsafe { Rc::decrement_strong_count(handle as *const Cell<Option<Priority>>) }
+            }
+        }
+
+        let last = Rc::new(Cell::new(None));
+        let spawner = LocalSpawner::new(PrioritySpawner(last.clone()));
+        assert!(spawner.extensions().priorities);
+        assert!(
+            spawner
+                .capabilities()
+                .contains(Capabilities::SUPPORTS_PRIORITY)
+        );
+        assert!(
+            !spawner
+                .capabilities()
+                .contains(Capabilities::SUPPORTS_NAMES)
+        );
+
+        spawner.spawn(async {}).unwrap();
+        assert_eq!(last.get(), Some(Priority::Normal));
+
+        let low = SpawnOptions::DEFAULT.with_priority(Priority::Low);
+        spawner.spawn_with_options(async {}, low).unwrap();
+        assert_eq!(last.get(), Some(Priority::Low));
+    }
+
+    #[test]
+    fn test_spawn_named_forwards_name() {
+        use std::{cell::Cell, rc::Rc};
+
+        /// Records the name of every spawn and drops the future without running it.
+        struct NamingSpawner(Rc<Cell<Option<&'static str>>>);
+
+        impl IntoLocalSpawner for NamingSpawner {
+            const EXTENSIONS: Extensions = Extensions::NONE.with_names();
+
+            unsafe fn into_handle(self) -> *const () {
+                Rc::into_raw(self.0) as *const ()
+            }
+
+            unsafe fn spawn_dyn(
+                _: *const (),
+                slot: SpawnSlot,
+                future_layout: Layout,
+            ) -> Result<SpawnCompleter> {
+                let future_ptr = crate::boxed::alloc_future(future_layout)?;
+                Ok(slot.build(future_ptr, future_ptr))
+            }
+
+            unsafe fn finish_spawn(
+                handle: *const (),
+                task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+                meta: TaskMeta,
+            ) -> Result<()> {
+                let last = unsafe { &*(handle as *const Cell<Option<&'static str>>) };
+                last.set(meta.options().name);
+                drop(unsafe { Box::from_raw(task_ptr_as_dyn_future) });
+                Ok(())
+            }
+
+            unsafe fn on_clone(handle: *const ()) {
+                unsafe { Rc::increment_strong_count(handle as *const Cell<Option<&'static str>>) }
+            }
+
+            unsafe fn on_drop(handle: *const ()) {
+                unsafe { Rc::decrement_strong_count(handle as *const Cell<Option<&'static str>>) }
+            }
+        }
+
+        let last = Rc::new(Cell::new(None));
+        let spawner = LocalSpawner::new(NamingSpawner(last.clone()));
+
+        spawner.spawn_named("heartbeat", async {}).unwrap();
+        assert_eq!(last.get(), Some("heartbeat"));
+
+        spawner.spawn(async {}).unwrap();
+        assert_eq!(last.get(), None);
+    }
+
+    #[test]
+    fn test_debug_names_executor() {
+        use std::format;
+
+        let spawner = LocalSpawner::new(CappedSpawner);
+        assert_eq!(spawner.name(), "ispawn::test::CappedSpawner");
+        assert!(format!("{spawner:?}").contains("ispawn::test::CappedSpawner"));
+        assert!(format!("{spawner}").starts_with("ispawn::test::CappedSpawner ("));
+    }
+
+    #[test]
+    fn test_executor_error_source() {
+        use core::error::Error;
+
+        let e = SpawnError::Executor(Box::new(SpawnError::QueueFull));
+        assert!(matches!(
+            e.source().unwrap().downcast_ref::<SpawnError>(),
+            Some(SpawnError::QueueFull)
+        ));
+        assert!(SpawnError::AllocFailed.source().is_none());
+    }
+
+    #[test]
+    fn test_into_inner_unsupported() {
+        let spawner = LocalSpawner::new(CappedSpawner);
+        assert!(spawner.into_inner::<CappedSpawner>().is_err());
+    }
+
+    #[test]
+    fn test_raw_parts_round_trip() {
+        let spawner = LocalSpawner::new(CappedSpawner);
+        let (handle, vtable) = spawner.into_raw_parts();
+        let spawner = unsafe { LocalSpawner::from_raw_parts(handle, vtable) };
+        spawner.spawn(async {}).unwrap();
+    }
+
+    #[test]
+    fn test_raw_parts_keep_hooks() {
+        static HOOKS: SpawnHooks = SpawnHooks::NONE;
+        let spawner = LocalSpawner::new(CappedSpawner).with_spawn_hooks(&HOOKS);
+        let (handle, vtable) = spawner.into_raw_parts();
+        let spawner = unsafe { LocalSpawner::from_raw_parts(handle, vtable) };
+        assert!(
+            spawner
+                .spawn_hooks
+                .is_some_and(|hooks| core::ptr::eq(hooks, &HOOKS))
+        );
+        assert_eq!(spawner.name(), core::any::type_name::<CappedSpawner>());
+        spawner.spawn(async {}).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_from_zst_in_const() {
+        const SPAWNER: LocalSpawner = LocalSpawner::from_zst::<CappedSpawner>();
+        std::thread_local! {
+            static LOCAL: LocalSpawner = const { LocalSpawner::from_zst::<CappedSpawner>() };
+        }
+
+        SPAWNER.spawn(async {}).unwrap();
+        LOCAL.with(|spawner| spawner.spawn(async {})).unwrap();
+        assert_eq!(
+            SPAWNER.extensions().inline_capacity,
+            Some(Layout::new::<[usize; 4]>())
+        );
+    }
+}
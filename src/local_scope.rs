@@ -0,0 +1,104 @@
+use crate::LocalSpawner;
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    cell::RefCell,
+    future::{Future, poll_fn},
+    pin::Pin,
+    task::Poll,
+};
+
+/// The scope handed to the closure passed to [`LocalSpawner::scope`], whose children may borrow
+/// data living for `'env`.
+pub struct LocalScope<'env> {
+    spawner: LocalSpawner,
+    children: RefCell<Vec<Pin<Box<dyn Future<Output = ()> + 'env>>>>,
+}
+
+impl<'env> LocalScope<'env> {
+    pub(crate) fn new(spawner: LocalSpawner) -> Self {
+        Self {
+            spawner,
+            children: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Spawn a child that may borrow data living for `'env`. It runs once the scope is awaited.
+    pub fn spawn<F: Future<Output = ()> + 'env>(&self, f: F) {
+        self.children.borrow_mut().push(Box::pin(f));
+    }
+
+    /// The spawner the scope was opened on, for spawning `'static` tasks that may outlive it.
+    pub fn spawner(&self) -> &LocalSpawner {
+        &self.spawner
+    }
+
+    /// Poll every child until all of them have finished.
+    pub(crate) async fn join(&self) {
+        poll_fn(|cx| {
+            let mut children = self.children.borrow_mut();
+            children.retain_mut(|child| child.as_mut().poll(cx).is_pending());
+            if children.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use crate::LocalSpawner;
+    use alloc::{boxed::Box, rc::Rc, vec::Vec};
+    use core::{
+        cell::{Cell, RefCell},
+        future::Future,
+        task::{Context, Waker},
+    };
+
+    #[test]
+    fn test_children_borrow_locals() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+
+        let input = [1, 2, 3];
+        let sum = Cell::new(0);
+        let order = RefCell::new(Vec::new());
+        let spawned = ex.run_until(spawner.scope(|scope| {
+            for i in &input {
+                let (sum, order) = (&sum, &order);
+                scope.spawn(async move {
+                    sum.set(sum.get() + i);
+                    order.borrow_mut().push(*i);
+                });
+            }
+            input.len()
+        }));
+
+        assert_eq!(spawned, 3);
+        assert_eq!(sum.get(), 6);
+        assert_eq!(*order.borrow(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_drop_drops_children() {
+        let ex = futures_executor::LocalPool::new();
+        let spawner = LocalSpawner::new(Rc::new(ex.spawner()));
+
+        let alive = Rc::new(());
+        let mut scope = Box::pin(spawner.scope(|scope| {
+            let alive = alive.clone();
+            scope.spawn(async move {
+                let _alive = alive;
+                core::future::pending::<()>().await;
+            });
+        }));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(scope.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(Rc::strong_count(&alive), 2);
+
+        drop(scope);
+        assert_eq!(Rc::strong_count(&alive), 1);
+    }
+}
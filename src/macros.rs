@@ -0,0 +1,267 @@
+/// Generate an [`IntoLocalSpawner`](crate::IntoLocalSpawner) impl that boxes every spawned future
+/// and hands it to a spawn expression, including the handle's refcounting.
+///
+/// `impl_local_spawner!(Rc<T>, |this, future| ...)` implements the trait for `Rc<T>`, using the
+/// `Rc` itself as the handle, and `Arc<T>` works the same way. `impl_local_spawner!(&'static T,
+/// |this, future| ...)` implements it for a `&'static T`, e.g. an executor in a `static`, without
+/// any reference counting. `impl_local_spawner!(T, |this, future| ...)` implements it for `T`,
+/// moving the value into an `Rc` first. Either way, `this` is a `&T`, `future` is a
+/// `Pin<Box<dyn Future<Output = ()>>>`, and the expression evaluates to an `ispawn::Result<()>`.
+///
+/// Outside of this crate, the orphan rule limits the macro to the `&'static T` and `T` forms with
+/// a local `T`. The `Rc<T>` and `Arc<T>` forms fail with E0117 there even for a local `T`, as
+/// neither `Rc` nor `Arc` is a fundamental type, so wrap the executor in a local type instead.
+/// Every form takes an optional trailing `extensions = ...` with further
+/// [`Extensions`](crate::Extensions) to report, on top of those the macro sets itself.
+///
+/// ```
+/// # use core::{future::Future, pin::Pin};
+/// # use std::{cell::RefCell, rc::Rc};
+/// #[derive(Default)]
+/// struct MyExecutor {
+///     queue: RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>>,
+/// }
+///
+/// ispawn::impl_local_spawner!(MyExecutor, |this, future| {
+///     this.queue.borrow_mut().push(future);
+///     Ok(())
+/// });
+///
+/// let spawner = ispawn::LocalSpawner::new(MyExecutor::default());
+/// ```
+#[macro_export]
+macro_rules! impl_local_spawner {
+    (Rc<$ty:ty>, |$this:ident, $future:ident| $spawn:expr $(, extensions = $ext:expr)? $(,)?) => {
+        impl $crate::IntoLocalSpawner for $crate::__private::Rc<$ty> {
+            unsafe fn into_handle(self) -> *const () {
+                $crate::__private::Rc::into_raw(self) as *const ()
+            }
+
+            $crate::impl_local_spawner!(@spawn $ty, |$this, $future| $spawn, [$($ext)?], Rc);
+
+            unsafe fn from_handle(handle: *const ()) -> ::core::option::Option<Self> {
+                ::core::option::Option::Some(unsafe {
+                    $crate::__private::Rc::from_raw(handle as *const $ty)
+                })
+            }
+        }
+
+        unsafe impl $crate::DowncastLocalSpawner for $crate::__private::Rc<$ty> {
+            type Target = $ty;
+        }
+    };
+    (Arc<$ty:ty>, |$this:ident, $future:ident| $spawn:expr $(, extensions = $ext:expr)? $(,)?) => {
+        impl $crate::IntoLocalSpawner for $crate::__private::Arc<$ty> {
+            unsafe fn into_handle(self) -> *const () {
+                $crate::__private::Arc::into_raw(self) as *const ()
+            }
+
+            $crate::impl_local_spawner!(@spawn $ty, |$this, $future| $spawn, [$($ext)?], Arc);
+
+            unsafe fn from_handle(handle: *const ()) -> ::core::option::Option<Self> {
+                ::core::option::Option::Some(unsafe {
+                    $crate::__private::Arc::from_raw(handle as *const $ty)
+                })
+            }
+        }
+
+        unsafe impl $crate::DowncastLocalSpawner for $crate::__private::Arc<$ty> {
+            type Target = $ty;
+        }
+    };
+    (&'static $ty:ty, |$this:ident, $future:ident| $spawn:expr $(, extensions = $ext:expr)? $(,)?) => {
+        impl $crate::IntoLocalSpawner for &'static $ty {
+            unsafe fn into_handle(self) -> *const () {
+                self as *const $ty as *const ()
+            }
+
+            $crate::impl_local_spawner!(@spawn $ty, |$this, $future| $spawn, [$($ext)?], static);
+
+            unsafe fn from_handle(handle: *const ()) -> ::core::option::Option<Self> {
+                ::core::option::Option::Some(unsafe { &*(handle as *const $ty) })
+            }
+        }
+
+        unsafe impl $crate::DowncastLocalSpawner for &'static $ty {
+            type Target = $ty;
+        }
+    };
+    ($ty:ty, |$this:ident, $future:ident| $spawn:expr $(, extensions = $ext:expr)? $(,)?) => {
+        impl $crate::IntoLocalSpawner for $ty {
+            unsafe fn into_handle(self) -> *const () {
+                $crate::__private::Rc::into_raw($crate::__private::Rc::new(self)) as *const ()
+            }
+
+            $crate::impl_local_spawner!(@spawn $ty, |$this, $future| $spawn, [$($ext)?], Rc);
+
+            unsafe fn from_handle(handle: *const ()) -> ::core::option::Option<Self> {
+                let rc = unsafe { $crate::__private::Rc::from_raw(handle as *const $ty) };
+                match $crate::__private::Rc::try_unwrap(rc) {
+                    ::core::result::Result::Ok(this) => ::core::option::Option::Some(this),
+                    ::core::result::Result::Err(rc) => {
+                        // Other spawners still share the handle, so keep it alive for them.
+                        let _ = $crate::__private::Rc::into_raw(rc);
+                        ::core::option::Option::None
+                    }
+                }
+            }
+        }
+
+        unsafe impl $crate::DowncastLocalSpawner for $ty {
+            type Target = $ty;
+        }
+    };
+    (@extensions) => {
+        $crate::Extensions::NONE
+    };
+    (@extensions $ext:expr) => {
+        $ext
+    };
+    (@weak $ty:ty, Rc) => {
+        $crate::WeakOps::rc::<$ty>()
+    };
+    (@refcount $ty:ty, Rc) => {
+        unsafe fn on_clone(handle: *const ()) {
+            unsafe { $crate::__private::Rc::increment_strong_count(handle as *const $ty) }
+        }
+
+        unsafe fn on_drop(handle: *const ()) {
+            unsafe { ::core::mem::drop($crate::__private::Rc::from_raw(handle as *const $ty)) }
+        }
+    };
+    (@weak $ty:ty, Arc) => {
+        $crate::WeakOps::arc::<$ty>()
+    };
+    (@refcount $ty:ty, Arc) => {
+        unsafe fn on_clone(handle: *const ()) {
+            unsafe { $crate::__private::Arc::increment_strong_count(handle as *const $ty) }
+        }
+
+        unsafe fn on_drop(handle: *const ()) {
+            unsafe { ::core::mem::drop($crate::__private::Arc::from_raw(handle as *const $ty)) }
+        }
+    };
+    (@weak $ty:ty, static) => {
+        $crate::WeakOps::STATIC
+    };
+    (@refcount $ty:ty, static) => {
+        unsafe fn on_clone(_: *const ()) {}
+
+        unsafe fn on_drop(_: *const ()) {}
+    };
+    (@spawn $ty:ty, |$this:ident, $future:ident| $spawn:expr, [$($ext:expr)?], $refcount:tt) => {
+        const EXTENSIONS: $crate::Extensions = $crate::impl_local_spawner!(@extensions $($ext)?)
+            .with_boxed_tasks()
+            .with_weak($crate::impl_local_spawner!(@weak $ty, $refcount));
+
+        unsafe fn spawn_dyn(
+            _: *const (),
+            slot: $crate::SpawnSlot,
+            future_layout: ::core::alloc::Layout,
+        ) -> $crate::Result<$crate::SpawnCompleter> {
+            unsafe { $crate::__private::box_spawn_dyn(slot, future_layout) }
+        }
+
+        unsafe fn cancel_spawn(
+            _: *const (),
+            task_ptr: *mut (),
+            future_layout: ::core::alloc::Layout,
+        ) {
+            unsafe { $crate::__private::box_cancel_spawn(task_ptr, future_layout) }
+        }
+
+        unsafe fn finish_spawn(
+            handle: *const (),
+            task_ptr_as_dyn_future: *mut dyn ::core::future::Future<Output = ()>,
+            _meta: $crate::TaskMeta,
+        ) -> $crate::Result<()> {
+            let $future = unsafe { $crate::__private::box_future(task_ptr_as_dyn_future) };
+            let $this: &$ty = unsafe { &*(handle as *const $ty) };
+            $spawn
+        }
+
+        $crate::impl_local_spawner!(@refcount $ty, $refcount);
+    };
+}
+
+/// Support code for `impl_local_spawner!`.
+#[doc(hidden)]
+pub mod __private {
+    use crate::{Result, SpawnCompleter, SpawnSlot};
+    use alloc::boxed::Box;
+    use core::{alloc::Layout, future::Future, pin::Pin};
+
+    pub use alloc::rc::Rc;
+    #[cfg(target_has_atomic = "ptr")]
+    pub use alloc::sync::Arc;
+
+    /// Allocate a `Box` for the future.
+    ///
+    /// # Safety
+    ///
+    /// `future_layout` must be the layout of the future being spawned.
+    pub unsafe fn box_spawn_dyn(slot: SpawnSlot, future_layout: Layout) -> Result<SpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    /// Free a `Box` allocated by `box_spawn_dyn` whose future was never written.
+    ///
+    /// # Safety
+    ///
+    /// `task_ptr` must have been allocated by `box_spawn_dyn` for `future_layout`.
+    pub unsafe fn box_cancel_spawn(task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    /// Take ownership of a future allocated by `box_spawn_dyn`.
+    ///
+    /// # Safety
+    ///
+    /// `task_ptr_as_dyn_future` must point to an initialized future allocated by `box_spawn_dyn`.
+    pub unsafe fn box_future(
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+    ) -> Pin<Box<dyn Future<Output = ()>>> {
+        Box::into_pin(unsafe { Box::from_raw(task_ptr_as_dyn_future) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::LocalSpawner;
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counter(AtomicUsize);
+
+    struct StaticCounter(AtomicUsize);
+
+    crate::impl_local_spawner!(Arc<Counter>, |this, future| {
+        drop(future);
+        this.0.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    });
+
+    crate::impl_local_spawner!(&'static StaticCounter, |this, future| {
+        drop(future);
+        this.0.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    });
+
+    #[test]
+    fn test_arc_and_static_forms() {
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let spawner = LocalSpawner::new(counter.clone());
+        spawner.clone().spawn(async {}).unwrap();
+        drop(spawner);
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+        assert_eq!(Arc::strong_count(&counter), 1);
+
+        static COUNTER: StaticCounter = StaticCounter(AtomicUsize::new(0));
+        let spawner = LocalSpawner::new(&COUNTER);
+        spawner.spawn(async {}).unwrap();
+        assert_eq!(COUNTER.0.load(Ordering::Relaxed), 1);
+        assert!(spawner.into_inner::<&'static StaticCounter>().is_ok());
+    }
+}
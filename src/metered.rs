@@ -0,0 +1,218 @@
+use crate::{LocalSpawner, Result, TaskMeta};
+use alloc::rc::Rc;
+use core::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A spawner that counts the tasks spawned through it, for observability into what libraries are
+/// doing with their spawners.
+///
+/// Clones share their counters. Create a `LocalSpawner` from a `MeteredSpawner` to hand it to code
+/// expecting one; futures spawned through that are boxed first.
+#[derive(Clone)]
+pub struct MeteredSpawner {
+    inner: LocalSpawner,
+    counters: Rc<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    spawned: Cell<u64>,
+    failed: Cell<u64>,
+    completed: Cell<u64>,
+    cancelled: Cell<u64>,
+    /// Set while spawning, when a dropped future is one the executor rejected.
+    spawning: Cell<bool>,
+    #[cfg(feature = "std")]
+    durations: Option<Durations>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Durations {
+    total: Cell<std::time::Duration>,
+    max: Cell<std::time::Duration>,
+}
+
+/// A snapshot of a [`MeteredSpawner`]'s counters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metrics {
+    /// Tasks spawned successfully.
+    pub spawned: u64,
+    /// Spawn attempts the executor rejected.
+    pub failed: u64,
+    /// Tasks whose future ran to completion.
+    pub completed: u64,
+    /// Tasks dropped before their future completed.
+    pub cancelled: u64,
+    /// The combined time from spawn to completion of every completed task, if durations are
+    /// recorded (see [`MeteredSpawner::with_durations`]).
+    #[cfg(feature = "std")]
+    pub total_duration: std::time::Duration,
+    /// The longest time from spawn to completion of any task, if durations are recorded.
+    #[cfg(feature = "std")]
+    pub max_duration: std::time::Duration,
+}
+
+impl Metrics {
+    /// Tasks that have been spawned and have neither completed nor been cancelled.
+    pub fn active(&self) -> u64 {
+        self.spawned - self.completed - self.cancelled
+    }
+}
+
+impl MeteredSpawner {
+    /// Create a new `MeteredSpawner` spawning onto `inner`.
+    pub fn new(inner: LocalSpawner) -> Self {
+        Self {
+            inner,
+            counters: Default::default(),
+        }
+    }
+
+    /// Create a new `MeteredSpawner` that also records how long each task takes from spawn to
+    /// completion.
+    #[cfg(feature = "std")]
+    pub fn with_durations(inner: LocalSpawner) -> Self {
+        Self {
+            inner,
+            counters: Rc::new(Counters {
+                durations: Some(Durations::default()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Spawn a `Future`, counting it.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.spawn_metered(f, TaskMeta::new::<F>())
+    }
+
+    /// A snapshot of the counters.
+    pub fn metrics(&self) -> Metrics {
+        let counters = &self.counters;
+        #[cfg_attr(not(feature = "std"), allow(unused_mut))]
+        let mut metrics = Metrics {
+            spawned: counters.spawned.get(),
+            failed: counters.failed.get(),
+            completed: counters.completed.get(),
+            cancelled: counters.cancelled.get(),
+            ..Default::default()
+        };
+        #[cfg(feature = "std")]
+        if let Some(durations) = &counters.durations {
+            metrics.total_duration = durations.total.get();
+            metrics.max_duration = durations.max.get();
+        }
+        metrics
+    }
+
+    fn spawn_metered<F: Future<Output = ()> + 'static>(&self, f: F, meta: TaskMeta) -> Result<()> {
+        let future = Metered {
+            counters: self.counters.clone(),
+            done: false,
+            #[cfg(feature = "std")]
+            started: std::time::Instant::now(),
+            future: f,
+        };
+        self.counters.spawning.set(true);
+        let result = self.inner.spawn_with_meta(future, meta);
+        self.counters.spawning.set(false);
+        let counter = match result {
+            Ok(()) => &self.counters.spawned,
+            Err(_) => &self.counters.failed,
+        };
+        counter.set(counter.get() + 1);
+        result
+    }
+}
+
+crate::impl_local_spawner!(
+    MeteredSpawner,
+    |this, future| {
+        this.spawn_metered(
+            future,
+            TaskMeta::new::<Pin<alloc::boxed::Box<dyn Future<Output = ()>>>>(),
+        )
+    },
+    extensions = crate::Extensions::NONE.with_active_tasks(|handle| {
+        unsafe { &*(handle as *const MeteredSpawner) }
+            .metrics()
+            .active() as usize
+    }),
+);
+
+struct Metered<F> {
+    counters: Rc<Counters>,
+    done: bool,
+    #[cfg(feature = "std")]
+    started: std::time::Instant,
+    future: F,
+}
+
+impl<F: Future<Output = ()>> Future for Metered<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `future` is structurally pinned; the other fields are never pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let poll = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx);
+        if poll.is_ready() {
+            this.done = true;
+            let completed = &this.counters.completed;
+            completed.set(completed.get() + 1);
+            #[cfg(feature = "std")]
+            if let Some(durations) = &this.counters.durations {
+                let elapsed = this.started.elapsed();
+                durations.total.set(durations.total.get() + elapsed);
+                durations.max.set(durations.max.get().max(elapsed));
+            }
+        }
+        poll
+    }
+}
+
+impl<F> Drop for Metered<F> {
+    fn drop(&mut self) {
+        if !self.done && !self.counters.spawning.get() {
+            let cancelled = &self.counters.cancelled;
+            cancelled.set(cancelled.get() + 1);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor", feature = "std"))]
+mod test {
+    use super::*;
+    use futures_executor::LocalPool;
+
+    #[test]
+    fn test_metrics() {
+        let mut ex = LocalPool::new();
+        let metered = MeteredSpawner::with_durations(LocalSpawner::new(Rc::new(ex.spawner())));
+
+        metered.spawn(async {}).unwrap();
+        let erased = LocalSpawner::new(metered.clone());
+        erased.spawn(core::future::pending()).unwrap();
+        assert_eq!(metered.metrics().active(), 2);
+        assert_eq!(erased.active_tasks(), Some(2));
+
+        ex.run_until_stalled();
+        let metrics = metered.metrics();
+        assert_eq!((metrics.spawned, metrics.completed), (2, 1));
+        assert_eq!(metrics.active(), 1);
+
+        drop(ex);
+        let metrics = metered.metrics();
+        assert_eq!(metrics.cancelled, 1);
+        assert!(metered.spawn(async {}).is_err());
+        let metrics = metered.metrics();
+        assert_eq!((metrics.failed, metrics.cancelled), (1, 1));
+        assert_eq!(metrics.active(), 0);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{LocalSpawner, Result, TaskMeta};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+impl LocalSpawner {
+    /// Spawn a `Future` and call `on_complete` once the task is done with it: with `true` when it
+    /// ran to completion, or with `false` when it was dropped before, e.g. because spawning failed
+    /// or the executor shut down. A lightweight alternative to a join handle for counting
+    /// in-flight work, e.g. to wait for it during shutdown.
+    #[track_caller]
+    pub fn spawn_with_on_complete<F, C>(&self, f: F, on_complete: C) -> Result<()>
+    where
+        F: Future<Output = ()> + 'static,
+        C: FnOnce(bool) + 'static,
+    {
+        let future = OnComplete {
+            future: f,
+            on_complete: Some(on_complete),
+        };
+        self.spawn_with_meta(future, TaskMeta::new::<F>())
+    }
+}
+
+struct OnComplete<F, C: FnOnce(bool)> {
+    future: F,
+    /// Taken once the callback has been called.
+    on_complete: Option<C>,
+}
+
+impl<F: Future<Output = ()>, C: FnOnce(bool)> Future for OnComplete<F, C> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `future` is structurally pinned, `on_complete` is not.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let poll = future.poll(cx);
+        if poll.is_ready()
+            && let Some(on_complete) = this.on_complete.take()
+        {
+            on_complete(true);
+        }
+        poll
+    }
+}
+
+impl<F, C: FnOnce(bool)> Drop for OnComplete<F, C> {
+    fn drop(&mut self) {
+        if let Some(on_complete) = self.on_complete.take() {
+            on_complete(false);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod test {
+    use crate::test_util::{NoopSpawner, RecordingSpawner};
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_on_complete() {
+        let outcome = Rc::new(Cell::new(None));
+
+        let recorder = RecordingSpawner::new();
+        let outcome2 = outcome.clone();
+        recorder
+            .spawner()
+            .spawn_with_on_complete(async {}, move |done| outcome2.set(Some(done)))
+            .unwrap();
+        assert_eq!(outcome.get(), None);
+        recorder.run_until_stalled();
+        assert_eq!(outcome.get(), Some(true));
+
+        let outcome2 = outcome.clone();
+        NoopSpawner::spawner()
+            .spawn_with_on_complete(async {}, move |done| outcome2.set(Some(done)))
+            .unwrap();
+        assert_eq!(outcome.get(), Some(false));
+    }
+}
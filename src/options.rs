@@ -0,0 +1,77 @@
+/// Options for spawning a task with [`LocalSpawner::spawn_with_options`] or
+/// [`Spawner::spawn_with_options`], forwarded to the executor through [`TaskMeta::options`].
+///
+/// Executors that can't honor an option spawn the task as usual, so options are hints rather than
+/// guarantees. New options are added over time, so options are built up from
+/// [`SpawnOptions::DEFAULT`]:
+///
+/// ```
+/// # use ispawn::{Priority, SpawnOptions};
+/// const HOUSEKEEPING: SpawnOptions = SpawnOptions::DEFAULT.with_priority(Priority::Low);
+/// ```
+///
+/// [`LocalSpawner::spawn_with_options`]: crate::LocalSpawner::spawn_with_options
+/// [`Spawner::spawn_with_options`]: crate::Spawner::spawn_with_options
+/// [`TaskMeta::options`]: crate::TaskMeta::options
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct SpawnOptions {
+    /// How urgently the task should be polled relative to other tasks on the same executor.
+    /// Honored by executors that report [`Extensions::priorities`](crate::Extensions::priorities).
+    pub priority: Priority,
+
+    /// A name for the task, e.g. to tell tasks apart in task dumps. Forwarded to executors that
+    /// report [`Extensions::names`](crate::Extensions::names), and recorded on the task's span
+    /// with the `tracing` feature.
+    pub name: Option<&'static str>,
+
+    /// Poll the future once in the caller's context as soon as the executor has accepted its task,
+    /// so that one that completes right away never waits for the scheduler. A spawn that fails
+    /// never polls the future, and the poll runs inside the task's [`SpawnHooks`] and span like
+    /// any other.
+    ///
+    /// Honored by [`LocalSpawner`](crate::LocalSpawner) itself, which boxes the future to share it
+    /// with its task. Without the `alloc` feature the option is ignored, and the task is spawned
+    /// as usual.
+    ///
+    /// [`SpawnHooks`]: crate::SpawnHooks
+    pub eager_poll: bool,
+}
+
+/// The priority of a spawned task, see [`SpawnOptions::priority`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Housekeeping work that should only run when nothing more urgent is ready.
+    Low,
+    #[default]
+    Normal,
+    /// Latency-sensitive work that should run ahead of other ready tasks.
+    High,
+}
+
+impl SpawnOptions {
+    /// The options tasks are spawned with by default.
+    pub const DEFAULT: Self = Self {
+        priority: Priority::Normal,
+        name: None,
+        eager_poll: false,
+    };
+
+    /// Set [`SpawnOptions::priority`].
+    pub const fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set [`SpawnOptions::name`].
+    pub const fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Set [`SpawnOptions::eager_poll`].
+    pub const fn with_eager_poll(mut self) -> Self {
+        self.eager_poll = true;
+        self
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{LocalSpawner, Result, SpawnError, cancel::CancelSignal};
+use core::{
+    future::{Future, poll_fn},
+    pin::{Pin, pin},
+    task::Poll,
+};
+
+/// The owner of a group of tasks spawned through [`OwnedScopeSpawner`]s.
+///
+/// Dropping the owner cancels every task spawned through its spawners (they are dropped the next
+/// time the executor polls them), and any later spawn attempt fails with `SpawnError::Shutdown`.
+/// This ties task lifetimes to the lifetime of e.g. a UI component.
+pub struct OwnedScope {
+    spawner: OwnedScopeSpawner,
+}
+
+impl OwnedScope {
+    /// Create a new `OwnedScope` spawning onto `inner`.
+    pub fn new(inner: LocalSpawner) -> Self {
+        Self {
+            spawner: OwnedScopeSpawner {
+                inner,
+                signal: CancelSignal::new(),
+            },
+        }
+    }
+
+    /// A cheaply cloneable spawner whose tasks are cancelled when this owner is dropped.
+    pub fn spawner(&self) -> OwnedScopeSpawner {
+        self.spawner.clone()
+    }
+}
+
+impl Drop for OwnedScope {
+    fn drop(&mut self) {
+        self.spawner.signal.cancel();
+    }
+}
+
+/// A spawner whose tasks are cancelled when the [`OwnedScope`] it was created from is dropped.
+#[derive(Clone)]
+pub struct OwnedScopeSpawner {
+    inner: LocalSpawner,
+    signal: CancelSignal,
+}
+
+impl OwnedScopeSpawner {
+    /// Spawn a `Future` owned by this spawner's scope.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        if self.is_closed() {
+            return Err(SpawnError::Shutdown);
+        }
+        self.inner.spawn_guarded(f, &self.signal, None)
+    }
+
+    /// Whether the owning scope has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.signal.is_cancelled()
+    }
+
+    /// Resolves when the owning scope is dropped or the underlying executor begins shutting down.
+    pub async fn closed(&self) {
+        let mut owner_dropped = pin!(self.signal.wrap(core::future::pending::<()>()));
+        let mut executor_closed = self.inner.closed();
+        poll_fn(|cx| {
+            if owner_dropped.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(());
+            }
+            Pin::new(&mut executor_closed).poll(cx)
+        })
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use crate::test::{local_pool, pending_task};
+    use alloc::rc::Rc;
+
+    #[test]
+    fn test_drop_owner_cancels_tasks() {
+        let (mut ex, spawner) = local_pool();
+        let owner = OwnedScope::new(spawner);
+        let spawner = owner.spawner();
+
+        let (task, alive) = pending_task();
+        spawner.spawn(task).unwrap();
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 2);
+
+        drop(owner);
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 1);
+
+        assert!(spawner.is_closed());
+        pollster::block_on(spawner.closed());
+        assert!(matches!(
+            spawner.spawn(async move {}),
+            Err(SpawnError::Shutdown)
+        ));
+    }
+}
@@ -0,0 +1,39 @@
+//! The two-phase spawn protocol, for executor authors integrating with `ispawn`.
+//!
+//! Everything here is also exported from the crate root; this module gathers it in one place and
+//! spells out the contract. An integration implements [`IntoLocalSpawner`] (or [`IntoSpawner`] for
+//! `Send` futures) for its spawn handle, and a spawn then goes through these steps:
+//!
+//! 1. [`spawn_dyn`](IntoLocalSpawner::spawn_dyn) is given a [`SpawnSlot`] describing the layout of
+//!    the future, and allocates a task with room for it. It hands the task back through
+//!    [`SpawnSlot::build`] as a `task_ptr` identifying the task and a `future_ptr` where the future
+//!    is to be written, or fails without allocating anything.
+//! 2. `ispawn` writes the future to `future_ptr`. If that panics, or the resulting
+//!    [`SpawnCompleter`] is dropped for any other reason,
+//!    [`cancel_spawn`](IntoLocalSpawner::cancel_spawn) is called with `task_ptr` instead of the
+//!    next step, and must free the task without touching the future.
+//! 3. [`finish_spawn`](IntoLocalSpawner::finish_spawn) is called with `task_ptr` cast to a
+//!    `*mut dyn Future` carrying the future's vtable. The task is now the executor's: it polls the
+//!    future through that vtable, at `future_ptr`, and drops it in place when done. If the
+//!    executor refuses the task at this point, it drops the future and frees the task before
+//!    returning the error.
+//!
+//! When the task and its future are one and the same allocation, e.g. a `Box` of the future,
+//! `task_ptr` and `future_ptr` are equal and the `*mut dyn Future` can be used as is. Executors
+//! that put a header in front of the future keep the header at `task_ptr`, and recover the future
+//! from the `*mut dyn Future` by offsetting it by the header's size, which keeps the vtable.
+//!
+//! The pointers are [`NonNull`](core::ptr::NonNull) and the protocol is strict-provenance clean:
+//! derive `future_ptr` from the task allocation with `byte_add` rather than through integer casts,
+//! and do the same when recovering the future in `finish_spawn`, so the pointers stay valid under
+//! Miri's `-Zmiri-strict-provenance` and on targets like CHERI.
+//!
+//! Every step is given the spawner's handle, as returned by
+//! [`into_handle`](IntoLocalSpawner::into_handle), which stays live throughout. Optional parts of
+//! the protocol are described by [`Extensions`] (or [`SendExtensions`]).
+
+pub use crate::{
+    DowncastLocalSpawner, Extensions, InlineFuture, IntoLocalSpawner, IntoSpawner, SendExtensions,
+    SendSpawnCompleter, SendSpawnSlot, SpawnCompleter, SpawnSlot, TaskMeta, WeakOps,
+    ZstLocalSpawner,
+};
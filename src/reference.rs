@@ -0,0 +1,570 @@
+//! A minimal single-threaded executor implementing the two-phase spawn protocol natively.
+//!
+//! Each spawned task is a single allocation holding a small header followed by the future itself.
+//! `spawn_dyn` allocates it for the future's layout and `SpawnCompleter::spawn` writes the future
+//! directly into place, so spawning through a [`LocalSpawner`] costs exactly one allocation and
+//! polling involves no extra indirection. It is meant as a template for executor authors more than
+//! as a production executor:
+//!
+//! ```
+//! let ex = ispawn::reference::ReferenceExecutor::new();
+//! let spawner = ex.spawner();
+//! spawner.spawn(async { println!("hello") }).unwrap();
+//! ex.run_until_stalled();
+//! ```
+
+use crate::{
+    Capabilities, Extensions, IntoLocalSpawner, LocalSpawner, Result, SpawnCompleter, SpawnError,
+    SpawnSlot, TaskMeta,
+    sync::atomic::{self, AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+};
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+use alloc::{
+    alloc::{alloc, dealloc},
+    collections::VecDeque,
+    rc::Rc,
+    vec::Vec,
+};
+use core::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    future::Future,
+    mem::ManuallyDrop,
+    pin::Pin,
+    ptr::{self, NonNull},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
+/// A single-threaded executor whose tasks store their futures inline.
+///
+/// Wakers may be woken and dropped from any thread. They push their task onto a lock-free stack
+/// the executor drains, and a task whose last waker is dropped is handed back to the executor the
+/// same way, so that its future is only ever dropped on the executor's thread. Futures of tasks
+/// whose wakers outlive the executor are leaked.
+pub struct ReferenceExecutor {
+    shared: Rc<Shared>,
+}
+
+struct Shared {
+    queue: RefCell<VecDeque<NonNull<Header>>>,
+    remote: Arc<Remote>,
+    closed_wakers: RefCell<Vec<Waker>>,
+    /// Tasks whose future has been written and not yet dropped.
+    active: Cell<usize>,
+    /// Set while `run_until_stalled` runs, which must not be re-entered by a task.
+    running: Cell<bool>,
+}
+
+/// Clears `Shared::running` once `run_until_stalled` returns or unwinds.
+struct Running<'a>(&'a Cell<bool>);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// The part of the executor wakers reach, from any thread.
+struct Remote {
+    /// The top of an intrusive stack of woken tasks, linked through `Header::next`.
+    woken: AtomicPtr<Header>,
+    closed: AtomicBool,
+}
+
+impl Remote {
+    /// Push `task` onto the stack of woken tasks, which takes over a reference to it.
+    ///
+    /// Safety: `task` must be live, and not already on the stack.
+    unsafe fn push(&self, task: NonNull<Header>) {
+        let header = unsafe { task.as_ref() };
+        let mut top = self.woken.load(Ordering::Relaxed);
+        loop {
+            header.next.store(top, Ordering::Relaxed);
+            match self.woken.compare_exchange_weak(
+                top,
+                task.as_ptr(),
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(current) => top = current,
+            }
+        }
+    }
+}
+
+impl Shared {
+    fn close(&self) {
+        self.remote.closed.store(true, Ordering::Release);
+        for waker in core::mem::take(&mut *self.closed_wakers.borrow_mut()) {
+            waker.wake();
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.remote.closed.load(Ordering::Acquire)
+    }
+
+    /// Pop the next task to poll, moving the tasks woken since the queue last ran dry into it.
+    fn pop(&self) -> Option<NonNull<Header>> {
+        let mut queue = self.queue.borrow_mut();
+        if queue.is_empty() {
+            let mut task = self.remote.woken.swap(ptr::null_mut(), Ordering::Acquire);
+            while let Some(woken) = NonNull::new(task) {
+                task = unsafe { woken.as_ref() }.next.load(Ordering::Relaxed);
+                queue.push_back(woken);
+            }
+            // The stack hands tasks out newest first.
+            queue.make_contiguous().reverse();
+        }
+        queue.pop_front()
+    }
+}
+
+impl ReferenceExecutor {
+    /// Create a new `ReferenceExecutor`.
+    pub fn new() -> Self {
+        Self {
+            shared: Rc::new(Shared {
+                queue: RefCell::new(VecDeque::new()),
+                remote: Arc::new(Remote {
+                    woken: AtomicPtr::new(ptr::null_mut()),
+                    closed: AtomicBool::new(false),
+                }),
+                closed_wakers: RefCell::new(Vec::new()),
+                active: Cell::new(0),
+                running: Cell::new(false),
+            }),
+        }
+    }
+
+    /// A `LocalSpawner` for this executor. Spawning fails with `SpawnError::Shutdown` once the
+    /// executor has been dropped.
+    pub fn spawner(&self) -> LocalSpawner {
+        LocalSpawner::new(Handle(self.shared.clone()))
+    }
+
+    /// Poll tasks until none of them is ready to make progress.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from one of the executor's own tasks, which could poll the task that is
+    /// already being polled.
+    pub fn run_until_stalled(&self) {
+        assert!(
+            !self.shared.running.replace(true),
+            "`ReferenceExecutor::run_until_stalled` called from one of its own tasks"
+        );
+        let _running = Running(&self.shared.running);
+        while let Some(task) = self.shared.pop() {
+            // Safety: the queue holds a reference to the task.
+            unsafe { Header::poll(task, &self.shared) };
+        }
+    }
+}
+
+impl Default for ReferenceExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ReferenceExecutor {
+    fn drop(&mut self) {
+        self.shared.close();
+        while let Some(task) = self.shared.pop() {
+            // Safety: the queue held a reference to the task, which is released here.
+            unsafe {
+                task.as_ref().queued.store(false, Ordering::Relaxed);
+                Header::drop_future(task, &self.shared);
+                Header::release(task, &self.shared);
+            }
+        }
+    }
+}
+
+/// The start of every task allocation. The future follows it at `future_offset`.
+struct Header {
+    /// The queue's and every waker's reference to the task.
+    refs: AtomicUsize,
+    /// Whether the task is in the queue or on the stack of woken tasks.
+    queued: AtomicBool,
+    /// Set when the last waker was dropped off the executor's thread while the future was still
+    /// live, leaving the executor to drop it.
+    orphaned: AtomicBool,
+    /// The next task on the stack of woken tasks.
+    next: AtomicPtr<Header>,
+    layout: Layout,
+    future_offset: usize,
+    /// Set once the future has been written, and cleared once it has been dropped. Only touched
+    /// on the executor's thread, or by whoever released the last reference.
+    future: Cell<Option<NonNull<dyn Future<Output = ()>>>>,
+    remote: Arc<Remote>,
+}
+
+impl Header {
+    /// Safety: `task` must be live, and the caller must own the queue's reference to it.
+    unsafe fn poll(task: NonNull<Header>, shared: &Shared) {
+        let header = unsafe { task.as_ref() };
+        header.queued.store(false, Ordering::Relaxed);
+        if header.orphaned.load(Ordering::Relaxed) {
+            unsafe { Header::drop_future(task, shared) };
+        } else if let Some(future) = header.future.get() {
+            let waker = ManuallyDrop::new(unsafe { Waker::from_raw(raw_waker(task)) });
+            let mut cx = Context::from_waker(&waker);
+            // Safety: the future never moves out of its task.
+            let future = unsafe { Pin::new_unchecked(&mut *future.as_ptr()) };
+            if future.poll(&mut cx).is_ready() {
+                unsafe { Header::drop_future(task, shared) };
+            }
+        }
+        unsafe { Header::release(task, shared) };
+    }
+
+    /// Queue the task to be polled, unless it already is or the executor was closed. Callable
+    /// from any thread.
+    ///
+    /// Safety: `task` must be live.
+    unsafe fn schedule(task: NonNull<Header>) {
+        let header = unsafe { task.as_ref() };
+        if header.remote.closed.load(Ordering::Acquire)
+            || header.queued.swap(true, Ordering::AcqRel)
+        {
+            return;
+        }
+        header.refs.fetch_add(1, Ordering::Relaxed);
+        unsafe { header.remote.push(task) };
+    }
+
+    /// Safety: `task` must be live, and this must be the executor's thread.
+    unsafe fn drop_future(task: NonNull<Header>, shared: &Shared) {
+        let header = unsafe { task.as_ref() };
+        if let Some(future) = header.future.take() {
+            unsafe { core::ptr::drop_in_place(future.as_ptr()) };
+            shared.active.set(shared.active.get() - 1);
+        }
+    }
+
+    /// Release one reference to the task on the executor's thread, freeing it if it was the last.
+    ///
+    /// Safety: `task` must be live, and the caller must own the reference being released.
+    unsafe fn release(task: NonNull<Header>, shared: &Shared) {
+        let header = unsafe { task.as_ref() };
+        if header.refs.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        atomic::fence(Ordering::Acquire);
+        unsafe {
+            Header::drop_future(task, shared);
+            Header::free(task);
+        }
+    }
+
+    /// Release a waker's reference to the task from any thread. If it was the last and the future
+    /// is still live, the task is handed back to the executor to drop it.
+    ///
+    /// Safety: `task` must be live, and the caller must own the reference being released.
+    unsafe fn release_remote(task: NonNull<Header>) {
+        let header = unsafe { task.as_ref() };
+        if header.refs.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        atomic::fence(Ordering::Acquire);
+        // Nothing else references the task, so its future can be looked at from here.
+        if header.future.get().is_none() {
+            unsafe { Header::free(task) };
+            return;
+        }
+        header.orphaned.store(true, Ordering::Relaxed);
+        header.queued.store(true, Ordering::Relaxed);
+        header.refs.store(1, Ordering::Relaxed);
+        unsafe { header.remote.push(task) };
+    }
+
+    /// Safety: `task` must be live, unreferenced, and have no future.
+    unsafe fn free(task: NonNull<Header>) {
+        let layout = unsafe { task.as_ref() }.layout;
+        unsafe {
+            core::ptr::drop_in_place(task.as_ptr());
+            dealloc(task.as_ptr() as *mut u8, layout);
+        }
+    }
+}
+
+fn raw_waker(task: NonNull<Header>) -> RawWaker {
+    RawWaker::new(task.as_ptr() as *const (), &WAKER_VTABLE)
+}
+
+static WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |task| {
+        let task = unsafe { NonNull::new_unchecked(task as *mut Header) };
+        unsafe { task.as_ref() }
+            .refs
+            .fetch_add(1, Ordering::Relaxed);
+        raw_waker(task)
+    },
+    |task| unsafe {
+        let task = NonNull::new_unchecked(task as *mut Header);
+        Header::schedule(task);
+        Header::release_remote(task);
+    },
+    |task| unsafe { Header::schedule(NonNull::new_unchecked(task as *mut Header)) },
+    |task| unsafe { Header::release_remote(NonNull::new_unchecked(task as *mut Header)) },
+);
+
+struct Handle(Rc<Shared>);
+
+impl IntoLocalSpawner for Handle {
+    const EXTENSIONS: Extensions = Extensions::NONE
+        .with_capabilities(Capabilities::ZERO_ALLOC)
+        .with_reserve(|handle, additional| {
+            let shared = unsafe { &*(handle as *const Shared) };
+            shared.queue.borrow_mut().reserve(additional);
+        })
+        .with_poll_closed(|handle, cx| {
+            let shared = unsafe { &*(handle as *const Shared) };
+            if shared.is_closed() {
+                return Poll::Ready(());
+            }
+            shared.closed_wakers.borrow_mut().push(cx.waker().clone());
+            Poll::Pending
+        })
+        .with_close(|handle| unsafe { &*(handle as *const Shared) }.close())
+        .with_active_tasks(|handle| unsafe { &*(handle as *const Shared) }.active.get());
+
+    unsafe fn into_handle(self) -> *const () {
+        Rc::into_raw(self.0) as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        handle: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        let (layout, future_offset) = match Layout::new::<Header>().extend(future_layout) {
+            Ok((layout, offset)) => (layout.pad_to_align(), offset),
+            Err(_) => return Err(SpawnError::AllocFailed),
+        };
+        let Some(task) = NonNull::new(unsafe { alloc(layout) } as *mut Header) else {
+            return Err(SpawnError::AllocFailed);
+        };
+
+        let shared = unsafe { &*(handle as *const Shared) };
+        unsafe {
+            task.as_ptr().write(Header {
+                refs: AtomicUsize::new(1),
+                queued: AtomicBool::new(false),
+                orphaned: AtomicBool::new(false),
+                next: AtomicPtr::new(ptr::null_mut()),
+                layout,
+                future_offset,
+                future: Cell::new(None),
+                remote: shared.remote.clone(),
+            });
+        }
+
+        let future_ptr = unsafe { task.cast::<()>().byte_add(future_offset) };
+        Ok(slot.build(task.cast(), future_ptr))
+    }
+
+    unsafe fn cancel_spawn(handle: *const (), task_ptr: *mut (), _future_layout: Layout) {
+        // The task has no future yet, so releasing its only reference just frees it.
+        let shared = unsafe { &*(handle as *const Shared) };
+        unsafe { Header::release(NonNull::new_unchecked(task_ptr as *mut Header), shared) }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        // `task_ptr_as_dyn_future` points to the start of the task, with the future's vtable.
+        let task = unsafe { NonNull::new_unchecked(task_ptr_as_dyn_future as *mut Header) };
+        let header = unsafe { task.as_ref() };
+        let future = unsafe { task_ptr_as_dyn_future.byte_add(header.future_offset) };
+        header
+            .future
+            .set(Some(unsafe { NonNull::new_unchecked(future) }));
+
+        let shared = unsafe { &*(handle as *const Shared) };
+        shared.active.set(shared.active.get() + 1);
+        if shared.is_closed() {
+            unsafe { Header::release(task, shared) };
+            return Err(SpawnError::Shutdown);
+        }
+        header.queued.store(true, Ordering::Relaxed);
+        shared.queue.borrow_mut().push_back(task);
+        Ok(())
+    }
+
+    unsafe fn on_clone(handle: *const ()) {
+        unsafe { Rc::increment_strong_count(handle as *const Shared) }
+    }
+
+    unsafe fn on_drop(handle: *const ()) {
+        unsafe {
+            let _ = Rc::from_raw(handle as *const Shared);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wake_from_other_thread() {
+        let ex = ReferenceExecutor::new();
+        let spawner = ex.spawner();
+
+        let (tx, rx) = std::sync::mpsc::channel::<Waker>();
+        let polls = Rc::new(Cell::new(0));
+        let polls2 = polls.clone();
+        spawner
+            .spawn(core::future::poll_fn(move |cx| {
+                polls2.set(polls2.get() + 1);
+                if polls2.get() == 3 {
+                    return Poll::Ready(());
+                }
+                tx.send(cx.waker().clone()).unwrap();
+                Poll::Pending
+            }))
+            .unwrap();
+
+        ex.run_until_stalled();
+        let waker = rx.recv().unwrap();
+        std::thread::spawn(move || waker.wake_by_ref())
+            .join()
+            .unwrap();
+        ex.run_until_stalled();
+        assert_eq!(polls.get(), 2);
+
+        // Dropping the last waker elsewhere leaves the future for the executor to drop.
+        let waker = rx.recv().unwrap();
+        std::thread::spawn(move || drop(waker)).join().unwrap();
+        assert_eq!(spawner.active_tasks(), Some(1));
+        ex.run_until_stalled();
+        assert_eq!(polls.get(), 2);
+        assert_eq!(spawner.active_tasks(), Some(0));
+        assert_eq!(Rc::strong_count(&polls), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "called from one of its own tasks")]
+    fn test_reentrant_run_panics() {
+        let ex = Rc::new(ReferenceExecutor::new());
+        let ex2 = ex.clone();
+        ex.spawner()
+            .spawn(core::future::poll_fn(move |cx| {
+                cx.waker().wake_by_ref();
+                ex2.run_until_stalled();
+                Poll::<()>::Pending
+            }))
+            .unwrap();
+        ex.run_until_stalled();
+    }
+
+    #[test]
+    fn test_spawn_after_drop_fails() {
+        let ex = ReferenceExecutor::new();
+        let spawner = ex.spawner();
+        let alive = Rc::new(());
+        let alive2 = alive.clone();
+        spawner
+            .spawn(async move {
+                let _alive = alive2;
+            })
+            .unwrap();
+
+        drop(ex);
+        assert_eq!(Rc::strong_count(&alive), 1);
+        assert!(matches!(spawner.spawn(async {}), Err(SpawnError::Shutdown)));
+    }
+
+    #[test]
+    fn test_close() {
+        let ex = ReferenceExecutor::new();
+        let spawner = ex.spawner();
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        spawner.spawn(async move { ran2.set(true) }).unwrap();
+
+        assert!(!spawner.is_closed());
+        ex.spawner().close().unwrap();
+        assert!(spawner.is_closed());
+        assert!(matches!(spawner.spawn(async {}), Err(SpawnError::Shutdown)));
+
+        ex.run_until_stalled();
+        assert!(ran.get());
+    }
+
+    #[test]
+    fn test_active_tasks() {
+        let ex = ReferenceExecutor::new();
+        let spawner = ex.spawner();
+        assert_eq!(spawner.active_tasks(), Some(0));
+        spawner.spawn(YieldNow(false)).unwrap();
+        // Keep the pending task alive through its waker.
+        let waker = Rc::new(RefCell::new(None));
+        let waker2 = waker.clone();
+        spawner
+            .spawn(core::future::poll_fn(move |cx| {
+                *waker2.borrow_mut() = Some(cx.waker().clone());
+                core::task::Poll::<()>::Pending
+            }))
+            .unwrap();
+        assert_eq!(spawner.active_tasks(), Some(2));
+
+        ex.run_until_stalled();
+        assert_eq!(spawner.active_tasks(), Some(1));
+
+        ex.spawner().close().unwrap();
+        assert!(spawner.spawn(async {}).is_err());
+        assert_eq!(spawner.active_tasks(), Some(1));
+        // The executor drops the future once the last waker is gone.
+        waker.take();
+        drop(ex);
+        assert_eq!(spawner.active_tasks(), Some(0));
+    }
+
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> core::task::Poll<()> {
+            if self.0 {
+                return core::task::Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            core::task::Poll::Pending
+        }
+    }
+
+    #[cfg(feature = "compliance")]
+    #[test]
+    fn test_reference_executor_complies() {
+        struct ReferenceHarness(Option<ReferenceExecutor>);
+
+        impl crate::compliance::Harness for ReferenceHarness {
+            fn spawner(&mut self) -> LocalSpawner {
+                self.0.as_ref().unwrap().spawner()
+            }
+
+            fn run_until_stalled(&mut self) {
+                self.0.as_ref().unwrap().run_until_stalled();
+            }
+
+            fn shut_down(&mut self) -> bool {
+                self.0 = None;
+                true
+            }
+        }
+
+        crate::compliance::check_all(&mut ReferenceHarness(Some(ReferenceExecutor::new())));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{LocalSpawner, Result, SpawnError, TaskMeta};
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use std::sync::Mutex;
+
+/// A `Send + Sync` proxy that lets other threads spawn onto a thread's `LocalSpawner`.
+///
+/// Spawn requests are queued and drained on the owning thread by a small pump task running on the
+/// `LocalSpawner`. They can be `Send` futures, or `Send` closures that construct `!Send` futures
+/// once they arrive. The pump finishes once every clone of the `RemoteSpawner` is dropped, and
+/// spawning fails with [`SpawnError::Shutdown`] once the pump itself has been dropped, e.g. because
+/// the executor shut down.
+///
+/// Spawning is asynchronous: `spawn` only fails if the request can't be queued. If the executor
+/// rejects the task once it arrives, the task is dropped.
+pub struct RemoteSpawner {
+    shared: Arc<Mutex<Shared>>,
+}
+
+type Job = Box<dyn FnOnce(&LocalSpawner) + Send>;
+
+struct Shared {
+    jobs: VecDeque<Job>,
+    pump: Option<Waker>,
+    /// The number of `RemoteSpawner`s, the pump finishes once this drops to 0.
+    remotes: usize,
+    closed: bool,
+}
+
+impl RemoteSpawner {
+    /// Spawn a pump task onto `local` and return a proxy to it.
+    pub fn new(local: &LocalSpawner) -> Result<Self> {
+        let shared = Arc::new(Mutex::new(Shared {
+            jobs: VecDeque::new(),
+            pump: None,
+            remotes: 1,
+            closed: false,
+        }));
+        local.spawn(Pump {
+            shared: shared.clone(),
+            spawner: local.clone(),
+        })?;
+        Ok(Self { shared })
+    }
+
+    /// Spawn a `Future` on the owning thread.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(&self, f: F) -> Result<()> {
+        self.spawn_fn_with_meta(move || f, TaskMeta::new::<F>())
+    }
+
+    /// Spawn the `Future` returned by `f`, which is called on the owning thread, so the future
+    /// itself doesn't need to be `Send`.
+    #[track_caller]
+    pub fn spawn_fn<F, Fut>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.spawn_fn_with_meta(f, TaskMeta::new::<Fut>())
+    }
+
+    fn spawn_fn_with_meta<F, Fut>(&self, f: F, meta: TaskMeta) -> Result<()>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.closed {
+            return Err(SpawnError::Shutdown);
+        }
+        shared
+            .jobs
+            .push_back(Box::new(move |spawner: &LocalSpawner| {
+                // Safety: `Fut` is `'static`.
+                let _ = unsafe { spawner.spawn_fn_unchecked_with_meta(f, meta) };
+            }));
+        if let Some(pump) = shared.pump.take() {
+            pump.wake();
+        }
+        Ok(())
+    }
+}
+
+impl Clone for RemoteSpawner {
+    fn clone(&self) -> Self {
+        self.shared.lock().unwrap().remotes += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for RemoteSpawner {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        shared.remotes -= 1;
+        if shared.remotes == 0
+            && let Some(pump) = shared.pump.take()
+        {
+            pump.wake();
+        }
+    }
+}
+
+/// Drains spawn requests on the owning thread.
+struct Pump {
+    shared: Arc<Mutex<Shared>>,
+    spawner: LocalSpawner,
+}
+
+impl Future for Pump {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            let job = {
+                let mut shared = self.shared.lock().unwrap();
+                match shared.jobs.pop_front() {
+                    Some(job) => job,
+                    None if shared.remotes == 0 => return Poll::Ready(()),
+                    None => {
+                        shared.pump = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            };
+            job(&self.spawner);
+        }
+    }
+}
+
+impl Drop for Pump {
+    fn drop(&mut self) {
+        let jobs = {
+            let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+            shared.closed = true;
+            core::mem::take(&mut shared.jobs)
+        };
+        // Dropped outside the lock, in case a job's captures spawn or drop remote spawners.
+        drop(jobs);
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+    use futures_executor::LocalPool;
+    use std::{sync::mpsc, thread};
+
+    #[test]
+    fn test_spawn_from_other_thread() {
+        let mut pool = LocalPool::new();
+        let local = LocalSpawner::new(Rc::new(pool.spawner()));
+        let remote = RemoteSpawner::new(&local).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        thread::scope(|s| {
+            s.spawn(|| {
+                let send_tx = tx.clone();
+                remote
+                    .spawn(async move { send_tx.send("send").unwrap() })
+                    .unwrap();
+                remote
+                    .spawn_fn(move || {
+                        // Holding an `Rc` makes the future `!Send`.
+                        let local = Rc::new("local");
+                        async move { tx.send(*local).unwrap() }
+                    })
+                    .unwrap();
+            });
+        });
+        drop(remote);
+
+        pool.run();
+        assert_eq!(
+            rx.try_iter().collect::<alloc::vec::Vec<_>>(),
+            ["send", "local"]
+        );
+    }
+
+    #[test]
+    fn test_spawn_after_pump_dropped_fails() {
+        let pool = LocalPool::new();
+        let local = LocalSpawner::new(Rc::new(pool.spawner()));
+        let remote = RemoteSpawner::new(&local).unwrap();
+        drop(pool);
+
+        assert!(matches!(remote.spawn(async {}), Err(SpawnError::Shutdown)));
+    }
+}
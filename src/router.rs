@@ -0,0 +1,102 @@
+use crate::{LocalSpawner, Result, TaskMeta};
+use alloc::rc::Rc;
+use core::{cell::Cell, future::Future};
+
+/// A spawner that routes each task to one of several `LocalSpawner`s, e.g. one per shard of a
+/// thread-per-core server, either round-robin or by a key.
+///
+/// Clones share the round-robin cursor. Create a `LocalSpawner` from a `RouterSpawner` to hand it
+/// to code expecting one; futures spawned through that are routed round-robin, and boxed first.
+#[derive(Clone)]
+pub struct RouterSpawner {
+    spawners: Rc<[LocalSpawner]>,
+    next: Rc<Cell<usize>>,
+}
+
+impl RouterSpawner {
+    /// Route between `spawners`, in the order given.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `spawners` is empty.
+    pub fn new(spawners: impl IntoIterator<Item = LocalSpawner>) -> Self {
+        let spawners: Rc<[LocalSpawner]> = spawners.into_iter().collect();
+        assert!(!spawners.is_empty(), "a `RouterSpawner` needs a spawner");
+        Self {
+            spawners,
+            next: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// Spawn a `Future` onto the next spawner in round-robin order.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.next_spawner().spawn_with_meta(f, TaskMeta::new::<F>())
+    }
+
+    /// Spawn a `Future` onto the spawner `key` maps to, e.g. a hash of a connection's address. The
+    /// same key always maps to the same spawner.
+    #[track_caller]
+    pub fn spawn_keyed<F: Future<Output = ()> + 'static>(&self, key: u64, f: F) -> Result<()> {
+        self.route(key).spawn_with_meta(f, TaskMeta::new::<F>())
+    }
+
+    /// The spawner `key` maps to.
+    pub fn route(&self, key: u64) -> &LocalSpawner {
+        &self.spawners[(key % self.spawners.len() as u64) as usize]
+    }
+
+    /// The spawners routed between, in the order given.
+    pub fn spawners(&self) -> &[LocalSpawner] {
+        &self.spawners
+    }
+
+    fn next_spawner(&self) -> &LocalSpawner {
+        let i = self.next.get();
+        self.next.set((i + 1) % self.spawners.len());
+        &self.spawners[i]
+    }
+}
+
+crate::impl_local_spawner!(RouterSpawner, |this, future| {
+    this.next_spawner().spawn_with_meta(
+        future,
+        TaskMeta::new::<core::pin::Pin<alloc::boxed::Box<dyn Future<Output = ()>>>>(),
+    )
+});
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+    use futures_executor::LocalPool;
+
+    #[test]
+    fn test_round_robin_and_keyed() {
+        let mut pools = [LocalPool::new(), LocalPool::new()];
+        let router = RouterSpawner::new(
+            pools
+                .iter()
+                .map(|pool| LocalSpawner::new(Rc::new(pool.spawner()))),
+        );
+
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        let record = |tag| {
+            let ran = ran.clone();
+            async move { ran.borrow_mut().push(tag) }
+        };
+        router.spawn(record("a")).unwrap();
+        router.spawn(record("b")).unwrap();
+        router.spawn(record("c")).unwrap();
+        router.spawn_keyed(3, record("d")).unwrap();
+        router.spawn_keyed(5, record("e")).unwrap();
+
+        pools[0].run_until_stalled();
+        assert_eq!(*ran.borrow(), ["a", "c"]);
+
+        ran.borrow_mut().clear();
+        pools[1].run_until_stalled();
+        assert_eq!(*ran.borrow(), ["b", "d", "e"]);
+    }
+}
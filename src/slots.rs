@@ -0,0 +1,165 @@
+//! A fixed set of pre-allocated task slots that shims can emplace futures into instead of
+//! allocating memory for every spawn.
+
+use alloc::{
+    alloc::{Layout, alloc, dealloc, handle_alloc_error},
+    rc::Rc,
+    vec::Vec,
+};
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    ptr::NonNull,
+    task::{Context, Poll},
+};
+
+pub(crate) struct SlotPool {
+    base: *mut u8,
+    slot_layout: Layout,
+    count: usize,
+    free: RefCell<Vec<usize>>,
+}
+
+impl SlotPool {
+    /// Allocate `count` slots, each able to hold a future of up to `slot_layout`.
+    pub(crate) fn new(count: usize, slot_layout: Layout) -> Self {
+        let slot_layout = slot_layout.pad_to_align();
+        let base = match Self::storage_layout(count, slot_layout) {
+            Some(layout) if layout.size() > 0 => {
+                let base = unsafe { alloc(layout) };
+                if base.is_null() {
+                    handle_alloc_error(layout);
+                }
+                base
+            }
+            Some(_) => core::ptr::null_mut(),
+            None => panic!("task slot storage overflows `isize::MAX` bytes"),
+        };
+        Self {
+            base,
+            slot_layout,
+            count,
+            free: RefCell::new((0..count).rev().collect()),
+        }
+    }
+
+    fn storage_layout(count: usize, slot_layout: Layout) -> Option<Layout> {
+        let size = slot_layout.size().checked_mul(count)?;
+        Layout::from_size_align(size, slot_layout.align()).ok()
+    }
+
+    /// Take a free slot for a future of `layout`, if it fits and one is available.
+    pub(crate) fn take(&self, layout: Layout) -> Option<NonNull<()>> {
+        if self.base.is_null()
+            || layout.size() > self.slot_layout.size()
+            || layout.align() > self.slot_layout.align()
+        {
+            return None;
+        }
+        let index = self.free.borrow_mut().pop()?;
+        let ptr = unsafe { self.base.add(index * self.slot_layout.size()) } as *mut ();
+        // Safety: `base` is non-null, and so is every slot following it.
+        Some(unsafe { NonNull::new_unchecked(ptr) })
+    }
+
+    /// Whether `ptr` points into one of this pool's slots.
+    pub(crate) fn owns(&self, ptr: *mut ()) -> bool {
+        let addr = ptr.addr();
+        let base = self.base.addr();
+        !self.base.is_null() && addr >= base && addr < base + self.count * self.slot_layout.size()
+    }
+
+    /// Return a slot taken by `take` to the pool.
+    pub(crate) fn release(&self, ptr: *mut ()) {
+        // Safety: `ptr` was derived from `self.base` by `take`.
+        let offset = unsafe { (ptr as *mut u8).offset_from(self.base) } as usize;
+        let index = offset / self.slot_layout.size();
+        self.free.borrow_mut().push(index);
+    }
+
+    /// The number of slots not currently holding a future.
+    #[cfg(test)]
+    pub(crate) fn available(&self) -> usize {
+        self.free.borrow().len()
+    }
+}
+
+impl Drop for SlotPool {
+    fn drop(&mut self) {
+        if let Some(layout) = Self::storage_layout(self.count, self.slot_layout)
+            && layout.size() > 0
+        {
+            unsafe { dealloc(self.base, layout) };
+        }
+    }
+}
+
+/// A future living in a `SlotPool` slot. Polls it in place, and drops it and frees the slot when
+/// dropped itself.
+pub(crate) struct SlotFuture {
+    pool: Rc<SlotPool>,
+    future: *mut dyn Future<Output = ()>,
+}
+
+impl SlotFuture {
+    /// Safety: `future` must point to an initialized future in a slot taken from `pool`, which the
+    /// returned `SlotFuture` takes ownership of.
+    pub(crate) unsafe fn new(pool: Rc<SlotPool>, future: *mut dyn Future<Output = ()>) -> Self {
+        Self { pool, future }
+    }
+}
+
+impl Future for SlotFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let future = self.future;
+        // Safety: the future never moves out of its slot until it is dropped.
+        unsafe { Pin::new_unchecked(&mut *future) }.poll(cx)
+    }
+}
+
+impl Drop for SlotFuture {
+    fn drop(&mut self) {
+        unsafe { core::ptr::drop_in_place(self.future) };
+        self.pool.release(self.future as *mut ());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slots_are_reused() {
+        let pool = Rc::new(SlotPool::new(2, Layout::new::<[u64; 4]>()));
+        assert!(pool.take(Layout::new::<[u64; 8]>()).is_none());
+
+        let a = pool.take(Layout::new::<u64>()).unwrap();
+        let b = pool.take(Layout::new::<[u64; 4]>()).unwrap();
+        assert!(pool.owns(a.as_ptr()) && pool.owns(b.as_ptr()));
+        assert!(pool.take(Layout::new::<u64>()).is_none());
+
+        let ran = Rc::new(RefCell::new(false));
+        let ran2 = ran.clone();
+        let future = async move {
+            *ran2.borrow_mut() = true;
+        };
+        let future_ptr = a.cast().as_ptr();
+        unsafe { core::ptr::write(future_ptr, future) };
+        let future_ptr = future_ptr as *mut dyn Future<Output = ()>;
+        let slot_future = unsafe { SlotFuture::new(pool.clone(), future_ptr) };
+
+        pollster::block_on(slot_future);
+        assert!(*ran.borrow());
+        assert_eq!(Rc::strong_count(&ran), 1);
+        assert_eq!(pool.available(), 1);
+
+        let c = pool.take(Layout::new::<u64>()).unwrap();
+        assert_eq!(a, c);
+        pool.release(b.as_ptr());
+        pool.release(c.as_ptr());
+        assert_eq!(pool.available(), 2);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{LocalSpawner, Result, TaskMeta};
+use alloc::{boxed::Box, rc::Rc};
+use core::{future::Future, pin::Pin};
+
+/// Spawning through something that may or may not be a [`LocalSpawner`], for APIs that want to
+/// accept `impl LocalSpawnExt` rather than the concrete type, e.g. to also take a library's own
+/// spawner.
+///
+/// Implementors only provide [`spawn_erased`](Self::spawn_erased), which makes the trait usable
+/// as `dyn LocalSpawnExt`. `spawn` boxes the future by default, and can be overridden to avoid
+/// that, as `LocalSpawner` does.
+///
+/// ```
+/// # use core::{future::Future, pin::Pin};
+/// use ispawn::{LocalSpawnExt, TaskMeta};
+///
+/// struct Discard;
+///
+/// impl LocalSpawnExt for Discard {
+///     fn spawn_erased(
+///         &self,
+///         future: Pin<Box<dyn Future<Output = ()>>>,
+///         _meta: TaskMeta,
+///     ) -> ispawn::Result<()> {
+///         drop(future);
+///         Ok(())
+///     }
+/// }
+///
+/// fn start_background_work(spawner: &impl LocalSpawnExt) -> ispawn::Result<()> {
+///     spawner.spawn(async {})
+/// }
+///
+/// start_background_work(&Discard).unwrap();
+/// ```
+pub trait LocalSpawnExt {
+    /// Spawn a boxed `Future`, along with the metadata of the future it was created from.
+    fn spawn_erased(&self, future: Pin<Box<dyn Future<Output = ()>>>, meta: TaskMeta)
+    -> Result<()>;
+
+    /// Spawn a `Future`.
+    #[track_caller]
+    fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()>
+    where
+        Self: Sized,
+    {
+        self.spawn_erased(Box::pin(f), TaskMeta::new::<F>())
+    }
+}
+
+impl LocalSpawnExt for LocalSpawner {
+    fn spawn_erased(
+        &self,
+        future: Pin<Box<dyn Future<Output = ()>>>,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        self.spawn_boxed_with_meta(future, meta)
+    }
+
+    #[track_caller]
+    fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.spawn_with_meta(f, TaskMeta::new::<F>())
+    }
+}
+
+impl<T: LocalSpawnExt + ?Sized> LocalSpawnExt for &T {
+    fn spawn_erased(
+        &self,
+        future: Pin<Box<dyn Future<Output = ()>>>,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        (**self).spawn_erased(future, meta)
+    }
+}
+
+impl<T: LocalSpawnExt + ?Sized> LocalSpawnExt for Rc<T> {
+    fn spawn_erased(
+        &self,
+        future: Pin<Box<dyn Future<Output = ()>>>,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        (**self).spawn_erased(future, meta)
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod test {
+    use super::*;
+    use crate::test_util::RecordingSpawner;
+
+    fn spawn_twice(spawner: &impl LocalSpawnExt) {
+        spawner.spawn(async {}).unwrap();
+        spawner.spawn(async {}).unwrap();
+    }
+
+    #[test]
+    fn test_generic_and_dyn_spawns() {
+        let recorder = RecordingSpawner::new();
+        let spawner = recorder.spawner();
+        spawn_twice(&spawner);
+
+        let erased: Rc<dyn LocalSpawnExt> = Rc::new(spawner);
+        spawn_twice(&erased);
+
+        let spawned = recorder.spawned();
+        assert_eq!(spawned.len(), 4);
+        assert!(
+            spawned
+                .iter()
+                .all(|meta| meta.type_name().contains("spawn_twice"))
+        );
+        assert_eq!(recorder.run_until_stalled(), 4);
+    }
+}
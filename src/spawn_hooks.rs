@@ -0,0 +1,160 @@
+use crate::{LocalSpawner, TaskMeta};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Callbacks run over the lifetime of every task spawned through a `LocalSpawner`, installed with
+/// [`LocalSpawner::with_spawn_hooks`], e.g. for profilers, leak detectors or custom telemetry.
+///
+/// ```
+/// static HOOKS: ispawn::SpawnHooks = ispawn::SpawnHooks {
+///     on_spawn: |meta| println!("spawned {} at {}", meta.type_name(), meta.location()),
+///     ..ispawn::SpawnHooks::NONE
+/// };
+///
+/// fn install(spawner: ispawn::LocalSpawner) -> ispawn::LocalSpawner {
+///     spawner.with_spawn_hooks(&HOOKS)
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct SpawnHooks {
+    /// Called once the executor has accepted a task.
+    pub on_spawn: fn(meta: &TaskMeta),
+
+    /// Called when a task's future completes.
+    pub on_complete: fn(meta: &TaskMeta),
+
+    /// Called when polling a task's future panics, while the panic unwinds.
+    pub on_panic: fn(meta: &TaskMeta),
+}
+
+impl SpawnHooks {
+    /// Hooks that do nothing, to fill in the ones that aren't needed.
+    pub const NONE: Self = Self {
+        on_spawn: |_| {},
+        on_complete: |_| {},
+        on_panic: |_| {},
+    };
+}
+
+impl LocalSpawner {
+    /// Run `hooks` for every task spawned through this spawner and its clones.
+    ///
+    /// Each future is wrapped to report its completion and panics, so it's still spawned on the
+    /// executor directly, without boxing. The hooks are only preserved by
+    /// [`into_raw_parts`](Self::into_raw_parts) with the `alloc` feature.
+    pub fn with_spawn_hooks(mut self, hooks: &'static SpawnHooks) -> Self {
+        self.spawn_hooks = Some(hooks);
+        self
+    }
+}
+
+pub(crate) struct Hooked<F> {
+    hooks: &'static SpawnHooks,
+    meta: TaskMeta,
+    future: F,
+}
+
+impl<F> Hooked<F> {
+    pub(crate) fn new(hooks: &'static SpawnHooks, meta: TaskMeta, future: F) -> Self {
+        Self {
+            hooks,
+            meta,
+            future,
+        }
+    }
+}
+
+impl<F: Future> Future for Hooked<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // Safety: `future` is structurally pinned; the other fields are never pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let guard = OnPanic {
+            hooks: this.hooks,
+            meta: &this.meta,
+        };
+        let poll = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx);
+        core::mem::forget(guard);
+        if poll.is_ready() {
+            (this.hooks.on_complete)(&this.meta);
+        }
+        poll
+    }
+}
+
+/// Runs `on_panic` if dropped while the future is being polled, i.e. when the poll unwinds.
+struct OnPanic<'a> {
+    hooks: &'static SpawnHooks,
+    meta: &'a TaskMeta,
+}
+
+impl Drop for OnPanic<'_> {
+    fn drop(&mut self) {
+        (self.hooks.on_panic)(self.meta);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::Result;
+    use alloc::{boxed::Box, rc::Rc, vec::Vec};
+    use core::{cell::RefCell, task::Waker};
+
+    std::thread_local! {
+        static EVENTS: RefCell<Vec<(&'static str, u32, usize)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(event: &'static str, meta: &TaskMeta) {
+        let event = (event, meta.location().line(), meta.layout().size());
+        EVENTS.with(|events| events.borrow_mut().push(event));
+    }
+
+    static HOOKS: SpawnHooks = SpawnHooks {
+        on_spawn: |meta| record("spawn", meta),
+        on_complete: |meta| record("complete", meta),
+        on_panic: |meta| record("panic", meta),
+    };
+
+    #[derive(Default)]
+    struct Queue(RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>>);
+
+    impl crate::BoxedLocalSpawn for Queue {
+        fn spawn_boxed(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+            self.0.borrow_mut().push(future);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_spawn_hooks() {
+        let queue = Rc::new(Queue::default());
+        let spawner = LocalSpawner::new(queue.clone()).with_spawn_hooks(&HOOKS);
+
+        let payload = [0u8; 32];
+        let first = async move { assert_eq!(payload[0], 0) };
+        let line = line!() + 1;
+        spawner.spawn(first).unwrap();
+        spawner.spawn(async { panic!("task failed") }).unwrap();
+
+        let mut futures = queue.0.take();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(futures[0].as_mut().poll(&mut cx).is_ready());
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _ = futures[1].as_mut().poll(&mut cx);
+        }));
+        assert!(panicked.is_err());
+
+        let events = EVENTS.with(|events| events.take());
+        let kinds: Vec<_> = events.iter().map(|event| event.0).collect();
+        assert_eq!(kinds, ["spawn", "spawn", "complete", "panic"]);
+        let tasks: Vec<_> = events.iter().map(|event| (event.1, event.2)).collect();
+        assert_eq!((tasks[0], tasks[1]), (tasks[2], tasks[3]));
+        assert_eq!((tasks[0].0, tasks[1].0), (line, line + 1));
+        assert!(tasks[0].1 >= 32);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{LocalSpawner, SpawnError, TaskMeta};
+use alloc::rc::{Rc, Weak};
+use core::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+impl LocalSpawner {
+    /// Spawn a `Future`, handing it back on failure like a channel's `SendError` does, e.g. to
+    /// retry on another spawner.
+    ///
+    /// The future is handed back if the executor dropped it without ever polling it by the time
+    /// spawning failed, which covers executors that reject a task before or while it is handed
+    /// over. Sharing the way back costs an allocation per spawn.
+    #[track_caller]
+    pub fn spawn_or_return<F: Future<Output = ()> + 'static>(
+        &self,
+        f: F,
+    ) -> core::result::Result<(), SpawnFailed<F>> {
+        let returned = Rc::new(Cell::new(None));
+        let future = Returnable {
+            future: Some(f),
+            polled: false,
+            returned: Rc::downgrade(&returned),
+        };
+        self.spawn_with_meta(future, TaskMeta::new::<F>())
+            .map_err(|error| SpawnFailed {
+                error,
+                future: returned.take(),
+            })
+    }
+}
+
+/// The error returned by [`LocalSpawner::spawn_or_return`].
+pub struct SpawnFailed<F> {
+    /// Why spawning failed.
+    pub error: SpawnError,
+
+    /// The future that couldn't be spawned, unless the executor kept it or polled it.
+    pub future: Option<F>,
+}
+
+impl<F> core::fmt::Debug for SpawnFailed<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SpawnFailed")
+            .field("error", &self.error)
+            .field("returned", &self.future.is_some())
+            .finish()
+    }
+}
+
+impl<F> core::fmt::Display for SpawnFailed<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl<F> core::error::Error for SpawnFailed<F> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Hands its future back through `returned` if it's dropped before it was ever polled.
+struct Returnable<F> {
+    /// Only pinned once `polled` is set, so it can be moved out until then.
+    future: Option<F>,
+    polled: bool,
+    returned: Weak<Cell<Option<F>>>,
+}
+
+impl<F: Future<Output = ()>> Future for Returnable<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `future` is structurally pinned from here on, and never moved out again.
+        let this = unsafe { self.get_unchecked_mut() };
+        this.polled = true;
+        match this.future.as_mut() {
+            Some(future) => unsafe { Pin::new_unchecked(future) }.poll(cx),
+            None => Poll::Ready(()),
+        }
+    }
+}
+
+impl<F> Drop for Returnable<F> {
+    fn drop(&mut self) {
+        if !self.polled
+            && let Some(returned) = self.returned.upgrade()
+        {
+            returned.set(self.future.take());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Result, StaticLocalSpawner};
+    use alloc::boxed::Box;
+
+    struct Rejecting;
+
+    impl crate::BoxedLocalSpawn for Rejecting {
+        fn spawn_boxed(&self, _: Pin<Box<dyn Future<Output = ()>>>) -> Result<()> {
+            Err(SpawnError::Shutdown)
+        }
+    }
+
+    #[test]
+    fn test_spawn_or_return() {
+        let ex = Box::leak(Box::new(StaticLocalSpawner::<1>::new()));
+        let spawner = ex.spawner();
+        let ran = Rc::new(Cell::new(0));
+
+        // Rejected by the executor after the future was handed over.
+        let ran2 = ran.clone();
+        let rejected = LocalSpawner::new(Rc::new(Rejecting))
+            .spawn_or_return(async move { ran2.set(ran2.get() + 1) })
+            .unwrap_err();
+        assert!(matches!(rejected.error, SpawnError::Shutdown));
+        spawner.spawn(rejected.future.unwrap()).unwrap();
+
+        // Rejected before the future was written into a task.
+        let ran3 = ran.clone();
+        let full = spawner
+            .spawn_or_return(async move { ran3.set(ran3.get() + 1) })
+            .unwrap_err();
+        assert!(matches!(full.error, SpawnError::QueueFull));
+
+        ex.run_until_stalled();
+        spawner.spawn_or_return(full.future.unwrap()).unwrap();
+        ex.run_until_stalled();
+        assert_eq!(ran.get(), 2);
+        assert_eq!(Rc::strong_count(&ran), 1);
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{Capabilities, Result, SendExtensions, SpawnOptions, TaskMeta};
+use core::{alloc::Layout, future::Future, mem::ManuallyDrop, ptr::NonNull};
+
+/// A spawner that can spawn `Send` futures and can itself be shared across threads, for
+/// multi-threaded executors.
+///
+/// This is the `Send` counterpart of [`LocalSpawner`](crate::LocalSpawner) and uses the same
+/// two-phase spawn protocol, through [`IntoSpawner`].
+pub struct Spawner {
+    handle: *const (),
+    vtable: &'static SpawnerVtable,
+}
+
+// Safety: `IntoSpawner` requires the handle's underlying spawner to be `Send + Sync`.
+unsafe impl Send for Spawner {}
+unsafe impl Sync for Spawner {}
+
+impl Spawner {
+    /// Create a new `Spawner`.
+    pub fn new<T: IntoSpawner + 'static>(inner: T) -> Self {
+        Self {
+            handle: unsafe { T::into_handle(inner) },
+            vtable: SpawnerVtable::get::<T>(),
+        }
+    }
+
+    /// The optional capabilities of the underlying executor.
+    pub fn extensions(&self) -> &'static SendExtensions {
+        &self.vtable.ext
+    }
+
+    /// What the underlying executor supports, including the capabilities described by its
+    /// [`SendExtensions`].
+    pub fn capabilities(&self) -> Capabilities {
+        self.vtable.ext.all_capabilities()
+    }
+
+    /// The name of the underlying executor (see [`SendExtensions::name`]), or the type name of the
+    /// spawner this `Spawner` was created from.
+    pub fn name(&self) -> &'static str {
+        self.vtable.ext.name.unwrap_or_else(self.vtable.type_name)
+    }
+
+    /// Spawn a `Future`.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(&self, f: F) -> Result<()> {
+        self.spawn_with_meta(f, TaskMeta::new::<F>())
+    }
+
+    /// Spawn a `Future` with the given [`SpawnOptions`], e.g. at a low priority. Executors that
+    /// don't support an option spawn the task as usual.
+    #[track_caller]
+    pub fn spawn_with_options<F: Future<Output = ()> + Send + 'static>(
+        &self,
+        f: F,
+        options: SpawnOptions,
+    ) -> Result<()> {
+        self.spawn_with_meta(f, TaskMeta::new::<F>().with_options(options))
+    }
+
+    /// Spawn a `Future` with a name (see [`SpawnOptions::name`]), so it can be told apart from
+    /// other tasks when debugging.
+    #[track_caller]
+    pub fn spawn_named<F: Future<Output = ()> + Send + 'static>(
+        &self,
+        name: &'static str,
+        f: F,
+    ) -> Result<()> {
+        self.spawn_with_options(f, SpawnOptions::DEFAULT.with_name(name))
+    }
+
+    /// Run a blocking function without blocking the executor's threads, on its blocking thread pool
+    /// if it has one, see [`LocalSpawner::spawn_blocking`](crate::LocalSpawner::spawn_blocking).
+    #[cfg(feature = "alloc")]
+    pub fn spawn_blocking<F: FnOnce() + Send + 'static>(&self, f: F) -> Result<()> {
+        unsafe {
+            crate::blocking::spawn_blocking(
+                self.vtable.ext.spawn_blocking,
+                self.handle,
+                alloc::boxed::Box::new(f),
+            )
+        }
+    }
+
+    /// Spawn a `Future` on behalf of a wrapper, forwarding the metadata of the future the wrapper
+    /// was originally given rather than that of the wrapping future.
+    pub(crate) fn spawn_with_meta<F: Future<Output = ()> + Send + 'static>(
+        &self,
+        f: F,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        let f = tracing::Instrument::instrument(f, crate::trace::task_span(&meta));
+        self.place(f, meta)
+    }
+
+    fn place<F: Future<Output = ()> + Send + 'static>(&self, f: F, meta: TaskMeta) -> Result<()> {
+        if !self.vtable.ext.fits_inline(Layout::new::<F>()) {
+            #[cfg(feature = "alloc")]
+            return self.emplace(alloc::boxed::Box::pin(f), meta);
+
+            // Without an allocator there is nowhere else to put the future.
+            #[cfg(not(feature = "alloc"))]
+            return Err(crate::SpawnError::Other);
+        }
+
+        self.emplace(f, meta)
+    }
+
+    fn emplace<F: Future<Output = ()> + Send + 'static>(&self, f: F, meta: TaskMeta) -> Result<()> {
+        let slot = SendSpawnSlot {
+            handle: self.handle,
+            vtable: self.vtable,
+            future_layout: Layout::new::<F>(),
+        };
+        unsafe {
+            let spawn_completer = (self.vtable.spawn_dyn)(self.handle, slot, Layout::new::<F>())?;
+            spawn_completer.spawn(f, meta)
+        }
+    }
+}
+
+impl Clone for Spawner {
+    fn clone(&self) -> Self {
+        unsafe {
+            (self.vtable.on_clone)(self.handle);
+        }
+        Self {
+            handle: self.handle,
+            vtable: self.vtable,
+        }
+    }
+}
+
+impl core::fmt::Debug for Spawner {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Spawner")
+            .field("executor", &self.name())
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl core::fmt::Display for Spawner {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} ({:p})", self.name(), self.handle)
+    }
+}
+
+impl Drop for Spawner {
+    fn drop(&mut self) {
+        unsafe {
+            (self.vtable.on_drop)(self.handle);
+        }
+    }
+}
+
+/// The methods of this trait are meant only for internal use in `ispawn`. Implement it to support
+/// creating an `ispawn::Spawner` from a multi-threaded executor's spawner.
+///
+/// The handle may be used from any thread, hence the `Send + Sync` bound.
+pub trait IntoSpawner: Send + Sync {
+    /// # Safety
+    ///
+    /// The implementer must ensure that the memory behind the returned pointer is 'static.
+    unsafe fn into_handle(self) -> *const ();
+
+    /// Optional capabilities beyond the core contract of the methods below.
+    const EXTENSIONS: SendExtensions = SendExtensions::NONE;
+
+    /// Fails with `SpawnError::AllocFailed` if memory for the task can't be allocated.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been returned by `into_handle` and not yet released by `on_drop`.
+    unsafe fn spawn_dyn(
+        handle: *const (),
+        slot: SendSpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SendSpawnCompleter>;
+
+    /// # Safety
+    ///
+    /// `handle` must be live, and `task_ptr_as_dyn_future` must point to the initialized future
+    /// of a task allocated by `spawn_dyn` on the same handle.
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut (dyn Future<Output = ()> + Send),
+        meta: TaskMeta,
+    ) -> Result<()>;
+
+    /// Release a task allocated by `spawn_dyn` whose future was never written, e.g. because
+    /// constructing the future panicked. The default leaks the task.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be live, and `task_ptr` must have been returned by `spawn_dyn` on the same
+    /// handle for a future of `future_layout`, and not yet passed to `finish_spawn`.
+    unsafe fn cancel_spawn(handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        let _ = (handle, task_ptr, future_layout);
+    }
+
+    /// # Safety
+    ///
+    /// `handle` must be live.
+    unsafe fn on_clone(handle: *const ());
+
+    /// # Safety
+    ///
+    /// `handle` must be live, and must not be used again by the caller after this returns.
+    unsafe fn on_drop(handle: *const ());
+}
+
+/// The `Send` counterpart of [`SpawnCompleter`](crate::SpawnCompleter).
+pub struct SendSpawnCompleter {
+    handle: *const (),
+    vtable: &'static SpawnerVtable,
+    task_ptr: NonNull<()>,
+    future_ptr: NonNull<()>,
+    future_layout: Layout,
+}
+
+/// The `Send` counterpart of [`SpawnSlot`](crate::SpawnSlot).
+pub struct SendSpawnSlot {
+    handle: *const (),
+    vtable: &'static SpawnerVtable,
+    future_layout: Layout,
+}
+
+impl SendSpawnSlot {
+    /// See [`SpawnSlot::layout`](crate::SpawnSlot::layout).
+    pub fn layout(&self) -> Layout {
+        self.future_layout
+    }
+
+    /// See [`SpawnSlot::build`](crate::SpawnSlot::build).
+    pub fn build(self, task_ptr: NonNull<()>, future_ptr: NonNull<()>) -> SendSpawnCompleter {
+        debug_assert!(
+            future_ptr
+                .addr()
+                .get()
+                .is_multiple_of(self.future_layout.align()),
+            "future pointer isn't aligned to the future's layout"
+        );
+        SendSpawnCompleter {
+            handle: self.handle,
+            vtable: self.vtable,
+            task_ptr,
+            future_ptr,
+            future_layout: self.future_layout,
+        }
+    }
+}
+
+impl SendSpawnCompleter {
+    /// Safety: The caller must ensure that `F` has the same layout that was used to create this
+    /// `SendSpawnCompleter`.
+    unsafe fn spawn<F: Future<Output = ()> + Send + 'static>(
+        self,
+        f: F,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        let this = ManuallyDrop::new(self);
+        unsafe {
+            core::ptr::write(this.future_ptr.cast::<F>().as_ptr(), f);
+
+            // See `SpawnCompleter::spawn`: the intermediate cast to `*mut F` is only used to get
+            // the right vtable.
+            let task_ptr =
+                this.task_ptr.cast::<F>().as_ptr() as *mut (dyn Future<Output = ()> + Send);
+            (this.vtable.finish_spawn)(this.handle, task_ptr, meta)
+        }
+    }
+}
+
+/// A `SendSpawnCompleter` that is dropped without spawning releases its task.
+impl Drop for SendSpawnCompleter {
+    fn drop(&mut self) {
+        unsafe {
+            (self.vtable.cancel_spawn)(self.handle, self.task_ptr.as_ptr(), self.future_layout)
+        }
+    }
+}
+
+struct SpawnerVtable {
+    ext: SendExtensions,
+
+    type_name: fn() -> &'static str,
+
+    spawn_dyn: unsafe fn(
+        handle: *const (),
+        slot: SendSpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SendSpawnCompleter>,
+
+    finish_spawn: unsafe fn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut (dyn Future<Output = ()> + Send),
+        meta: TaskMeta,
+    ) -> Result<()>,
+
+    cancel_spawn: unsafe fn(handle: *const (), task_ptr: *mut (), future_layout: Layout),
+
+    on_clone: unsafe fn(handle: *const ()),
+
+    on_drop: unsafe fn(handle: *const ()),
+}
+
+impl SpawnerVtable {
+    fn get<T: IntoSpawner + 'static>() -> &'static Self {
+        &const {
+            SpawnerVtable {
+                ext: T::EXTENSIONS,
+                type_name: core::any::type_name::<T>,
+                spawn_dyn: T::spawn_dyn,
+                finish_spawn: T::finish_spawn,
+                cancel_spawn: T::cancel_spawn,
+                on_clone: T::on_clone,
+                on_drop: T::on_drop,
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use alloc::boxed::Box;
+    use std::sync::mpsc;
+
+    /// Runs every spawned future to completion on a new thread.
+    struct ThreadSpawner;
+
+    impl IntoSpawner for ThreadSpawner {
+        unsafe fn into_handle(self) -> *const () {
+            core::ptr::null()
+        }
+
+        unsafe fn spawn_dyn(
+            _: *const (),
+            slot: SendSpawnSlot,
+            future_layout: Layout,
+        ) -> Result<SendSpawnCompleter> {
+            let future_ptr = crate::boxed::alloc_future(future_layout)?;
+            let task_ptr = future_ptr;
+            Ok(slot.build(task_ptr, future_ptr))
+        }
+
+        unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+            unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+        }
+
+        unsafe fn finish_spawn(
+            _: *const (),
+            task_ptr_as_dyn_future: *mut (dyn Future<Output = ()> + Send),
+            _meta: TaskMeta,
+        ) -> Result<()> {
+            let future = Box::into_pin(unsafe { Box::from_raw(task_ptr_as_dyn_future) });
+            std::thread::spawn(move || pollster::block_on(future));
+            Ok(())
+        }
+
+        unsafe fn on_clone(_: *const ()) {}
+
+        unsafe fn on_drop(_: *const ()) {}
+    }
+
+    #[test]
+    fn test_spawn_send_future() {
+        let spawner = Spawner::new(ThreadSpawner);
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            spawner
+                .clone()
+                .spawn(async move { tx.send(42).unwrap() })
+                .unwrap();
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(rx.recv().unwrap(), 42);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{LocalSpawner, Result, cancel::CancelSignal, tracker::TaskTracker};
+use alloc::vec::Vec;
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A set of `LocalSpawner`s, e.g. one per executor, that a task can be broadcast to.
+///
+/// This is the usual way to bootstrap a service that runs one instance per executor, like
+/// acceptors or metric flushers.
+#[derive(Clone, Default)]
+pub struct SpawnerSet {
+    spawners: Vec<LocalSpawner>,
+}
+
+impl SpawnerSet {
+    /// Create an empty `SpawnerSet`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a spawner with the set.
+    pub fn push(&mut self, spawner: LocalSpawner) {
+        self.spawners.push(spawner);
+    }
+
+    /// The number of spawners in the set.
+    pub fn len(&self) -> usize {
+        self.spawners.len()
+    }
+
+    /// Whether the set has no spawners.
+    pub fn is_empty(&self) -> bool {
+        self.spawners.is_empty()
+    }
+
+    /// The spawners in the set, in registration order.
+    pub fn spawners(&self) -> &[LocalSpawner] {
+        &self.spawners
+    }
+
+    /// Spawn one instance of a task onto every spawner in the set. `factory` is called with the
+    /// index of each spawner to create its instance.
+    ///
+    /// If any spawn fails, the instances spawned so far are cancelled and the error is returned.
+    #[track_caller]
+    pub fn broadcast<F, Fut>(&self, mut factory: F) -> Result<BroadcastHandle>
+    where
+        F: FnMut(usize) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let signal = CancelSignal::new();
+        let tracker = TaskTracker::new();
+
+        for (i, spawner) in self.spawners.iter().enumerate() {
+            if let Err(e) = spawner.spawn_guarded(factory(i), &signal, Some(&tracker)) {
+                signal.cancel();
+                return Err(e);
+            }
+        }
+
+        Ok(BroadcastHandle { tracker, signal })
+    }
+}
+
+impl FromIterator<LocalSpawner> for SpawnerSet {
+    fn from_iter<I: IntoIterator<Item = LocalSpawner>>(iter: I) -> Self {
+        Self {
+            spawners: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// A handle to every instance of a task spawned by [`SpawnerSet::broadcast`].
+///
+/// Awaiting the handle waits for all instances to finish. Dropping it does not cancel them.
+pub struct BroadcastHandle {
+    tracker: TaskTracker,
+    signal: CancelSignal,
+}
+
+impl BroadcastHandle {
+    /// The number of instances still running.
+    pub fn active(&self) -> usize {
+        self.tracker.active()
+    }
+
+    /// Cancel every instance. They are dropped the next time their executor polls them.
+    pub fn cancel(&self) {
+        self.signal.cancel();
+    }
+}
+
+impl Future for BroadcastHandle {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.tracker.idle()).poll(cx)
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use crate::test::local_pool;
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    #[test]
+    fn test_broadcast() {
+        let (mut ex1, spawner1) = local_pool();
+        let (mut ex2, spawner2) = local_pool();
+        let set: SpawnerSet = [spawner1, spawner2].into_iter().collect();
+
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        let mut handle = set
+            .broadcast(|i| {
+                let ran = ran.clone();
+                async move { ran.borrow_mut().push(i) }
+            })
+            .unwrap();
+        assert_eq!(handle.active(), 2);
+
+        ex1.run_until_stalled();
+        assert_eq!(*ran.borrow(), [0]);
+        assert_eq!(handle.active(), 1);
+
+        ex2.run_until(&mut handle);
+        assert_eq!(*ran.borrow(), [0, 1]);
+        assert_eq!(handle.active(), 0);
+    }
+}
@@ -0,0 +1,449 @@
+//! A fixed-capacity executor whose tasks live in statically sized slots, for targets without an
+//! allocator.
+
+use crate::{
+    Capabilities, DowncastLocalSpawner, Extensions, IntoLocalSpawner, LocalSpawner, Result,
+    SpawnCompleter, SpawnError, SpawnSlot, TaskMeta,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use core::{
+    alloc::Layout,
+    cell::{Cell, UnsafeCell},
+    future::Future,
+    mem::{ManuallyDrop, MaybeUninit},
+    pin::Pin,
+    ptr::NonNull,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+/// A single-threaded executor with room for `N` tasks, each holding a future of up to the size and
+/// alignment of `S`. Spawning never allocates: it fails with `SpawnError::QueueFull` while every
+/// slot is taken, and larger futures are boxed first (with the `alloc` feature) or rejected with
+/// `SpawnError::Other`.
+///
+/// The executor must live for `'static` to hand out spawners, e.g. in a `static_cell::StaticCell`
+/// on embedded targets:
+///
+/// ```
+/// let ex: &'static ispawn::StaticLocalSpawner<4> =
+///     Box::leak(Box::new(ispawn::StaticLocalSpawner::new()));
+/// ex.spawner().spawn(async { println!("hello") }).unwrap();
+/// ex.run_until_stalled();
+/// ```
+pub struct StaticLocalSpawner<const N: usize, S = [usize; 16]> {
+    slots: [Slot<S>; N],
+    /// Waiting for a slot to be freed, see `Extensions::poll_capacity`.
+    waiters: Waiters,
+    /// Set while `run_until_stalled` runs, which must not be re-entered by a task.
+    running: Cell<bool>,
+}
+
+/// Clears `running` once `run_until_stalled` returns or unwinds.
+struct Running<'a>(&'a Cell<bool>);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// Wakers to wake once a slot is freed. Without an allocator only the latest waiter is kept, and
+/// the one it replaces is woken right away to poll again.
+struct Waiters {
+    #[cfg(feature = "alloc")]
+    wakers: core::cell::RefCell<alloc::vec::Vec<Waker>>,
+    #[cfg(not(feature = "alloc"))]
+    waker: Cell<Option<Waker>>,
+}
+
+impl Waiters {
+    const fn new() -> Self {
+        Self {
+            #[cfg(feature = "alloc")]
+            wakers: core::cell::RefCell::new(alloc::vec::Vec::new()),
+            #[cfg(not(feature = "alloc"))]
+            waker: Cell::new(None),
+        }
+    }
+
+    fn register(&self, waker: &Waker) {
+        #[cfg(feature = "alloc")]
+        {
+            let mut wakers = self.wakers.borrow_mut();
+            if !wakers.iter().any(|w| w.will_wake(waker)) {
+                wakers.push(waker.clone());
+            }
+        }
+        #[cfg(not(feature = "alloc"))]
+        if let Some(replaced) = self.waker.replace(Some(waker.clone()))
+            && !replaced.will_wake(waker)
+        {
+            replaced.wake();
+        }
+    }
+
+    fn wake(&self) {
+        #[cfg(feature = "alloc")]
+        for waker in core::mem::take(&mut *self.wakers.borrow_mut()) {
+            waker.wake();
+        }
+        #[cfg(not(feature = "alloc"))]
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct Slot<S> {
+    header: Header,
+    storage: UnsafeCell<MaybeUninit<S>>,
+}
+
+struct Header {
+    state: Cell<State>,
+    /// Whether the task has been woken since it was last polled. Atomic because wakers are `Send`
+    /// and `Sync`, and may be woken from any thread.
+    woken: AtomicBool,
+    /// Set once the future has been written, and cleared once it has been dropped.
+    future: Cell<Option<NonNull<dyn Future<Output = ()>>>>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum State {
+    Free,
+    /// Taken by `spawn_dyn`, waiting for `finish_spawn`.
+    Reserved,
+    Spawned,
+}
+
+impl<const N: usize, S> StaticLocalSpawner<N, S> {
+    /// Create a new `StaticLocalSpawner` with every slot free.
+    pub const fn new() -> Self {
+        Self {
+            slots: [const {
+                Slot {
+                    header: Header {
+                        state: Cell::new(State::Free),
+                        woken: AtomicBool::new(false),
+                        future: Cell::new(None),
+                    },
+                    storage: UnsafeCell::new(MaybeUninit::uninit()),
+                }
+            }; N],
+            waiters: Waiters::new(),
+            running: Cell::new(false),
+        }
+    }
+
+    /// A `LocalSpawner` for this executor.
+    pub fn spawner(&'static self) -> LocalSpawner
+    where
+        S: 'static,
+    {
+        LocalSpawner::new(self)
+    }
+
+    /// The number of slots not currently holding a task.
+    pub fn available(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.header.state.get() == State::Free)
+            .count()
+    }
+
+    /// Poll tasks until none of them is ready to make progress.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from one of the executor's own tasks, which would poll the task that is
+    /// already being polled.
+    pub fn run_until_stalled(&self) {
+        assert!(
+            !self.running.replace(true),
+            "`StaticLocalSpawner::run_until_stalled` called from one of its own tasks"
+        );
+        let _running = Running(&self.running);
+        loop {
+            let mut progressed = false;
+            for slot in &self.slots {
+                let header = &slot.header;
+                if header.state.get() != State::Spawned
+                    || !header.woken.swap(false, Ordering::Acquire)
+                {
+                    continue;
+                }
+                progressed = true;
+                let Some(future) = header.future.get() else {
+                    continue;
+                };
+
+                let waker = ManuallyDrop::new(unsafe { Waker::from_raw(raw_waker(header)) });
+                let mut cx = Context::from_waker(&waker);
+                // Safety: the future never moves out of its slot.
+                let pinned = unsafe { Pin::new_unchecked(&mut *future.as_ptr()) };
+                if pinned.poll(&mut cx).is_ready() {
+                    header.future.set(None);
+                    unsafe { core::ptr::drop_in_place(future.as_ptr()) };
+                    header.state.set(State::Free);
+                    self.waiters.wake();
+                }
+            }
+            if !progressed {
+                return;
+            }
+        }
+    }
+}
+
+impl<const N: usize, S> Default for StaticLocalSpawner<N, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn raw_waker(header: &Header) -> RawWaker {
+    RawWaker::new(header as *const Header as *const (), &WAKER_VTABLE)
+}
+
+// Slots are never freed, so wakers need no reference counting. A waker outliving its task at
+// worst causes a spurious poll of whichever task takes the slot next. Wakers only ever touch the
+// atomic `woken` flag, which is what makes sending them to other threads sound.
+static WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |header| raw_waker(unsafe { &*(header as *const Header) }),
+    |header| wake(unsafe { &*(header as *const Header) }),
+    |header| wake(unsafe { &*(header as *const Header) }),
+    |_| {},
+);
+
+fn wake(header: &Header) {
+    header.woken.store(true, Ordering::Release);
+}
+
+impl<const N: usize, S: 'static> IntoLocalSpawner for &'static StaticLocalSpawner<N, S> {
+    const EXTENSIONS: Extensions = Extensions::NONE
+        .with_inline_capacity(Layout::new::<S>())
+        .with_capabilities(Capabilities::ZERO_ALLOC)
+        .with_poll_capacity(|handle, cx| {
+            let this = unsafe { &*(handle as *const StaticLocalSpawner<N, S>) };
+            if this.available() > 0 {
+                return Poll::Ready(());
+            }
+            this.waiters.register(cx.waker());
+            Poll::Pending
+        });
+
+    unsafe fn into_handle(self) -> *const () {
+        self as *const StaticLocalSpawner<N, S> as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        handle: *const (),
+        spawn_slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        let this = unsafe { &*(handle as *const StaticLocalSpawner<N, S>) };
+        let slot_layout = Layout::new::<S>();
+        if future_layout.size() > slot_layout.size() || future_layout.align() > slot_layout.align()
+        {
+            return Err(SpawnError::Other);
+        }
+        let slot = this
+            .slots
+            .iter()
+            .find(|slot| slot.header.state.get() == State::Free)
+            .ok_or(SpawnError::QueueFull)?;
+        slot.header.state.set(State::Reserved);
+
+        let task_ptr = NonNull::from(slot).cast::<()>();
+        let future_ptr = unsafe { NonNull::new_unchecked(slot.storage.get()) }.cast::<()>();
+        Ok(spawn_slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(handle: *const (), task_ptr: *mut (), _future_layout: Layout) {
+        let this = unsafe { &*(handle as *const StaticLocalSpawner<N, S>) };
+        let slot = unsafe { &*(task_ptr as *const Slot<S>) };
+        slot.header.state.set(State::Free);
+        this.waiters.wake();
+    }
+
+    unsafe fn finish_spawn(
+        _handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        // `task_ptr_as_dyn_future` points to the start of the slot, with the future's vtable.
+        let slot = unsafe { &*(task_ptr_as_dyn_future as *const Slot<S>) };
+        let offset = unsafe { slot.storage.get().byte_offset_from(slot) } as usize;
+        let future = unsafe { task_ptr_as_dyn_future.byte_add(offset) };
+        slot.header
+            .future
+            .set(Some(unsafe { NonNull::new_unchecked(future) }));
+        slot.header.woken.store(true, Ordering::Relaxed);
+        slot.header.state.set(State::Spawned);
+        Ok(())
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(handle: *const ()) -> Option<Self> {
+        Some(unsafe { &*(handle as *const StaticLocalSpawner<N, S>) })
+    }
+}
+
+unsafe impl<const N: usize, S: 'static> DowncastLocalSpawner for &'static StaticLocalSpawner<N, S> {
+    type Target = StaticLocalSpawner<N, S>;
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::*;
+    use alloc::{boxed::Box, rc::Rc};
+
+    fn leak<const N: usize>() -> &'static StaticLocalSpawner<N, [usize; 4]> {
+        Box::leak(Box::new(StaticLocalSpawner::new()))
+    }
+
+    #[test]
+    fn test_spawn_until_full() {
+        let ex = leak::<2>();
+        let spawner = ex.spawner();
+        assert!(spawner.capabilities().contains(Capabilities::ZERO_ALLOC));
+
+        let ran = Rc::new(Cell::new(0));
+        for _ in 0..2 {
+            let ran = ran.clone();
+            spawner
+                .spawn(async move { ran.set(ran.get() + 1) })
+                .unwrap();
+        }
+        assert!(matches!(
+            spawner.spawn(async {}),
+            Err(SpawnError::QueueFull)
+        ));
+        assert_eq!(ex.available(), 0);
+
+        ex.run_until_stalled();
+        assert_eq!(ran.get(), 2);
+        assert_eq!(Rc::strong_count(&ran), 1);
+        assert_eq!(ex.available(), 2);
+        spawner.spawn(async {}).unwrap();
+    }
+
+    #[test]
+    fn test_woken_task_is_polled_again() {
+        let ex = leak::<1>();
+
+        let polls = Rc::new(Cell::new(0));
+        let polls2 = polls.clone();
+        ex.spawner()
+            .spawn(core::future::poll_fn(move |cx| {
+                polls2.set(polls2.get() + 1);
+                if polls2.get() == 3 {
+                    return Poll::Ready(());
+                }
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }))
+            .unwrap();
+
+        ex.run_until_stalled();
+        assert_eq!(polls.get(), 3);
+        assert_eq!(ex.available(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_wake_from_other_thread() {
+        let ex = leak::<1>();
+
+        let (tx, rx) = std::sync::mpsc::channel::<Waker>();
+        let polls = Rc::new(Cell::new(0));
+        let polls2 = polls.clone();
+        ex.spawner()
+            .spawn(core::future::poll_fn(move |cx| {
+                polls2.set(polls2.get() + 1);
+                if polls2.get() == 2 {
+                    return Poll::Ready(());
+                }
+                tx.send(cx.waker().clone()).unwrap();
+                Poll::Pending
+            }))
+            .unwrap();
+
+        ex.run_until_stalled();
+        assert_eq!(polls.get(), 1);
+        let waker = rx.recv().unwrap();
+        std::thread::spawn(move || waker.wake()).join().unwrap();
+        ex.run_until_stalled();
+        assert_eq!(polls.get(), 2);
+        assert_eq!(ex.available(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "called from one of its own tasks")]
+    fn test_reentrant_run_panics() {
+        let ex = leak::<1>();
+        ex.spawner()
+            .spawn(core::future::poll_fn(move |cx| {
+                cx.waker().wake_by_ref();
+                ex.run_until_stalled();
+                Poll::<()>::Pending
+            }))
+            .unwrap();
+        ex.run_until_stalled();
+    }
+
+    #[test]
+    fn test_spawn_when_ready() {
+        use alloc::{sync::Arc, task::Wake};
+        use core::sync::atomic::{AtomicBool, Ordering};
+
+        struct Flag(AtomicBool);
+
+        impl Wake for Flag {
+            fn wake(self: Arc<Self>) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let ex = leak::<1>();
+        let spawner = ex.spawner();
+        let done = Rc::new(Cell::new(false));
+        let done2 = done.clone();
+        spawner
+            .spawn(core::future::poll_fn(move |_| {
+                if done2.get() {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            }))
+            .unwrap();
+        ex.run_until_stalled();
+        assert!(matches!(
+            spawner.try_spawn(async {}),
+            Err(SpawnError::QueueFull)
+        ));
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        let mut spawning = Box::pin(spawner.spawn_when_ready(async move { ran2.set(true) }));
+        let flag = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+        assert!(spawning.as_mut().poll(&mut cx).is_pending());
+
+        done.set(true);
+        ex.slots[0].header.woken.store(true, Ordering::SeqCst);
+        ex.run_until_stalled();
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert!(matches!(
+            spawning.as_mut().poll(&mut cx),
+            Poll::Ready(Ok(()))
+        ));
+        ex.run_until_stalled();
+        assert!(ran.get());
+    }
+}
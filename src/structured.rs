@@ -0,0 +1,66 @@
+use crate::{LocalSpawner, OwnedScope, Result, SpawnError, TaskScope};
+use core::future::Future;
+
+/// A spawner that enforces structured concurrency: every task must be attached to a scope that
+/// owns it, and fire-and-forget spawning fails with `SpawnError::DetachedForbidden`.
+///
+/// Hand this out instead of a `LocalSpawner` to make sure no code path can leave a task running
+/// unowned.
+#[derive(Clone)]
+pub struct StructuredSpawner {
+    inner: LocalSpawner,
+}
+
+impl StructuredSpawner {
+    /// Create a new `StructuredSpawner` spawning onto `inner`.
+    pub fn new(inner: LocalSpawner) -> Self {
+        Self { inner }
+    }
+
+    /// Open a scope on the underlying spawner. Its tasks are cancelled when it is dropped.
+    pub fn scope(&self) -> OwnedScope {
+        OwnedScope::new(self.inner.clone())
+    }
+
+    /// Open a [`TaskScope`] on the underlying spawner, which can be joined.
+    pub fn task_scope(&self) -> TaskScope {
+        TaskScope::new(self.inner.clone())
+    }
+
+    /// Always fails with `SpawnError::DetachedForbidden`: spawn through a [`scope`](Self::scope)
+    /// or [`task_scope`](Self::task_scope) instead.
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        drop(f);
+        Err(SpawnError::DetachedForbidden)
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_only_scoped_tasks_are_spawned() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = StructuredSpawner::new(LocalSpawner::new(Rc::new(ex.spawner())));
+
+        let ran = Rc::new(Cell::new(0));
+        let ran2 = ran.clone();
+        assert!(matches!(
+            spawner.spawn(async move { ran2.set(ran2.get() + 1) }),
+            Err(SpawnError::DetachedForbidden)
+        ));
+
+        let scope = spawner.scope();
+        let ran2 = ran.clone();
+        scope
+            .spawner()
+            .spawn(async move { ran2.set(ran2.get() + 1) })
+            .unwrap();
+        ex.run_until_stalled();
+        assert_eq!(ran.get(), 1);
+        assert_eq!(Rc::strong_count(&ran), 1);
+    }
+}
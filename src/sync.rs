@@ -0,0 +1,7 @@
+//! Atomics, taken from `portable-atomic` with the feature of the same name so that spawners
+//! relying on them also build for targets without native atomics.
+
+#[cfg(not(feature = "portable-atomic"))]
+pub(crate) use core::sync::atomic;
+#[cfg(feature = "portable-atomic")]
+pub(crate) use portable_atomic as atomic;
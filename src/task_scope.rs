@@ -0,0 +1,111 @@
+use crate::{LocalSpawner, Result, SpawnError, cancel::CancelSignal, tracker::TaskTracker};
+use core::{cell::Cell, future::Future};
+
+/// A nursery of child tasks spawned through a `LocalSpawner`.
+///
+/// [`join`](Self::join) closes the scope and waits for every child to finish. Dropping the scope
+/// cancels the children that are still running (they are dropped the next time the executor polls
+/// them).
+pub struct TaskScope {
+    inner: LocalSpawner,
+    signal: CancelSignal,
+    tracker: TaskTracker,
+    closed: Cell<bool>,
+}
+
+impl TaskScope {
+    /// Create a new `TaskScope` spawning onto `inner`.
+    pub fn new(inner: LocalSpawner) -> Self {
+        Self {
+            inner,
+            signal: CancelSignal::new(),
+            tracker: TaskTracker::new(),
+            closed: Cell::new(false),
+        }
+    }
+
+    /// Spawn a child task. Fails with `SpawnError::Shutdown` once the scope is closed.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        if self.is_closed() {
+            return Err(SpawnError::Shutdown);
+        }
+        self.inner
+            .spawn_guarded(f, &self.signal, Some(&self.tracker))
+    }
+
+    /// The number of children still running.
+    pub fn active(&self) -> usize {
+        self.tracker.active()
+    }
+
+    /// Stop accepting new children. The running ones are unaffected.
+    pub fn close(&self) {
+        self.closed.set(true);
+    }
+
+    /// Whether the scope has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    /// Cancel every running child and close the scope.
+    pub fn cancel(&self) {
+        self.close();
+        self.signal.cancel();
+    }
+
+    /// Close the scope, then wait until every child has finished.
+    pub async fn join(&self) {
+        self.close();
+        self.tracker.idle().await
+    }
+}
+
+impl Drop for TaskScope {
+    fn drop(&mut self) {
+        self.signal.cancel();
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use crate::test::{local_pool, pending_task};
+    use alloc::{rc::Rc, vec::Vec};
+    use core::cell::RefCell;
+
+    #[test]
+    fn test_join_waits_for_children() {
+        let (mut ex, spawner) = local_pool();
+        let scope = TaskScope::new(spawner);
+
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        for i in 0..3 {
+            let ran = ran.clone();
+            scope
+                .spawn(async move { ran.borrow_mut().push(i) })
+                .unwrap();
+        }
+        assert_eq!(scope.active(), 3);
+
+        ex.run_until(scope.join());
+        assert_eq!(*ran.borrow(), [0, 1, 2]);
+        assert!(matches!(scope.spawn(async {}), Err(SpawnError::Shutdown)));
+    }
+
+    #[test]
+    fn test_drop_cancels_children() {
+        let (mut ex, spawner) = local_pool();
+        let scope = TaskScope::new(spawner);
+
+        let (task, alive) = pending_task();
+        scope.spawn(task).unwrap();
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 2);
+
+        drop(scope);
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 1);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{LocalSpawner, Result, cancel::CancelSignal, tracker::TaskTracker};
+use core::{cell::RefCell, future::Future};
+
+/// A group of tasks spawned through a `LocalSpawner` that can be counted, joined and aborted
+/// together.
+///
+/// Unlike a [`TaskScope`](crate::TaskScope), a `TaskSet` never closes: tasks can be spawned into it
+/// again after joining or aborting. Dropping the set aborts the tasks that are still running.
+pub struct TaskSet {
+    inner: LocalSpawner,
+    signal: RefCell<CancelSignal>,
+    tracker: TaskTracker,
+}
+
+impl TaskSet {
+    /// Create an empty `TaskSet` spawning onto `inner`.
+    pub fn new(inner: LocalSpawner) -> Self {
+        Self {
+            inner,
+            signal: RefCell::new(CancelSignal::new()),
+            tracker: TaskTracker::new(),
+        }
+    }
+
+    /// Spawn a task into the set.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.inner
+            .spawn_guarded(f, &self.signal.borrow(), Some(&self.tracker))
+    }
+
+    /// The number of tasks in the set that are still running.
+    pub fn len(&self) -> usize {
+        self.tracker.active()
+    }
+
+    /// Whether every task in the set has finished.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Wait until every task in the set has finished, including tasks spawned while waiting.
+    pub async fn join_all(&self) {
+        self.tracker.idle().await
+    }
+
+    /// Abort every task in the set. They are dropped the next time their executor polls them, and
+    /// stop counting towards [`len`](Self::len) once they are.
+    pub fn abort_all(&self) {
+        self.signal.replace(CancelSignal::new()).cancel();
+    }
+}
+
+impl Drop for TaskSet {
+    fn drop(&mut self) {
+        self.signal.get_mut().cancel();
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use crate::test::{local_pool, pending_task};
+    use alloc::{rc::Rc, vec::Vec};
+    use core::cell::Cell;
+
+    #[test]
+    fn test_join_all() {
+        let (mut ex, spawner) = local_pool();
+        let set = TaskSet::new(spawner);
+
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        for i in 0..3 {
+            let ran = ran.clone();
+            set.spawn(async move { ran.borrow_mut().push(i) }).unwrap();
+        }
+        assert_eq!(set.len(), 3);
+
+        ex.run_until(set.join_all());
+        assert_eq!(*ran.borrow(), [0, 1, 2]);
+        assert!(set.is_empty());
+
+        // The set stays open after joining.
+        set.spawn(async {}).unwrap();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_abort_all() {
+        let (mut ex, spawner) = local_pool();
+        let set = TaskSet::new(spawner);
+
+        let (task, alive) = pending_task();
+        set.spawn(task).unwrap();
+        ex.run_until_stalled();
+        assert_eq!(Rc::strong_count(&alive), 2);
+
+        set.abort_all();
+        ex.run_until(set.join_all());
+        assert_eq!(Rc::strong_count(&alive), 1);
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        set.spawn(async move { ran2.set(true) }).unwrap();
+        ex.run_until(set.join_all());
+        assert!(ran.get());
+    }
+}
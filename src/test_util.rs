@@ -0,0 +1,453 @@
+//! Spawners for unit tests of code that takes a [`LocalSpawner`], which don't need a real
+//! executor, and [`ChaosSpawner`] for injecting faults into a real one.
+//!
+//! ```
+//! use ispawn::test_util::RecordingSpawner;
+//!
+//! let recorder = RecordingSpawner::new();
+//! recorder.spawner().spawn(async {}).unwrap();
+//! assert_eq!(recorder.spawned().len(), 1);
+//! assert_eq!(recorder.run_until_stalled(), 1);
+//! ```
+
+use crate::{
+    Extensions, IntoLocalSpawner, LocalSpawner, Result, SpawnCompleter, SpawnError, SpawnSlot,
+    TaskMeta, ZstLocalSpawner,
+};
+use alloc::{boxed::Box, collections::VecDeque, rc::Rc, vec::Vec};
+use core::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+/// Accepts every task and drops its future without polling it.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoopSpawner;
+
+impl NoopSpawner {
+    pub const fn spawner() -> LocalSpawner {
+        LocalSpawner::from_zst::<NoopSpawner>()
+    }
+}
+
+impl IntoLocalSpawner for NoopSpawner {
+    const EXTENSIONS: Extensions = Extensions::NONE.with_boxed_tasks();
+
+    unsafe fn into_handle(self) -> *const () {
+        core::ptr::null()
+    }
+
+    unsafe fn spawn_dyn(
+        _handle: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        unsafe { crate::__private::box_spawn_dyn(slot, future_layout) }
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::__private::box_cancel_spawn(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        _handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        _meta: TaskMeta,
+    ) -> Result<()> {
+        drop(unsafe { crate::__private::box_future(task_ptr_as_dyn_future) });
+        Ok(())
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(_handle: *const ()) -> Option<Self> {
+        Some(Self)
+    }
+}
+
+// Safety: `NoopSpawner` never touches its handle.
+unsafe impl ZstLocalSpawner for NoopSpawner {}
+
+/// Panics on every spawn, naming the task's future type and spawn location, for code paths that
+/// must not spawn.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PanicSpawner;
+
+impl PanicSpawner {
+    pub const fn spawner() -> LocalSpawner {
+        LocalSpawner::from_zst::<PanicSpawner>()
+    }
+}
+
+impl IntoLocalSpawner for PanicSpawner {
+    const EXTENSIONS: Extensions = Extensions::NONE.with_boxed_tasks();
+
+    unsafe fn into_handle(self) -> *const () {
+        core::ptr::null()
+    }
+
+    unsafe fn spawn_dyn(
+        _handle: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        unsafe { crate::__private::box_spawn_dyn(slot, future_layout) }
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::__private::box_cancel_spawn(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        _handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        drop(unsafe { crate::__private::box_future(task_ptr_as_dyn_future) });
+        panic!(
+            "unexpected spawn of `{}` at {}",
+            meta.type_name(),
+            meta.location()
+        );
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(_handle: *const ()) -> Option<Self> {
+        Some(Self)
+    }
+}
+
+// Safety: `PanicSpawner` never touches its handle.
+unsafe impl ZstLocalSpawner for PanicSpawner {}
+
+/// Queues spawned tasks for the test to poll by hand, and records the metadata of every spawn.
+///
+/// Tasks are polled with a no-op waker, so stepping through them is entirely up to the test.
+#[derive(Default)]
+pub struct RecordingSpawner {
+    shared: Rc<Shared>,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: RefCell<VecDeque<Pin<Box<dyn Future<Output = ()>>>>>,
+    spawned: RefCell<Vec<TaskMeta>>,
+}
+
+impl RecordingSpawner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A `LocalSpawner` queueing its tasks onto this `RecordingSpawner`.
+    pub fn spawner(&self) -> LocalSpawner {
+        LocalSpawner::new(Handle(self.shared.clone()))
+    }
+
+    /// The metadata of every task spawned so far, in spawn order.
+    pub fn spawned(&self) -> Vec<TaskMeta> {
+        self.shared.spawned.borrow().clone()
+    }
+
+    /// The number of queued tasks that haven't completed yet.
+    pub fn pending(&self) -> usize {
+        self.shared.queue.borrow().len()
+    }
+
+    /// Poll the oldest queued task once, moving it to the back of the queue if it's still pending.
+    /// Returns `None` if there are no queued tasks.
+    pub fn step(&self) -> Option<Poll<()>> {
+        let mut task = self.shared.queue.borrow_mut().pop_front()?;
+        let poll = task.as_mut().poll(&mut Context::from_waker(Waker::noop()));
+        if poll.is_pending() {
+            self.shared.queue.borrow_mut().push_back(task);
+        }
+        Some(poll)
+    }
+
+    /// Poll the queued tasks, including ones they spawn, until a full round over the queue neither
+    /// completes nor spawns a task. Returns the number of tasks completed.
+    pub fn run_until_stalled(&self) -> usize {
+        let mut completed = 0;
+        loop {
+            let spawned = self.shared.spawned.borrow().len();
+            let mut progress = false;
+            for _ in 0..self.pending() {
+                if self.step() == Some(Poll::Ready(())) {
+                    completed += 1;
+                    progress = true;
+                }
+            }
+            if !progress && self.shared.spawned.borrow().len() == spawned {
+                return completed;
+            }
+        }
+    }
+}
+
+struct Handle(Rc<Shared>);
+
+impl IntoLocalSpawner for Handle {
+    const EXTENSIONS: Extensions = Extensions::NONE.with_boxed_tasks();
+
+    unsafe fn into_handle(self) -> *const () {
+        Rc::into_raw(self.0) as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        _handle: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        unsafe { crate::__private::box_spawn_dyn(slot, future_layout) }
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::__private::box_cancel_spawn(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        let future = unsafe { crate::__private::box_future(task_ptr_as_dyn_future) };
+        let shared = unsafe { &*(handle as *const Shared) };
+        shared.spawned.borrow_mut().push(meta);
+        shared.queue.borrow_mut().push_back(future);
+        Ok(())
+    }
+
+    unsafe fn on_clone(handle: *const ()) {
+        unsafe { Rc::increment_strong_count(handle as *const Shared) }
+    }
+
+    unsafe fn on_drop(handle: *const ()) {
+        unsafe { drop(Rc::from_raw(handle as *const Shared)) }
+    }
+}
+
+/// Wraps a spawner to inject faults, for exercising the error handling of code that spawns.
+///
+/// Spawns can be made to fail every so often, accepted tasks can be dropped at random without
+/// running, and the start of tasks can be delayed by a random number of yields. Randomness comes
+/// from a generator seeded on creation, so a failing run can be reproduced with the same seed.
+/// Clones share their spawn count and generator.
+#[derive(Clone)]
+pub struct ChaosSpawner {
+    inner: LocalSpawner,
+    fail_every: u64,
+    error: fn() -> SpawnError,
+    drop_one_in: u64,
+    max_delay: u32,
+    state: Rc<ChaosState>,
+}
+
+struct ChaosState {
+    spawns: Cell<u64>,
+    rng: Cell<u64>,
+}
+
+impl ChaosSpawner {
+    /// Wrap `inner`, seeding the random generator with `seed`. Injects no faults until configured
+    /// to.
+    pub fn new(inner: LocalSpawner, seed: u64) -> Self {
+        Self {
+            inner,
+            fail_every: 0,
+            error: || SpawnError::Other,
+            drop_one_in: 0,
+            max_delay: 0,
+            state: Rc::new(ChaosState {
+                spawns: Cell::new(0),
+                rng: Cell::new(seed),
+            }),
+        }
+    }
+
+    /// Fail every `n`th spawn, with the error set by [`fail_with`](Self::fail_with). `0` disables
+    /// it.
+    pub fn fail_every(mut self, n: u64) -> Self {
+        self.fail_every = n;
+        self
+    }
+
+    /// The error failed spawns return, `SpawnError::Other` by default.
+    pub fn fail_with(mut self, error: fn() -> SpawnError) -> Self {
+        self.error = error;
+        self
+    }
+
+    /// Accept but drop about one in `n` tasks without ever polling them. `0` disables it.
+    pub fn drop_one_in(mut self, n: u64) -> Self {
+        self.drop_one_in = n;
+        self
+    }
+
+    /// Have each task yield up to `max_yields` times, chosen at random, before it is first polled.
+    pub fn delay_start(mut self, max_yields: u32) -> Self {
+        self.max_delay = max_yields;
+        self
+    }
+
+    /// Spawn a `Future`, subject to the configured faults.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.spawn_chaos(f, TaskMeta::new::<F>())
+    }
+
+    fn spawn_chaos<F: Future<Output = ()> + 'static>(&self, f: F, meta: TaskMeta) -> Result<()> {
+        let spawns = self.state.spawns.get() + 1;
+        self.state.spawns.set(spawns);
+        if self.fail_every != 0 && spawns.is_multiple_of(self.fail_every) {
+            return Err((self.error)());
+        }
+        if self.drop_one_in != 0 && self.next_random().is_multiple_of(self.drop_one_in) {
+            drop(f);
+            return Ok(());
+        }
+        let yields = match self.max_delay {
+            0 => 0,
+            max => (self.next_random() % (max as u64 + 1)) as u32,
+        };
+        self.inner
+            .spawn_with_meta(DelayStart { yields, future: f }, meta)
+    }
+
+    /// xorshift64*, which is plenty for picking faults.
+    fn next_random(&self) -> u64 {
+        // A zero state would stay zero forever.
+        let mut x = self.state.rng.get().max(1);
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state.rng.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+crate::impl_local_spawner!(ChaosSpawner, |this, future| {
+    this.spawn_chaos(future, TaskMeta::new::<Pin<Box<dyn Future<Output = ()>>>>())
+});
+
+struct DelayStart<F> {
+    yields: u32,
+    future: F,
+}
+
+impl<F: Future<Output = ()>> Future for DelayStart<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: `future` is structurally pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.yields > 0 {
+            this.yields -= 1;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_noop_spawner_drops_tasks() {
+        let dropped = Rc::new(Cell::new(false));
+        struct SetOnDrop(Rc<Cell<bool>>);
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let guard = SetOnDrop(dropped.clone());
+        NoopSpawner::spawner()
+            .spawn(async move {
+                let _guard = guard;
+                unreachable!();
+            })
+            .unwrap();
+        assert!(dropped.get());
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected spawn")]
+    fn test_panic_spawner_panics() {
+        let _ = PanicSpawner::spawner().spawn(async {});
+    }
+
+    #[test]
+    fn test_recording_spawner_steps_tasks() {
+        let recorder = RecordingSpawner::new();
+        let spawner = recorder.spawner();
+        let ran = Rc::new(Cell::new(0));
+
+        let ran2 = ran.clone();
+        let inner = spawner.clone();
+        spawner
+            .spawn(async move {
+                ran2.set(ran2.get() + 1);
+                let ran3 = ran2.clone();
+                inner
+                    .spawn(async move { ran3.set(ran3.get() + 1) })
+                    .unwrap();
+            })
+            .unwrap();
+
+        assert_eq!(recorder.spawned().len(), 1);
+        assert_eq!(ran.get(), 0);
+        assert_eq!(recorder.step(), Some(Poll::Ready(())));
+        assert_eq!(recorder.spawned().len(), 2);
+        assert_eq!(recorder.pending(), 1);
+        assert_eq!(recorder.run_until_stalled(), 1);
+        assert_eq!(ran.get(), 2);
+        assert_eq!(recorder.step(), None);
+    }
+
+    #[test]
+    fn test_chaos_spawner_fails_every_nth_spawn() {
+        let chaos = ChaosSpawner::new(NoopSpawner::spawner(), 0)
+            .fail_every(3)
+            .fail_with(|| SpawnError::QueueFull);
+        let results: Vec<_> = (0..6).map(|_| chaos.spawn(async {})).collect();
+        assert!(results[0].is_ok() && results[1].is_ok() && results[3].is_ok());
+        assert!(matches!(results[2], Err(SpawnError::QueueFull)));
+        assert!(matches!(results[5], Err(SpawnError::QueueFull)));
+    }
+
+    #[test]
+    fn test_chaos_spawner_is_reproducible() {
+        fn run(seed: u64) -> (Vec<TaskMeta>, usize) {
+            let recorder = RecordingSpawner::new();
+            let chaos = ChaosSpawner::new(recorder.spawner(), seed)
+                .drop_one_in(2)
+                .delay_start(3);
+            for _ in 0..32 {
+                chaos.spawn(async {}).unwrap();
+            }
+            let mut steps = 0;
+            while recorder.step().is_some() {
+                steps += 1;
+            }
+            (recorder.spawned(), steps)
+        }
+
+        let (spawned, steps) = run(42);
+        assert!(!spawned.is_empty() && spawned.len() < 32);
+        assert!(steps > spawned.len());
+        assert_eq!(run(42).1, steps);
+        assert_eq!(run(42).0.len(), spawned.len());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{LocalSpawner, Result, TaskMeta};
+use core::{future::Future, mem::ManuallyDrop};
+use std::thread::{self, ThreadId};
+
+/// A `LocalSpawner` that can be sent to other threads, but only used on the thread that created
+/// it.
+///
+/// This lets a spawner be stored in `Send` state, e.g. a struct shared with a worker thread that
+/// hands it back later. Spawning through or cloning the spawner on any other thread panics.
+/// Dropping it on another thread panics as well, unless that thread is already panicking, in which
+/// case the spawner is leaked.
+pub struct ThreadBoundSpawner {
+    inner: ManuallyDrop<LocalSpawner>,
+    thread: ThreadId,
+}
+
+// Safety: the inner `LocalSpawner` is only ever touched on `thread`.
+unsafe impl Send for ThreadBoundSpawner {}
+
+impl ThreadBoundSpawner {
+    /// Bind `inner` to the current thread.
+    pub fn new(inner: LocalSpawner) -> Self {
+        Self {
+            inner: ManuallyDrop::new(inner),
+            thread: thread::current().id(),
+        }
+    }
+
+    /// Whether this is the thread the spawner is bound to.
+    pub fn is_owning_thread(&self) -> bool {
+        thread::current().id() == self.thread
+    }
+
+    /// The bound `LocalSpawner`, or `None` when called from any other thread.
+    pub fn get(&self) -> Option<&LocalSpawner> {
+        self.is_owning_thread().then_some(&*self.inner)
+    }
+
+    /// Spawn a `Future`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a thread other than the one the spawner is bound to.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        self.expect_owning_thread()
+            .spawn_with_meta(f, TaskMeta::new::<F>())
+    }
+
+    #[track_caller]
+    fn expect_owning_thread(&self) -> &LocalSpawner {
+        match self.get() {
+            Some(inner) => inner,
+            None => panic!("`ThreadBoundSpawner` used off the thread it is bound to"),
+        }
+    }
+}
+
+impl Clone for ThreadBoundSpawner {
+    fn clone(&self) -> Self {
+        Self {
+            inner: ManuallyDrop::new(self.expect_owning_thread().clone()),
+            thread: self.thread,
+        }
+    }
+}
+
+impl Drop for ThreadBoundSpawner {
+    fn drop(&mut self) {
+        if self.is_owning_thread() {
+            // Safety: `inner` is never used again.
+            unsafe { ManuallyDrop::drop(&mut self.inner) };
+        } else if !thread::panicking() {
+            panic!("`ThreadBoundSpawner` dropped off the thread it is bound to");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_only_usable_on_owning_thread() {
+        let mut ex = futures_executor::LocalPool::new();
+        let spawner = ThreadBoundSpawner::new(LocalSpawner::new(Rc::new(ex.spawner())));
+
+        let spawner = thread::spawn(move || {
+            assert!(spawner.get().is_none());
+            let result = std::panic::catch_unwind(|| spawner.spawn(async {}));
+            assert!(result.is_err());
+            spawner
+        })
+        .join()
+        .unwrap();
+
+        let ran = Rc::new(Cell::new(false));
+        let ran2 = ran.clone();
+        spawner
+            .clone()
+            .spawn(async move { ran2.set(true) })
+            .unwrap();
+        ex.run_until_stalled();
+        assert!(ran.get());
+    }
+}
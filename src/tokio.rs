@@ -1,49 +1,205 @@
-use crate::{IntoLocalSpawner, Result, SpawnCompleter, SpawnCompleterBuilder};
-use alloc::{alloc::Layout, boxed::Box, rc::Rc};
+use crate::{
+    Extensions, IntoSpawner, Result, SendExtensions, SendSpawnCompleter, SendSpawnSlot, SpawnError,
+    TaskMeta,
+};
+use alloc::{
+    alloc::Layout,
+    boxed::Box,
+    rc::{Rc, Weak},
+    sync::Arc,
+};
 use core::future::Future;
 
-impl IntoLocalSpawner for Rc<tokio::task::LocalSet> {
+// The spawner shares ownership of the `LocalSet`, so spawning always succeeds, even once nothing
+// will run the `LocalSet` again. `TokioLocalSetSpawner` reports that instead.
+crate::impl_local_spawner!(
+    Rc<tokio::task::LocalSet>,
+    |this, future| {
+        drop(this.spawn_local(future));
+        Ok(())
+    },
+    extensions = Extensions::NONE
+        .with_spawn_inline(|handle, future, _| {
+            let this = unsafe { &*(handle as *const tokio::task::LocalSet) };
+            drop(this.spawn_local(future));
+            Ok(())
+        })
+        .with_sleep(sleep)
+        .with_spawn_blocking(spawn_blocking)
+        .with_spawn_abortable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const tokio::task::LocalSet) };
+            Ok(abort_task(this.spawn_local(future).abort_handle()))
+        })
+        .with_spawn_joinable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const tokio::task::LocalSet) };
+            Ok(join_task(this.spawn_local(future)))
+        }),
+);
+
+/// Spawns onto a `LocalSet` without keeping it alive, failing with [`SpawnError::Shutdown`] once
+/// its owner dropped it, e.g. after running it to completion.
+///
+/// Tokio doesn't expose whether the runtime driving a `LocalSet` has shut down, so a `LocalSet`
+/// that is kept alive but never run again can't be detected.
+#[derive(Clone, Debug)]
+pub struct TokioLocalSetSpawner {
+    local_set: Weak<tokio::task::LocalSet>,
+}
+
+impl TokioLocalSetSpawner {
+    pub fn new(local_set: &Rc<tokio::task::LocalSet>) -> Self {
+        Self {
+            local_set: Rc::downgrade(local_set),
+        }
+    }
+}
+
+crate::impl_local_spawner!(
+    TokioLocalSetSpawner,
+    |this, future| {
+        let local_set = this.local_set.upgrade().ok_or(SpawnError::Shutdown)?;
+        drop(local_set.spawn_local(future));
+        Ok(())
+    },
+    extensions = Extensions::NONE
+        .with_spawn_inline(|handle, future, _| {
+            let this = unsafe { &*(handle as *const TokioLocalSetSpawner) };
+            let local_set = this.local_set.upgrade().ok_or(SpawnError::Shutdown)?;
+            drop(local_set.spawn_local(future));
+            Ok(())
+        })
+        .with_sleep(sleep)
+        .with_spawn_blocking(spawn_blocking)
+        .with_spawn_abortable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const TokioLocalSetSpawner) };
+            let local_set = this.local_set.upgrade().ok_or(SpawnError::Shutdown)?;
+            Ok(abort_task(local_set.spawn_local(future).abort_handle()))
+        })
+        .with_spawn_joinable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const TokioLocalSetSpawner) };
+            let local_set = this.local_set.upgrade().ok_or(SpawnError::Shutdown)?;
+            Ok(join_task(local_set.spawn_local(future)))
+        }),
+);
+
+#[cfg(all(feature = "tokio-local-runtime", not(tokio_unstable)))]
+compile_error!(
+    "the `tokio-local-runtime` feature requires building with `RUSTFLAGS=\"--cfg tokio_unstable\"`"
+);
+
+// Tokio's `LocalRuntime` spawns `!Send` tasks directly, without going through a `LocalSet`.
+#[cfg(all(feature = "tokio-local-runtime", tokio_unstable))]
+crate::impl_local_spawner!(
+    Rc<tokio::runtime::LocalRuntime>,
+    |this, future| {
+        drop(this.spawn_local(future));
+        Ok(())
+    },
+    extensions = Extensions::NONE
+        .with_spawn_inline(|handle, future, _| {
+            let this = unsafe { &*(handle as *const tokio::runtime::LocalRuntime) };
+            drop(this.spawn_local(future));
+            Ok(())
+        })
+        .with_sleep(sleep)
+        .with_spawn_blocking(spawn_blocking)
+        .with_spawn_abortable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const tokio::runtime::LocalRuntime) };
+            Ok(abort_task(this.spawn_local(future).abort_handle()))
+        })
+        .with_spawn_joinable(|handle, future, _| {
+            let this = unsafe { &*(handle as *const tokio::runtime::LocalRuntime) };
+            Ok(join_task(this.spawn_local(future)))
+        })
+        // Panics when called from within a runtime, like `LocalRuntime::block_on` itself.
+        .with_block_on(|handle, future| {
+            let this = unsafe { &*(handle as *const tokio::runtime::LocalRuntime) };
+            this.block_on(future);
+            Ok(())
+        }),
+);
+
+/// Aborts a task through tokio's own `AbortHandle`.
+fn abort_task(handle: tokio::task::AbortHandle) -> crate::AbortTask {
+    Rc::new(move || handle.abort())
+}
+
+/// Hands a task's output back through tokio's own `JoinHandle`, which detaches the task when
+/// dropped. Tasks that panicked or were aborted are reported as cancelled.
+fn join_task(handle: tokio::task::JoinHandle<crate::TaskOutput>) -> crate::JoinTask {
+    Box::pin(async move { handle.await.map_err(|_| crate::JoinError::Cancelled) })
+}
+
+/// Sleeps on the timer of the runtime the task runs on, which must have time enabled.
+unsafe fn sleep(_: *const (), duration: core::time::Duration) -> crate::Sleep {
+    // Created lazily, as `tokio::time::sleep` needs to be called within the runtime.
+    Box::pin(async move { tokio::time::sleep(duration).await })
+}
+
+/// Runs the job on the blocking pool of the runtime the caller runs on, failing outside of one.
+unsafe fn spawn_blocking(_: *const (), job: crate::BlockingJob) -> Result<()> {
+    let runtime =
+        tokio::runtime::Handle::try_current().map_err(|e| SpawnError::Executor(Box::new(e)))?;
+    drop(runtime.spawn_blocking(job));
+    Ok(())
+}
+
+/// Spawns onto any tokio runtime, including multi-threaded ones.
+impl IntoSpawner for tokio::runtime::Handle {
+    const EXTENSIONS: SendExtensions = SendExtensions::NONE
+        .with_name("tokio::runtime::Handle")
+        .with_spawn_blocking(|handle, job| {
+            let this = unsafe { &*(handle as *const tokio::runtime::Handle) };
+            drop(this.spawn_blocking(job));
+            Ok(())
+        });
+
     unsafe fn into_handle(self) -> *const () {
-        Rc::into_raw(self) as *const ()
+        Arc::into_raw(Arc::new(self)) as *const ()
     }
 
     unsafe fn spawn_dyn(
         _: *const (),
-        builder: SpawnCompleterBuilder,
+        slot: SendSpawnSlot,
         future_layout: Layout,
-    ) -> SpawnCompleter {
-        let future_ptr = unsafe { alloc::alloc::alloc(future_layout) } as *mut ();
+    ) -> Result<SendSpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
         let task_ptr = future_ptr;
-        builder.build(task_ptr, future_ptr)
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
     }
 
     unsafe fn finish_spawn(
         handle: *const (),
-        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        task_ptr_as_dyn_future: *mut (dyn Future<Output = ()> + Send),
+        _meta: TaskMeta,
     ) -> Result<()> {
-        let future_box: Box<dyn Future<Output = ()>> =
+        let future_box: Box<dyn Future<Output = ()> + Send> =
             unsafe { Box::from_raw(task_ptr_as_dyn_future) };
 
-        let this = unsafe { &*(handle as *const tokio::task::LocalSet) };
-        let _ = this.spawn_local(Box::into_pin(future_box));
+        let this = unsafe { &*(handle as *const tokio::runtime::Handle) };
+        drop(this.spawn(Box::into_pin(future_box)));
 
         Ok(())
     }
 
     unsafe fn on_clone(handle: *const ()) {
-        unsafe { Rc::increment_strong_count(handle as *const tokio::task::LocalSet) }
+        unsafe { Arc::increment_strong_count(handle as *const tokio::runtime::Handle) }
     }
 
     unsafe fn on_drop(handle: *const ()) {
         unsafe {
-            let _ = Rc::from_raw(handle as *const tokio::task::LocalSet);
+            let _ = Arc::from_raw(handle as *const tokio::runtime::Handle);
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::*;
+    use alloc::rc::Rc;
 
     #[test]
     fn test_tokio_executor() {
@@ -65,6 +221,190 @@ mod test {
         assert_eq!(result.unwrap(), 42);
     }
 
+    #[test]
+    fn test_from_current_context() {
+        assert!(matches!(
+            crate::LocalSpawner::from_current_context(),
+            Err(crate::SpawnError::Unsupported)
+        ));
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        // Tokio can't tell whether `spawn_local` would panic, so it's never detected.
+        let ex = tokio::task::LocalSet::new();
+        ex.block_on(&rt, async {
+            assert!(matches!(
+                crate::LocalSpawner::from_current_context(),
+                Err(crate::SpawnError::Unsupported)
+            ));
+        });
+        rt.block_on(async {
+            assert!(matches!(
+                crate::LocalSpawner::from_current_context(),
+                Err(crate::SpawnError::Unsupported)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_native_abort() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ex = Rc::new(tokio::task::LocalSet::new());
+        let spawner = crate::LocalSpawner::new(ex.clone());
+        assert!(
+            spawner
+                .capabilities()
+                .contains(crate::Capabilities::SUPPORTS_ABORT)
+        );
+
+        let held = Rc::new(());
+        let held2 = held.clone();
+        let handle = spawner
+            .spawn_abortable(async move {
+                let _held = held2;
+                core::future::pending::<()>().await;
+            })
+            .unwrap();
+        ex.block_on(&rt, tokio::task::yield_now());
+        assert_eq!(Rc::strong_count(&held), 2);
+
+        handle.abort();
+        assert!(handle.is_aborted());
+        ex.block_on(&rt, tokio::task::yield_now());
+        assert_eq!(Rc::strong_count(&held), 1);
+    }
+
+    #[test]
+    fn test_native_join() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ex = Rc::new(tokio::task::LocalSet::new());
+        let spawner = crate::LocalSpawner::new(ex.clone());
+        assert!(
+            spawner
+                .capabilities()
+                .contains(crate::Capabilities::SUPPORTS_JOIN)
+        );
+
+        let ran = Rc::new(core::cell::Cell::new(false));
+        let ran2 = ran.clone();
+        drop(
+            spawner
+                .spawn_with_handle(async move { ran2.set(true) })
+                .unwrap(),
+        );
+        let handle = spawner.spawn_with_handle(async { 42 }).unwrap();
+        assert_eq!(ex.block_on(&rt, handle), Ok(42));
+        assert!(ran.get());
+
+        let handle = spawner
+            .spawn_with_handle(core::future::pending::<()>())
+            .unwrap();
+        drop((spawner, ex));
+        assert!(handle.is_finished());
+        assert_eq!(rt.block_on(handle), Err(crate::JoinError::Cancelled));
+    }
+
+    #[test]
+    fn test_dropped_abort_handle_detaches() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ex = Rc::new(tokio::task::LocalSet::new());
+        let spawner = crate::LocalSpawner::new(ex.clone());
+
+        let ran = Rc::new(core::cell::Cell::new(false));
+        let ran2 = ran.clone();
+        let handle = spawner
+            .spawn_abortable(async move { ran2.set(true) })
+            .unwrap();
+        drop(handle);
+        ex.block_on(&rt, tokio::task::yield_now());
+        assert!(ran.get());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_spawn_after() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let ex = Rc::new(tokio::task::LocalSet::new());
+        let spawner = crate::LocalSpawner::new(ex.clone());
+
+        let delay = core::time::Duration::from_millis(10);
+        let started = std::time::Instant::now();
+        let (result_tx, mut result_rx) = localq::mpsc::channel(1);
+        spawner
+            .spawn_after(delay, async move {
+                result_tx.try_send(started.elapsed()).unwrap();
+            })
+            .unwrap();
+
+        let elapsed = ex.block_on(&rt, async move { result_rx.recv().await });
+        assert!(elapsed.unwrap() >= delay);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_spawn_blocking() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .thread_name("blocking-pool")
+            .build()
+            .unwrap();
+        let ex = Rc::new(tokio::task::LocalSet::new());
+        let spawner = crate::LocalSpawner::new(ex.clone());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx2 = tx.clone();
+        assert!(spawner.spawn_blocking(|| {}).is_err());
+        ex.block_on(&rt, async {
+            spawner
+                .spawn_blocking(move || {
+                    tx.send(std::thread::current().name().map(Into::into))
+                        .unwrap();
+                })
+                .unwrap();
+        });
+        crate::Spawner::new(rt.handle().clone())
+            .spawn_blocking(move || {
+                tx2.send(std::thread::current().name().map(Into::into))
+                    .unwrap();
+            })
+            .unwrap();
+
+        for _ in 0..2 {
+            let name: Option<alloc::string::String> = rx.recv().unwrap();
+            assert_eq!(name.as_deref(), Some("blocking-pool"));
+        }
+    }
+
+    #[test]
+    fn test_downcast_local_set() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ex = Rc::new(tokio::task::LocalSet::new());
+        let spawner = crate::LocalSpawner::new(ex.clone());
+
+        let (result_tx, mut result_rx) = localq::mpsc::channel(1);
+        spawner
+            .spawn(async move {
+                result_tx.try_send(42).unwrap();
+            })
+            .unwrap();
+
+        let local_set = spawner.downcast_ref::<Rc<tokio::task::LocalSet>>().unwrap();
+        let result = rt.block_on(local_set.run_until(async move { result_rx.recv().await }));
+
+        assert_eq!(result.unwrap(), 42);
+    }
+
     #[test]
     fn test_tokio_executor_drop_before_spawner() {
         let rt = tokio::runtime::Builder::new_current_thread()
@@ -78,4 +418,78 @@ mod test {
 
         spawner.spawn(async move {}).unwrap();
     }
+
+    #[test]
+    fn test_local_set_spawner_shutdown() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ex = Rc::new(tokio::task::LocalSet::new());
+        let spawner = crate::LocalSpawner::new(super::TokioLocalSetSpawner::new(&ex));
+
+        let (result_tx, mut result_rx) = localq::mpsc::channel(1);
+        spawner
+            .spawn(async move {
+                result_tx.try_send(42).unwrap();
+            })
+            .unwrap();
+        let result = ex.block_on(&rt, async move { result_rx.recv().await });
+        assert_eq!(result.unwrap(), 42);
+
+        drop(ex);
+        assert!(matches!(
+            spawner.spawn(async {}),
+            Err(crate::SpawnError::Shutdown)
+        ));
+    }
+
+    #[cfg(all(feature = "tokio-local-runtime", tokio_unstable))]
+    #[test]
+    fn test_tokio_local_runtime() {
+        let rt = Rc::new(
+            tokio::runtime::Builder::new_current_thread()
+                .build_local(Default::default())
+                .unwrap(),
+        );
+        let spawner = crate::LocalSpawner::new(rt.clone());
+
+        let (result_tx, mut result_rx) = localq::mpsc::channel(1);
+        spawner
+            .spawn(async move {
+                result_tx.try_send(42).unwrap();
+            })
+            .unwrap();
+
+        let result = spawner
+            .block_on(async move { result_rx.recv().await })
+            .unwrap();
+
+        assert_eq!(result.unwrap(), 42);
+    }
+
+    #[test]
+    fn test_tokio_handle() {
+        use alloc::sync::Arc;
+        use core::sync::atomic::{AtomicBool, Ordering};
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let spawner = crate::Spawner::new(rt.handle().clone());
+        assert_eq!(spawner.name(), "tokio::runtime::Handle");
+        assert!(spawner.extensions().spawn_blocking.is_some());
+        assert!(spawner.capabilities().is_empty());
+
+        let ran = Arc::new(AtomicBool::new(false));
+        let ran2 = ran.clone();
+        spawner
+            .spawn(async move { ran2.store(true, Ordering::Relaxed) })
+            .unwrap();
+
+        rt.block_on(async {
+            while !ran.load(Ordering::Relaxed) {
+                tokio::task::yield_now().await;
+            }
+        });
+    }
 }
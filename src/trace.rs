@@ -0,0 +1,13 @@
+//! `tracing` instrumentation of spawned tasks.
+
+use crate::TaskMeta;
+
+/// The span every poll of a task spawned with `meta` is run in.
+pub(crate) fn task_span(meta: &TaskMeta) -> tracing::Span {
+    tracing::trace_span!(
+        "ispawn::task",
+        task.name = meta.options().name,
+        task.type_name = meta.type_name(),
+        task.location = %meta.location(),
+    )
+}
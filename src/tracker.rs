@@ -0,0 +1,138 @@
+//! Single-threaded bookkeeping of how many tasks in a group are still running.
+
+use alloc::{rc::Rc, vec::Vec};
+use core::{
+    cell::{Cell, RefCell},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+#[derive(Clone, Default)]
+pub(crate) struct TaskTracker {
+    inner: Rc<TrackerInner>,
+}
+
+#[derive(Default)]
+struct TrackerInner {
+    active: Cell<usize>,
+    idle_wakers: RefCell<Vec<Waker>>,
+    below_wakers: RefCell<Vec<Waker>>,
+}
+
+impl TaskTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of tasks whose `TrackGuard` is still alive.
+    pub(crate) fn active(&self) -> usize {
+        self.inner.active.get()
+    }
+
+    /// Register a task. The task counts as active until the returned guard is dropped, which
+    /// happens when the task's future completes or is dropped.
+    pub(crate) fn track(&self) -> TrackGuard {
+        self.inner.active.set(self.inner.active.get() + 1);
+        TrackGuard {
+            tracker: self.clone(),
+        }
+    }
+
+    /// Wrap `future` so that it counts as active until it completes or is dropped.
+    pub(crate) fn wrap<F: Future>(&self, future: F) -> Tracked<F> {
+        Tracked {
+            _guard: self.track(),
+            future,
+        }
+    }
+
+    /// Resolves once no tracked task is active.
+    pub(crate) fn idle(&self) -> Idle {
+        Idle {
+            tracker: self.clone(),
+        }
+    }
+
+    /// Resolves once fewer than `limit` tracked tasks are active.
+    pub(crate) fn below(&self, limit: usize) -> Below {
+        Below {
+            tracker: self.clone(),
+            limit,
+        }
+    }
+}
+
+pub(crate) struct TrackGuard {
+    tracker: TaskTracker,
+}
+
+impl Drop for TrackGuard {
+    fn drop(&mut self) {
+        let inner = &self.tracker.inner;
+        inner.active.set(inner.active.get() - 1);
+        let wakers = core::mem::take(&mut *inner.below_wakers.borrow_mut());
+        for waker in wakers {
+            waker.wake();
+        }
+        if inner.active.get() == 0 {
+            let wakers = core::mem::take(&mut *inner.idle_wakers.borrow_mut());
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+}
+
+pub(crate) struct Tracked<F> {
+    _guard: TrackGuard,
+    future: F,
+}
+
+impl<F: Future> Future for Tracked<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // Safety: `future` is structurally pinned; the guard is never pinned.
+        unsafe { self.map_unchecked_mut(|this| &mut this.future) }.poll(cx)
+    }
+}
+
+pub(crate) struct Idle {
+    tracker: TaskTracker,
+}
+
+impl Future for Idle {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.tracker.active() == 0 {
+            return Poll::Ready(());
+        }
+        let mut wakers = self.tracker.inner.idle_wakers.borrow_mut();
+        if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+pub(crate) struct Below {
+    tracker: TaskTracker,
+    limit: usize,
+}
+
+impl Future for Below {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.tracker.active() < self.limit {
+            return Poll::Ready(());
+        }
+        let mut wakers = self.tracker.inner.below_wakers.borrow_mut();
+        if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
@@ -1,32 +1,131 @@
-use crate::{IntoLocalSpawner, Result, SpawnCompleter, SpawnCompleterBuilder};
-use alloc::{alloc::Layout, boxed::Box};
-use core::future::Future;
+use crate::slots::{SlotFuture, SlotPool};
+use crate::{
+    Extensions, FallibleSpawner, IntoLocalSpawner, IntoSpawner, LocalSpawner, Result,
+    SendSpawnCompleter, SendSpawnSlot, SpawnCompleter, SpawnSlot, TaskMeta,
+};
+use alloc::{alloc::Layout, boxed::Box, format, rc::Rc};
+use core::{fmt::Debug, future::Future};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console, js_name = error)]
+    fn console_error(message: &str);
+}
 
 #[derive(Copy, Clone, Debug)]
 pub struct WasmBindgenSpawner;
 
+impl WasmBindgenSpawner {
+    /// A [`FallibleSpawner`] for tasks returning a `Result`, such as ones awaiting a `JsFuture`
+    /// whose promise may reject with a JS exception. Errors are logged with
+    /// [`log_error`](Self::log_error).
+    ///
+    /// To handle errors some other way, e.g. to forward them to an error tracker, pass a handler
+    /// to [`FallibleSpawner::new`] instead, which may call `log_error` itself.
+    pub fn fallible() -> FallibleSpawner {
+        FallibleSpawner::new(LocalSpawner::new(WasmBindgenSpawner), Self::log_error)
+    }
+
+    /// Log an error returned by a task via `console.error`, along with the task's future type and
+    /// spawn location. An [`ErrorHandler`](crate::ErrorHandler) for [`FallibleSpawner`].
+    pub fn log_error(meta: &TaskMeta, error: &dyn Debug) {
+        console_error(&format!(
+            "task `{}` spawned at {} failed: {error:?}",
+            meta.type_name(),
+            meta.location(),
+        ));
+    }
+}
+
+#[cfg(feature = "std")]
+impl WasmBindgenSpawner {
+    /// Report panics raised while polling tasks spawned through `WasmBindgenSpawner` on this
+    /// thread.
+    ///
+    /// With `panic = "abort"` (the wasm default) a panicking task otherwise surfaces as a generic
+    /// `unreachable` trap with no context. Once enabled, the panic hook logs the panicking task's
+    /// future type and spawn location via `console.error`, then calls `hook` (if any) and finally
+    /// the previously installed panic hook. Only tasks spawned after this call are reported.
+    pub fn report_panics(hook: Option<TaskPanicHook>) {
+        panic_report::enable(hook);
+    }
+}
+
+/// A hook receiving panics raised while polling a task spawned by `WasmBindgenSpawner`.
+#[cfg(feature = "std")]
+pub type TaskPanicHook = fn(&TaskMeta, &std::panic::PanicHookInfo<'_>);
+
 impl IntoLocalSpawner for WasmBindgenSpawner {
+    const EXTENSIONS: Extensions = Extensions::NONE.with_boxed_tasks();
+
     unsafe fn into_handle(self) -> *const () {
         core::ptr::null()
     }
 
     unsafe fn spawn_dyn(
         _handle: *const (),
-        builder: SpawnCompleterBuilder,
+        slot: SpawnSlot,
         future_layout: Layout,
-    ) -> SpawnCompleter {
-        let future_ptr = unsafe { alloc::alloc::alloc(future_layout) } as *mut ();
+    ) -> Result<SpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
         let task_ptr = future_ptr;
-        builder.build(task_ptr, future_ptr)
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
     }
 
     unsafe fn finish_spawn(
         _handle: *const (),
         task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        meta: TaskMeta,
     ) -> Result<()> {
         let future_box: Box<dyn Future<Output = ()>> =
             unsafe { Box::from_raw(task_ptr_as_dyn_future) };
-        let _ = wasm_bindgen_futures::spawn_local(Box::into_pin(future_box));
+        spawn_local(Box::into_pin(future_box), meta);
+        Ok(())
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(_handle: *const ()) -> Option<Self> {
+        Some(Self)
+    }
+}
+
+/// Runs `Send` futures on the thread that spawns them. Without wasm threads there is only one
+/// thread to run them on anyway, so libraries written against `Spawner` work unchanged.
+impl IntoSpawner for WasmBindgenSpawner {
+    unsafe fn into_handle(self) -> *const () {
+        core::ptr::null()
+    }
+
+    unsafe fn spawn_dyn(
+        _handle: *const (),
+        slot: SendSpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SendSpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        _handle: *const (),
+        task_ptr_as_dyn_future: *mut (dyn Future<Output = ()> + Send),
+        meta: TaskMeta,
+    ) -> Result<()> {
+        let future_box: Box<dyn Future<Output = ()> + Send> =
+            unsafe { Box::from_raw(task_ptr_as_dyn_future) };
+        spawn_local(Box::into_pin(future_box), meta);
         Ok(())
     }
 
@@ -34,3 +133,305 @@ impl IntoLocalSpawner for WasmBindgenSpawner {
 
     unsafe fn on_drop(_handle: *const ()) {}
 }
+
+impl WasmBindgenSpawner {
+    /// Create a spawner that emplaces futures of up to `slot_layout` into one of `count`
+    /// pre-allocated task slots, so that frequent small spawns on the browser main thread don't
+    /// have to allocate memory for the future's state. Larger futures, and spawns while every slot
+    /// is in use, fall back to allocating.
+    ///
+    /// `wasm-bindgen-futures` still allocates a small task header for every spawn.
+    pub fn with_task_slots(count: usize, slot_layout: Layout) -> SlottedWasmBindgenSpawner {
+        SlottedWasmBindgenSpawner {
+            pool: Rc::new(SlotPool::new(count, slot_layout)),
+        }
+    }
+}
+
+/// A [`WasmBindgenSpawner`] backed by pre-allocated task slots, created with
+/// [`WasmBindgenSpawner::with_task_slots`].
+#[derive(Clone)]
+pub struct SlottedWasmBindgenSpawner {
+    pool: Rc<SlotPool>,
+}
+
+impl IntoLocalSpawner for SlottedWasmBindgenSpawner {
+    unsafe fn into_handle(self) -> *const () {
+        Rc::into_raw(self.pool) as *const ()
+    }
+
+    unsafe fn spawn_dyn(
+        handle: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        let pool = unsafe { &*(handle as *const SlotPool) };
+        let future_ptr = match pool.take(future_layout) {
+            Some(slot) => slot,
+            None => crate::boxed::alloc_future(future_layout)?,
+        };
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        let pool = unsafe { &*(handle as *const SlotPool) };
+        if pool.owns(task_ptr) {
+            pool.release(task_ptr);
+        } else {
+            unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+        }
+    }
+
+    unsafe fn finish_spawn(
+        handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        let pool = unsafe { &*(handle as *const SlotPool) };
+        if pool.owns(task_ptr_as_dyn_future as *mut ()) {
+            let pool = unsafe {
+                Rc::increment_strong_count(handle as *const SlotPool);
+                Rc::from_raw(handle as *const SlotPool)
+            };
+            spawn_local(
+                unsafe { SlotFuture::new(pool, task_ptr_as_dyn_future) },
+                meta,
+            );
+        } else {
+            let future_box: Box<dyn Future<Output = ()>> =
+                unsafe { Box::from_raw(task_ptr_as_dyn_future) };
+            spawn_local(Box::into_pin(future_box), meta);
+        }
+        Ok(())
+    }
+
+    unsafe fn on_clone(handle: *const ()) {
+        unsafe { Rc::increment_strong_count(handle as *const SlotPool) }
+    }
+
+    unsafe fn on_drop(handle: *const ()) {
+        unsafe {
+            let _ = Rc::from_raw(handle as *const SlotPool);
+        }
+    }
+
+    unsafe fn from_handle(handle: *const ()) -> Option<Self> {
+        Some(Self {
+            pool: unsafe { Rc::from_raw(handle as *const SlotPool) },
+        })
+    }
+}
+
+impl WasmBindgenSpawner {
+    /// Create a spawner that schedules its tasks as macrotasks rather than microtasks.
+    ///
+    /// `wasm_bindgen_futures::spawn_local` polls tasks from the microtask queue, so a busy task
+    /// that keeps waking itself runs before the browser gets to render or handle input. Tasks
+    /// spawned through a macrotask spawner are started, and re-polled after every wake, from a
+    /// `setTimeout(0)` callback instead, yielding to the event loop in between at the cost of
+    /// some latency.
+    pub const fn macrotask() -> MacrotaskWasmBindgenSpawner {
+        MacrotaskWasmBindgenSpawner
+    }
+}
+
+/// A [`WasmBindgenSpawner`] scheduling tasks as macrotasks, created with
+/// [`WasmBindgenSpawner::macrotask`].
+#[derive(Copy, Clone, Debug)]
+pub struct MacrotaskWasmBindgenSpawner;
+
+impl IntoLocalSpawner for MacrotaskWasmBindgenSpawner {
+    const EXTENSIONS: Extensions = Extensions::NONE.with_boxed_tasks();
+
+    unsafe fn into_handle(self) -> *const () {
+        core::ptr::null()
+    }
+
+    unsafe fn spawn_dyn(
+        _handle: *const (),
+        slot: SpawnSlot,
+        future_layout: Layout,
+    ) -> Result<SpawnCompleter> {
+        let future_ptr = crate::boxed::alloc_future(future_layout)?;
+        let task_ptr = future_ptr;
+        Ok(slot.build(task_ptr, future_ptr))
+    }
+
+    unsafe fn cancel_spawn(_handle: *const (), task_ptr: *mut (), future_layout: Layout) {
+        unsafe { crate::boxed::dealloc_future(task_ptr, future_layout) }
+    }
+
+    unsafe fn finish_spawn(
+        _handle: *const (),
+        task_ptr_as_dyn_future: *mut dyn Future<Output = ()>,
+        meta: TaskMeta,
+    ) -> Result<()> {
+        let future_box: Box<dyn Future<Output = ()>> =
+            unsafe { Box::from_raw(task_ptr_as_dyn_future) };
+        let future = macrotask::Macrotask::new(Box::into_pin(future_box));
+        macrotask::set_timeout_0(move || spawn_local(future, meta));
+        Ok(())
+    }
+
+    unsafe fn on_clone(_handle: *const ()) {}
+
+    unsafe fn on_drop(_handle: *const ()) {}
+
+    unsafe fn from_handle(_handle: *const ()) -> Option<Self> {
+        Some(Self)
+    }
+}
+
+mod macrotask {
+    use alloc::{sync::Arc, task::Wake};
+    use core::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll, Waker},
+    };
+    use wasm_bindgen::{JsValue, closure::Closure, prelude::wasm_bindgen};
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_name = setTimeout)]
+        fn set_timeout(handler: &JsValue, timeout: i32) -> JsValue;
+    }
+
+    /// Run `f` from a `setTimeout(0)` callback.
+    pub(super) fn set_timeout_0(f: impl FnOnce() + 'static) {
+        set_timeout(&Closure::once_into_js(f), 0);
+    }
+
+    /// Defers every wake of `future` to a macrotask.
+    pub(super) struct Macrotask<F> {
+        future: F,
+        /// The executor's waker, and the deferring waker handed to `future` in its place.
+        wakers: Option<(Waker, Waker)>,
+    }
+
+    impl<F> Macrotask<F> {
+        pub(super) fn new(future: F) -> Self {
+            Self {
+                future,
+                wakers: None,
+            }
+        }
+    }
+
+    impl<F: Future<Output = ()>> Future for Macrotask<F> {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            // Safety: `future` is structurally pinned; `wakers` is never pinned.
+            let this = unsafe { self.get_unchecked_mut() };
+            let waker = match &this.wakers {
+                Some((outer, deferred)) if outer.will_wake(cx.waker()) => deferred,
+                _ => {
+                    let outer = cx.waker().clone();
+                    let deferred = Waker::from(Arc::new(DeferredWake(outer.clone())));
+                    &this.wakers.insert((outer, deferred)).1
+                }
+            };
+            let future = unsafe { Pin::new_unchecked(&mut this.future) };
+            future.poll(&mut Context::from_waker(waker))
+        }
+    }
+
+    struct DeferredWake(Waker);
+
+    impl Wake for DeferredWake {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            let waker = self.0.clone();
+            set_timeout_0(move || waker.wake());
+        }
+    }
+}
+
+fn spawn_local<F: Future<Output = ()> + 'static>(future: F, _meta: TaskMeta) {
+    #[cfg(feature = "std")]
+    if panic_report::is_enabled() {
+        wasm_bindgen_futures::spawn_local(panic_report::ReportPanics {
+            meta: _meta,
+            future,
+        });
+        return;
+    }
+
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+#[cfg(feature = "std")]
+mod panic_report {
+    use super::{TaskPanicHook, console_error};
+    use crate::TaskMeta;
+    use alloc::{boxed::Box, format};
+    use core::{
+        cell::Cell,
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+    std::thread_local! {
+        static ENABLED: Cell<bool> = const { Cell::new(false) };
+        static HOOK: Cell<Option<TaskPanicHook>> = const { Cell::new(None) };
+        static CURRENT_TASK: Cell<Option<TaskMeta>> = const { Cell::new(None) };
+    }
+
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+
+    pub(super) fn enable(hook: Option<TaskPanicHook>) {
+        HOOK.with(|h| h.set(hook));
+        ENABLED.with(|e| e.set(true));
+
+        INSTALL.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                if let Some(meta) = CURRENT_TASK.with(|t| t.get()) {
+                    console_error(&format!(
+                        "panic in task `{}` spawned at {}: {}",
+                        meta.type_name(),
+                        meta.location(),
+                        info,
+                    ));
+                    if let Some(hook) = HOOK.with(|h| h.get()) {
+                        hook(&meta, info);
+                    }
+                }
+                previous(info);
+            }));
+        });
+    }
+
+    pub(super) fn is_enabled() -> bool {
+        ENABLED.with(|e| e.get())
+    }
+
+    /// Records `meta` as the current task for the duration of every poll of `future`.
+    pub(super) struct ReportPanics<F> {
+        pub(super) meta: TaskMeta,
+        pub(super) future: F,
+    }
+
+    impl<F: Future<Output = ()>> Future for ReportPanics<F> {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            struct Restore(Option<TaskMeta>);
+
+            impl Drop for Restore {
+                fn drop(&mut self) {
+                    CURRENT_TASK.with(|t| t.set(self.0));
+                }
+            }
+
+            let _restore = Restore(CURRENT_TASK.with(|t| t.replace(Some(self.meta))));
+            // Safety: `future` is structurally pinned.
+            unsafe { self.map_unchecked_mut(|this| &mut this.future) }.poll(cx)
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{
+    AllocHooks, LocalSpawner, LocalSpawnerVtable, Result, SpawnError, SpawnHooks, TaskMeta,
+};
+use core::future::Future;
+
+/// A [`LocalSpawner`] that doesn't keep the executor's handle alive, created with
+/// [`LocalSpawner::downgrade`].
+///
+/// Executors that don't support weak handles (see [`Extensions::weak`](crate::Extensions::weak))
+/// are held on to strongly instead, so that `upgrade` always succeeds for them.
+pub struct WeakLocalSpawner {
+    inner: Inner,
+}
+
+enum Inner {
+    Weak {
+        weak: *const (),
+        vtable: &'static LocalSpawnerVtable,
+        alloc_hooks: Option<&'static AllocHooks>,
+        spawn_hooks: Option<&'static SpawnHooks>,
+    },
+    Strong(LocalSpawner),
+}
+
+impl LocalSpawner {
+    /// Create a [`WeakLocalSpawner`] for this spawner's executor.
+    pub fn downgrade(&self) -> WeakLocalSpawner {
+        let inner = match &self.vtable.ext.weak {
+            Some(ops) => Inner::Weak {
+                weak: unsafe { (ops.downgrade)(self.handle) },
+                vtable: self.vtable,
+                alloc_hooks: self.alloc_hooks,
+                spawn_hooks: self.spawn_hooks,
+            },
+            None => Inner::Strong(self.clone()),
+        };
+        WeakLocalSpawner { inner }
+    }
+}
+
+impl WeakLocalSpawner {
+    /// Get a `LocalSpawner` back, or `None` if every `LocalSpawner` for the executor has been
+    /// dropped.
+    pub fn upgrade(&self) -> Option<LocalSpawner> {
+        match &self.inner {
+            Inner::Weak {
+                weak,
+                vtable,
+                alloc_hooks,
+                spawn_hooks,
+            } => {
+                let ops = vtable.ext.weak.as_ref()?;
+                let handle = unsafe { (ops.upgrade)(*weak) }?;
+                Some(LocalSpawner {
+                    handle,
+                    vtable,
+                    alloc_hooks: *alloc_hooks,
+                    spawn_hooks: *spawn_hooks,
+                })
+            }
+            Inner::Strong(spawner) => Some(spawner.clone()),
+        }
+    }
+
+    /// Spawn a `Future`, failing with `SpawnError::Shutdown` if the executor's handle is gone.
+    #[track_caller]
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, f: F) -> Result<()> {
+        match self.upgrade() {
+            Some(spawner) => spawner.spawn_with_meta(f, TaskMeta::new::<F>()),
+            None => Err(SpawnError::Shutdown),
+        }
+    }
+}
+
+impl Clone for WeakLocalSpawner {
+    fn clone(&self) -> Self {
+        let inner = match &self.inner {
+            Inner::Weak {
+                weak,
+                vtable,
+                alloc_hooks,
+                spawn_hooks,
+            } => {
+                if let Some(ops) = &vtable.ext.weak {
+                    unsafe { (ops.clone_weak)(*weak) }
+                }
+                Inner::Weak {
+                    weak: *weak,
+                    vtable,
+                    alloc_hooks: *alloc_hooks,
+                    spawn_hooks: *spawn_hooks,
+                }
+            }
+            Inner::Strong(spawner) => Inner::Strong(spawner.clone()),
+        };
+        Self { inner }
+    }
+}
+
+impl Drop for WeakLocalSpawner {
+    fn drop(&mut self) {
+        if let Inner::Weak { weak, vtable, .. } = &self.inner
+            && let Some(ops) = &vtable.ext.weak
+        {
+            unsafe { (ops.drop_weak)(*weak) }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "futures-executor"))]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+
+    #[test]
+    fn test_weak_does_not_keep_handle_alive() {
+        let ex = futures_executor::LocalPool::new();
+        let handle = Rc::new(ex.spawner());
+        let spawner = LocalSpawner::new(handle.clone());
+        let weak = spawner.downgrade();
+        let weak2 = weak.clone();
+        assert_eq!(Rc::strong_count(&handle), 2);
+
+        let upgraded = weak.upgrade().unwrap();
+        assert_eq!(Rc::strong_count(&handle), 3);
+        drop(upgraded);
+
+        weak.spawn(async {}).unwrap();
+        drop(spawner);
+        drop(handle);
+        assert!(weak.upgrade().is_none());
+        assert!(matches!(weak2.spawn(async {}), Err(SpawnError::Shutdown)));
+    }
+}
@@ -0,0 +1,103 @@
+//! Counts the allocations the reference executor makes per spawn. Kept out of the unit tests
+//! since it installs its own global allocator.
+
+use core::{
+    alloc::Layout,
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use ispawn::reference::ReferenceExecutor;
+use std::{
+    alloc::{GlobalAlloc, System},
+    rc::Rc,
+};
+
+struct CountingAllocator;
+
+std::thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|a| a.get())
+}
+
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[test]
+fn test_spawn_allocates_once() {
+    let ex = ReferenceExecutor::new();
+    let spawner = ex.spawner();
+
+    // Let the run queue grow to its working size first.
+    for _ in 0..4 {
+        spawner.spawn(async {}).unwrap();
+    }
+    ex.run_until_stalled();
+
+    let ran = Rc::new(Cell::new(0));
+    let before = allocations();
+    for _ in 0..4 {
+        let ran = ran.clone();
+        let large = [1u8; 256];
+        spawner
+            .spawn(async move {
+                YieldNow(false).await;
+                ran.set(ran.get() + large.len());
+            })
+            .unwrap();
+    }
+    ex.run_until_stalled();
+    assert_eq!(allocations() - before, 4);
+    assert_eq!(ran.get(), 4 * 256);
+    assert_eq!(Rc::strong_count(&ran), 1);
+}
+
+#[test]
+fn test_spawn_iter_grows_queue_once() {
+    let ex = ReferenceExecutor::new();
+    let spawner = ex.spawner();
+
+    let ran = Rc::new(Cell::new(0));
+    let before = allocations();
+    spawner
+        .spawn_iter((0..64).map(|_| {
+            let ran = ran.clone();
+            async move { ran.set(ran.get() + 1) }
+        }))
+        .unwrap();
+    // One allocation per task, plus one for the run queue.
+    assert_eq!(allocations() - before, 65);
+
+    ex.run_until_stalled();
+    assert_eq!(ran.get(), 64);
+}